@@ -15,7 +15,7 @@ fn main() {
     info!("writing asn info data to '{}' ...", path);
     let mut writer = oneio::get_writer(path).unwrap();
     let mut info_vec = as_info_map.values().collect::<Vec<_>>();
-    info_vec.sort_by(|a, b| a.asn.cmp(&b.asn));
+    info_vec.sort_by_key(|a| a.asn);
     let values_vec: Vec<Value> = info_vec.into_iter().map(|v| json!(v)).collect();
     for as_info in values_vec {
         writeln!(writer, "{}", serde_json::to_string(&as_info).unwrap()).unwrap();
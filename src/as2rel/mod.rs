@@ -4,6 +4,11 @@
 //! * [as2rel-latest.json.bz2](https://data.bgpkit.com/as2rel/as2rel-latest.json.bz2): latest combined
 //! * [as2rel-v4-latest.json.bz2](https://data.bgpkit.com/as2rel/as2rel-v4-latest.json.bz2): latest IPv4 relationship
 //! * [as2rel-v6-latest.json.bz2](https://data.bgpkit.com/as2rel/as2rel-v6-latest.json.bz2): latest IPv6 relationship
+//!
+//! Besides pairwise lookups, [`As2relBgpkit::check_valley_free`] checks a full AS path against
+//! the valley-free rule and reports the first AS leaking the route.
+
+mod valley_free;
 
 use crate::errors::{data_sources, load_methods, modules};
 use crate::{BgpkitCommons, BgpkitCommonsError, LazyLoadable, Result};
@@ -11,6 +16,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 use tracing::info;
+pub use valley_free::{HopDirection, ValleyFreeCheck, ValleyFreeHop};
 
 #[allow(dead_code)]
 const AS2REL_LATEST_COMBINED: &str = "https://data.bgpkit.com/as2rel/as2rel-latest.json.bz2";
@@ -43,9 +49,11 @@ impl<'de> Deserialize<'de> for AsRelationship {
     where
         D: serde::Deserializer<'de>,
     {
+        // `rel` of asn1 to asn2: 1 if asn1 is the provider, -1 if asn1 is the customer
         let s = i8::deserialize(deserializer)?;
         match s {
-            -1 | 1 => Ok(AsRelationship::ProviderCustomer),
+            1 => Ok(AsRelationship::ProviderCustomer),
+            -1 => Ok(AsRelationship::CustomerProvider),
             0 => Ok(AsRelationship::PeerPeer),
             _ => Err(serde::de::Error::custom("invalid relationship")),
         }
@@ -111,6 +119,10 @@ impl As2relBgpkit {
     pub fn new() -> Result<Self> {
         let v4_rels = parse_as2rel_data(AS2REL_LATEST_V4)?;
         let v6_rels = parse_as2rel_data(AS2REL_LATEST_V6)?;
+        Ok(Self::from_entries(v4_rels, v6_rels))
+    }
+
+    fn from_entries(v4_rels: Vec<As2relEntry>, v6_rels: Vec<As2relEntry>) -> Self {
         let mut v4_rels_map = HashMap::new();
         let mut v6_rels_map = HashMap::new();
         let mut v4_max_peer_count = 0;
@@ -139,12 +151,12 @@ impl As2relBgpkit {
 
             v6_max_peer_count = v6_max_peer_count.max(entry.peers_count);
         }
-        Ok(Self {
+        Self {
             v4_rels_map,
             v6_rels_map,
             v4_max_peer_count,
             v6_max_peer_count,
-        })
+        }
     }
 
     pub fn lookup_pair(
@@ -274,6 +286,18 @@ impl BgpkitCommons {
         Ok(self.as2rel.as_ref().unwrap().lookup_pair(asn1, asn2))
     }
 
+    /// Check an AS path (neighbor first, origin last) against the valley-free rule.
+    ///
+    /// See [`As2relBgpkit::check_valley_free`] for details.
+    pub fn as2rel_check_valley_free(&self, as_path: &[u32]) -> Result<ValleyFreeCheck> {
+        self.as2rel
+            .as_ref()
+            .ok_or_else(|| {
+                BgpkitCommonsError::module_not_loaded(modules::AS2REL, load_methods::LOAD_AS2REL)
+            })
+            .map(|data| data.check_valley_free(as_path))
+    }
+
     /// Returns an iterator over all unique AS relationship entries.
     ///
     /// Requires the `as2rel` module to be loaded via [`load_as2rel`](Self::load_as2rel).
@@ -0,0 +1,278 @@
+//! Valley-free (Gao-Rexford) AS path checking.
+//!
+//! An AS exports routes learned from customers to everyone, but routes
+//! learned from peers or providers only to its customers. A path that
+//! follows this rule climbs customer-to-provider links, crosses at most one
+//! peer-to-peer link and then only descends provider-to-customer links.
+//! A hop that goes up or sideways after the path has gone sideways or down is
+//! a valley; the AS sending it is leaking the route (RFC 7908 type 1 to 4
+//! leaks). [`As2relBgpkit::check_valley_free`] labels each hop with the
+//! inferred relationship and flags such hops, which is a way to screen for
+//! leaks where no ASPA records are available.
+//!
+//! Relationships are inferred, so each label carries the inference's
+//! `peers_count` and `max_peer_count`; their ratio is reported as confidence.
+
+use serde::{Deserialize, Serialize};
+
+use super::{As2relBgpkit, As2relBgpkitData, AsRelationship};
+
+/// Direction of a hop as the route propagates from the origin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HopDirection {
+    /// Customer to provider
+    Up,
+    /// Peer to peer
+    Flat,
+    /// Provider to customer
+    Down,
+    /// No relationship known
+    Unknown,
+}
+
+/// One hop of a checked AS path.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ValleyFreeHop {
+    /// The AS exporting the route
+    pub from: u32,
+    /// The AS receiving the route
+    pub to: u32,
+    /// Direction of the hop
+    pub direction: HopDirection,
+    /// The inferred relationship of `from` to `to`, with the most peers seeing it
+    pub relationship: Option<As2relBgpkitData>,
+    /// Whether this hop goes up or sideways after the path went sideways or down
+    pub violation: bool,
+}
+
+impl ValleyFreeHop {
+    /// `peers_count / max_peer_count` of the inferred relationship; `None` if unknown.
+    pub fn confidence(&self) -> Option<f64> {
+        self.relationship.map(|data| data.confidence())
+    }
+}
+
+/// Result of [`As2relBgpkit::check_valley_free`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValleyFreeCheck {
+    /// The AS path, origin first, with prepends collapsed
+    pub path: Vec<u32>,
+    /// Hops in propagation order, `hops[i]` from `path[i]` to `path[i + 1]`
+    pub hops: Vec<ValleyFreeHop>,
+    /// The AS that sent the first violating hop
+    pub first_leaker: Option<u32>,
+    /// Index in `hops` of the first violating hop
+    pub first_violation: Option<usize>,
+    /// Confidence of the first violation: the lower confidence of the violating
+    /// hop and of the hop where the path first went sideways or down
+    pub leak_confidence: Option<f64>,
+}
+
+impl ValleyFreeCheck {
+    /// Whether no hop violates the valley-free rule.
+    ///
+    /// Hops with unknown relationships are not counted as violations.
+    pub fn is_valley_free(&self) -> bool {
+        self.first_violation.is_none()
+    }
+
+    /// Indexes in `hops` of all violating hops.
+    pub fn violations(&self) -> Vec<usize> {
+        (0..self.hops.len())
+            .filter(|i| self.hops[*i].violation)
+            .collect()
+    }
+
+    /// Number of hops without a known relationship.
+    pub fn unknown_hops(&self) -> usize {
+        self.hops
+            .iter()
+            .filter(|hop| hop.direction == HopDirection::Unknown)
+            .count()
+    }
+}
+
+impl As2relBgpkitData {
+    /// `peers_count / max_peer_count`, between 0 and 1.
+    pub fn confidence(&self) -> f64 {
+        if self.max_peer_count == 0 {
+            return 0.0;
+        }
+        self.peers_count as f64 / self.max_peer_count as f64
+    }
+}
+
+impl As2relBgpkit {
+    /// The relationship of `asn1` to `asn2` seen by the most peers, over IPv4 and IPv6.
+    fn best_relationship(&self, asn1: u32, asn2: u32) -> Option<As2relBgpkitData> {
+        let (v4, v6) = self.lookup_pair(asn1, asn2);
+        v4.into_iter()
+            .chain(v6)
+            .max_by(|a, b| a.confidence().total_cmp(&b.confidence()))
+    }
+
+    /// Check an AS path against the valley-free rule using the inferred relationships.
+    ///
+    /// `as_path` is in BGP order (neighbor first, origin last). Prepends are
+    /// collapsed before checking.
+    pub fn check_valley_free(&self, as_path: &[u32]) -> ValleyFreeCheck {
+        let mut path: Vec<u32> = Vec::with_capacity(as_path.len());
+        for asn in as_path.iter().rev() {
+            if path.last() != Some(asn) {
+                path.push(*asn);
+            }
+        }
+
+        let mut check = ValleyFreeCheck {
+            path,
+            hops: vec![],
+            first_leaker: None,
+            first_violation: None,
+            leak_confidence: None,
+        };
+        // the hop where the path first went sideways or down
+        let mut turn: Option<usize> = None;
+        for (i, w) in check.path.windows(2).enumerate() {
+            let relationship = self.best_relationship(w[0], w[1]);
+            let direction = match relationship.map(|data| data.rel) {
+                Some(AsRelationship::CustomerProvider) => HopDirection::Up,
+                Some(AsRelationship::PeerPeer) => HopDirection::Flat,
+                Some(AsRelationship::ProviderCustomer) => HopDirection::Down,
+                None => HopDirection::Unknown,
+            };
+            let mut hop = ValleyFreeHop {
+                from: w[0],
+                to: w[1],
+                direction,
+                relationship,
+                violation: false,
+            };
+
+            if let Some(turn) = turn {
+                if matches!(direction, HopDirection::Up | HopDirection::Flat) {
+                    hop.violation = true;
+                    if check.first_violation.is_none() {
+                        check.first_violation = Some(i);
+                        check.first_leaker = Some(hop.from);
+                        check.leak_confidence = hop
+                            .confidence()
+                            .zip(check.hops[turn].confidence())
+                            .map(|(a, b)| a.min(b));
+                    }
+                }
+            } else if matches!(direction, HopDirection::Flat | HopDirection::Down) {
+                turn = Some(i);
+            }
+            check.hops.push(hop);
+        }
+        check
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::as2rel::As2relEntry;
+    use std::collections::{HashMap, HashSet};
+
+    /// Relationships as (asn1, asn2, rel, peers_count), with `rel` of asn1 to asn2.
+    fn as2rel(entries: &[(u32, u32, AsRelationship, u32)]) -> As2relBgpkit {
+        let mut v4_rels_map: HashMap<(u32, u32), HashSet<As2relEntry>> = HashMap::new();
+        for (asn1, asn2, rel, peers_count) in entries {
+            let entry = As2relEntry {
+                asn1: *asn1,
+                asn2: *asn2,
+                paths_count: 1,
+                peers_count: *peers_count,
+                rel: *rel,
+            };
+            v4_rels_map
+                .entry((entry.asn1, entry.asn2))
+                .or_default()
+                .insert(entry);
+            v4_rels_map
+                .entry((entry.asn2, entry.asn1))
+                .or_default()
+                .insert(entry.reverse());
+        }
+        As2relBgpkit {
+            v4_rels_map,
+            v6_rels_map: HashMap::new(),
+            v4_max_peer_count: 100,
+            v6_max_peer_count: 0,
+        }
+    }
+
+    #[test]
+    fn test_valley_free() {
+        use AsRelationship::*;
+        // 64500 and 64501 are peers, 64496..64499 are customers
+        let rels = as2rel(&[
+            (64496, 64500, CustomerProvider, 90),
+            (64500, 64501, PeerPeer, 80),
+            (64501, 64497, ProviderCustomer, 70),
+            (64497, 64502, CustomerProvider, 40),
+            (64498, 64500, CustomerProvider, 90),
+            (64498, 64501, CustomerProvider, 60),
+        ]);
+
+        // up, flat, down, with a prepend
+        let check = rels.check_valley_free(&[64497, 64501, 64500, 64496, 64496]);
+        assert_eq!(check.path, vec![64496, 64500, 64501, 64497]);
+        let directions: Vec<HopDirection> = check.hops.iter().map(|h| h.direction).collect();
+        assert_eq!(
+            directions,
+            vec![HopDirection::Up, HopDirection::Flat, HopDirection::Down]
+        );
+        assert!(check.is_valley_free());
+        assert_eq!(check.hops[0].confidence(), Some(0.9));
+
+        // 64497 leaks a route from its provider 64501 to its other provider 64502
+        let check = rels.check_valley_free(&[64502, 64497, 64501, 64500, 64496]);
+        assert!(!check.is_valley_free());
+        assert_eq!(check.first_leaker, Some(64497));
+        assert_eq!(check.first_violation, Some(3));
+        assert_eq!(check.violations(), vec![3]);
+        assert_eq!(check.leak_confidence, Some(0.4));
+
+        // 64498 leaks between its two providers
+        let check = rels.check_valley_free(&[64501, 64498, 64500, 64496]);
+        assert_eq!(check.first_leaker, Some(64498));
+        assert_eq!(check.leak_confidence, Some(0.6));
+
+        // unknown hops are not violations
+        let check = rels.check_valley_free(&[64510, 64511, 64496]);
+        assert!(check.is_valley_free());
+        assert_eq!(check.unknown_hops(), 2);
+        assert!(rels.check_valley_free(&[]).hops.is_empty());
+    }
+
+    #[test]
+    fn test_valley_free_from_json() {
+        // 64496 is a customer of 64500, 64500 a provider of 64497, 64497 a customer of 64501
+        let entries: Vec<As2relEntry> = serde_json::from_str(
+            r#"[
+                {"asn1": 64496, "asn2": 64500, "paths_count": 10, "peers_count": 50, "rel": -1},
+                {"asn1": 64500, "asn2": 64497, "paths_count": 10, "peers_count": 50, "rel": 1},
+                {"asn1": 64497, "asn2": 64501, "paths_count": 10, "peers_count": 25, "rel": -1}
+            ]"#,
+        )
+        .unwrap();
+        let rels = As2relBgpkit::from_entries(entries, vec![]);
+
+        let (v4, _) = rels.lookup_pair(64496, 64500);
+        assert_eq!(v4[0].rel, AsRelationship::CustomerProvider);
+        let (v4, _) = rels.lookup_pair(64500, 64496);
+        assert_eq!(v4[0].rel, AsRelationship::ProviderCustomer);
+
+        let check = rels.check_valley_free(&[64497, 64500, 64496]);
+        let directions: Vec<HopDirection> = check.hops.iter().map(|h| h.direction).collect();
+        assert_eq!(directions, vec![HopDirection::Up, HopDirection::Down]);
+        assert!(check.is_valley_free());
+
+        // 64497 leaks a route from its provider 64500 to its provider 64501
+        let check = rels.check_valley_free(&[64501, 64497, 64500, 64496]);
+        assert_eq!(check.first_leaker, Some(64497));
+        assert_eq!(check.leak_confidence, Some(0.5));
+    }
+}
@@ -1481,9 +1481,11 @@ ripencc|GB|asn|100|1|20200101|allocated|extra|fields|ok
             String,
             std::collections::HashMap<u32, IrrAsnInfoBuilder>,
         > = std::collections::HashMap::new();
-        let mut builder = IrrAsnInfoBuilder::default();
-        builder.source = "RIPE".to_string();
-        builder.as_name = "CLOUDFLARE-NET".to_string();
+        let builder = IrrAsnInfoBuilder {
+            source: "RIPE".to_string(),
+            as_name: "CLOUDFLARE-NET".to_string(),
+            ..Default::default()
+        };
         per_source.insert("RIPE".to_string(), [(13335, builder)].into_iter().collect());
 
         let ripe = crate::irr::sources::all_sources()
@@ -158,9 +158,32 @@ fn main() {
 
     // ---- Optional: RPKI ----
     if cli.with_rpki {
-        tracing::info!("exporting RPKI ROAs + ASPAs...");
-        tracing::warn!("RPKI export is not yet implemented in this PR");
-        failures.push("rpki: not yet implemented".to_string());
+        tracing::info!("loading rpki...");
+        match commons.load_rpki(None) {
+            Ok(()) => {
+                export_source("rpki_roas", &mut exported, &mut failures, || {
+                    export::rpki_roas(output_dir, &commons)
+                });
+                export_source("rpki_aspas", &mut exported, &mut failures, || {
+                    export::rpki_aspas(output_dir, &commons)
+                });
+                tracing::info!("writing rpki.json and rpki_vrps.csv...");
+                match write_rpki_files(output_dir, &commons) {
+                    Ok(()) => {
+                        exported.push("rpki.json".to_string());
+                        exported.push("rpki_vrps.csv".to_string());
+                    }
+                    Err(e) => {
+                        tracing::error!("failed to write RPKI files: {e}");
+                        failures.push(format!("rpki files: {e}"));
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::warn!("failed to load rpki: {e}");
+                failures.push(format!("rpki: {e}"));
+            }
+        }
     }
 
     // ---- Write manifest ----
@@ -202,6 +225,8 @@ fn export_source(
                 "mrt_collectors" => "mrt_collectors.parquet",
                 "as_relationships" => "as_relationships.parquet",
                 "rir_delegated" => "rir_delegated.parquet",
+                "rpki_roas" => "rpki_roas.parquet",
+                "rpki_aspas" => "rpki_aspas.parquet",
                 _ => name,
             };
             exported.push(filename.to_string());
@@ -236,6 +261,18 @@ fn write_asninfo_jsonl(
     Ok(())
 }
 
+fn write_rpki_files(
+    dir: &std::path::Path,
+    commons: &BgpkitCommons,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let trie = commons.rpki_trie()?;
+    let json = std::io::BufWriter::new(std::fs::File::create(dir.join("rpki.json"))?);
+    trie.write_rpki_client_json(json)?;
+    let csv = std::io::BufWriter::new(std::fs::File::create(dir.join("rpki_vrps.csv"))?);
+    trie.write_vrp_csv(csv)?;
+    Ok(())
+}
+
 fn build_manifest(exported: &[String], failures: &[String]) -> serde_json::Value {
     use serde_json::json;
     let now = chrono::Utc::now();
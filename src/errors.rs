@@ -32,6 +32,14 @@ pub enum BgpkitCommonsError {
         details: String,
     },
 
+    /// Error when a data source is reachable but has no data to serve yet,
+    /// e.g. an RTR cache that is still validating
+    #[error("{data_source} has no data available yet, retry in {} seconds", retry_after.as_secs())]
+    DataNotAvailable {
+        data_source: String,
+        retry_after: std::time::Duration,
+    },
+
     /// Error when input data format is invalid
     #[error("Invalid {data_type} format '{input}': {reason}")]
     InvalidFormat {
@@ -113,6 +121,14 @@ impl BgpkitCommonsError {
         }
     }
 
+    /// Create a data not available error
+    pub fn data_not_available(source: impl Into<String>, retry_after: std::time::Duration) -> Self {
+        Self::DataNotAvailable {
+            data_source: source.into(),
+            retry_after,
+        }
+    }
+
     /// Create an invalid format error
     pub fn invalid_format(
         data_type: &'static str,
@@ -170,4 +186,5 @@ pub mod data_sources {
     pub const PEERINGDB: &str = "PeeringDB";
     pub const APNIC: &str = "APNIC";
     pub const IIJ_IHR: &str = "IIJ IHR";
+    pub const RTR: &str = "RTR";
 }
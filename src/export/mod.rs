@@ -17,11 +17,11 @@ use std::path::Path;
 use std::sync::Arc;
 
 use arrow::array::{
-    ArrayRef, BooleanArray, Date32Array, ListArray, RecordBatch, StringArray, UInt8Array,
-    UInt32Array,
+    ArrayRef, BooleanArray, Date32Array, ListArray, RecordBatch, StringArray, TimestampSecondArray,
+    UInt8Array, UInt32Array,
 };
 use arrow::buffer::{NullBuffer, OffsetBuffer};
-use arrow::datatypes::{DataType, Field, Schema};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
 use parquet::arrow::ArrowWriter;
 
 use crate::BgpkitCommons;
@@ -92,6 +92,39 @@ fn date32_array(data: Vec<Option<i32>>) -> ArrayRef {
     Arc::new(Date32Array::from(data))
 }
 
+fn timestamp_array(data: Vec<Option<i64>>) -> ArrayRef {
+    Arc::new(TimestampSecondArray::from(data).with_timezone("UTC"))
+}
+
+fn timestamp_field(name: &str, nullable: bool) -> Field {
+    Field::new(
+        name,
+        DataType::Timestamp(TimeUnit::Second, Some("UTC".into())),
+        nullable,
+    )
+}
+
+fn list_u32_field(name: &str, nullable: bool) -> Field {
+    Field::new(
+        name,
+        DataType::List(Arc::new(Field::new("item", DataType::UInt32, true))),
+        nullable,
+    )
+}
+
+fn build_u32_list(data: &[Vec<u32>]) -> ArrayRef {
+    let mut values: Vec<u32> = Vec::new();
+    let mut offsets: Vec<i32> = vec![0];
+    for list in data {
+        values.extend_from_slice(list);
+        offsets.push(values.len() as i32);
+    }
+    let field = Arc::new(Field::new("item", DataType::UInt32, true));
+    let offsets = OffsetBuffer::new(offsets.into());
+    let values_arr: ArrayRef = Arc::new(UInt32Array::from(values));
+    Arc::new(ListArray::new(field, offsets, values_arr, None))
+}
+
 fn list_string_field(name: &str, nullable: bool) -> Field {
     Field::new(
         name,
@@ -377,6 +410,109 @@ pub fn iana_bogons(dir: impl AsRef<Path>, commons: &BgpkitCommons) -> WriteResul
     }
 }
 
+/// Export the ROAs of the loaded RPKI data to `<dir>/rpki_roas.parquet`.
+///
+/// One row per VRP, sorted by prefix, origin ASN and max-length, with the
+/// RIR the ROA was validated under and its validity period.
+pub fn rpki_roas(dir: impl AsRef<Path>, commons: &BgpkitCommons) -> WriteResult {
+    #[cfg(feature = "rpki")]
+    {
+        let trie = commons
+            .rpki_trie()
+            .map_err(|_| ExportError::ModuleNotLoaded("rpki".into()))?;
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("prefix", DataType::Utf8, false),
+            Field::new("asn", DataType::UInt32, false),
+            Field::new("max_length", DataType::UInt8, false),
+            Field::new("rir", DataType::Utf8, true),
+            timestamp_field("not_before", true),
+            timestamp_field("not_after", true),
+        ]));
+
+        let mut prefixes = Vec::new();
+        let mut asns = Vec::new();
+        let mut max_lengths = Vec::new();
+        let mut rirs = Vec::new();
+        let mut not_befores = Vec::new();
+        let mut not_afters = Vec::new();
+
+        for roa in trie.sorted_roas() {
+            prefixes.push(roa.prefix.to_string());
+            asns.push(roa.asn);
+            max_lengths.push(roa.max_length);
+            rirs.push(roa.rir.map(|r| r.to_string()));
+            not_befores.push(roa.not_before.map(|t| t.and_utc().timestamp()));
+            not_afters.push(roa.not_after.map(|t| t.and_utc().timestamp()));
+        }
+
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                string_array_nn(prefixes),
+                u32_array_nn(asns),
+                u8_array_nn(max_lengths),
+                string_array(rirs),
+                timestamp_array(not_befores),
+                timestamp_array(not_afters),
+            ],
+        )?;
+
+        write_parquet(dir.as_ref().join("rpki_roas.parquet"), batch)?;
+        Ok(())
+    }
+    #[cfg(not(feature = "rpki"))]
+    {
+        let _ = (dir, commons);
+        Err(ExportError::ModuleNotLoaded("rpki".into()))
+    }
+}
+
+/// Export the ASPAs of the loaded RPKI data to `<dir>/rpki_aspas.parquet`.
+pub fn rpki_aspas(dir: impl AsRef<Path>, commons: &BgpkitCommons) -> WriteResult {
+    #[cfg(feature = "rpki")]
+    {
+        let trie = commons
+            .rpki_trie()
+            .map_err(|_| ExportError::ModuleNotLoaded("rpki".into()))?;
+
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("customer_asn", DataType::UInt32, false),
+            list_u32_field("providers", false),
+            timestamp_field("expires", true),
+        ]));
+
+        let mut customers = Vec::new();
+        let mut providers_list = Vec::new();
+        let mut expires = Vec::new();
+
+        for aspa in trie.sorted_aspas() {
+            customers.push(aspa.customer_asn);
+            providers_list.push(aspa.providers.clone());
+            expires.push(aspa.expires.map(|t| t.and_utc().timestamp()));
+        }
+
+        let providers_arr = build_u32_list(&providers_list);
+
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                u32_array_nn(customers),
+                providers_arr,
+                timestamp_array(expires),
+            ],
+        )?;
+
+        write_parquet(dir.as_ref().join("rpki_aspas.parquet"), batch)?;
+        Ok(())
+    }
+    #[cfg(not(feature = "rpki"))]
+    {
+        let _ = (dir, commons);
+        Err(ExportError::ModuleNotLoaded("rpki".into()))
+    }
+}
+
 /// Export AS relationship data to `<dir>/as_relationships.parquet`.
 pub fn as_relationships(dir: impl AsRef<Path>, commons: &BgpkitCommons) -> WriteResult {
     let schema = Arc::new(Schema::new(vec![
@@ -46,7 +46,7 @@
 //! Feature: `as2rel` | Source: BGPKIT AS relationship inference
 //!
 //! - Load: `load_as2rel()`
-//! - Access: `as2rel_lookup(asn1, asn2)`
+//! - Access: `as2rel_lookup(asn1, asn2)`, `as2rel_check_valley_free(as_path)`
 //! - Provider-customer, peer-to-peer, and sibling relationships between ASes
 //!
 //! ### [`bogons`] — Bogon Detection
@@ -85,16 +85,20 @@
 //!
 //! ### [`rpki`] — RPKI Validation
 //!
-//! Feature: `rpki` | Sources: Cloudflare (real-time), RIPE NCC historical, RPKIviews historical, RPKISPOOL historical
+//! Feature: `rpki` | Sources: Cloudflare (real-time), RIPE NCC historical, RPKIviews historical, RPKISPOOL historical, local RTR cache
 //!
-//! - Load: `load_rpki(optional_date)`, `load_rpki_historical(date, source)`, `load_rpki_from_files(urls, source, date)`
+//! - Load: `load_rpki(optional_date)`, `load_rpki_historical(date, source)`, `load_rpki_historical_failover(date, sources)`, `load_rpki_rpkispools_at(collector, time)`, `load_rpki_from_files(urls, format, date)`, `load_rpki_from_rtr(addr)`
 //! - Poll: `RpkiTrie::from_cloudflare_conditional(etag, last_modified)` returns `Ok(None)` on `304 Not Modified`
+//! - RTR: `rpki::RtrClient` keeps a trie in sync with a validator cache via Serial Notify/Serial Query; `rpki::RtrServer` serves a trie to routers
 //! - Access: `rpki_validate(asn, prefix)`, `rpki_validate_check_expiry(asn, prefix, timestamp)`, `rpki_lookup_by_prefix(prefix)`, `rpki_lookup_aspa(customer_asn)`
+//! - ASPA: `rpki_verify_aspa_path(as_path, direction)` verifies a full AS_PATH (upstream or downstream)
 //! - Route Origin Authorization (ROA) and ASPA validation, supports real-time and historical sources
 //! - Poll current Cloudflare data with `RpkiTrie::from_cloudflare_conditional`, retaining the returned
 //!   [`rpki::RpkiLoad`] validators and keeping the existing trie when the result is `Ok(None)`.
-//! - `BgpkitCommons::reload()` performs a full reload; it does not use validators or provide an atomic
-//!   poll-and-swap operation.
+//! - `BgpkitCommons::reload()` calls `RpkiTrie::reload()`, which reloads from the source recorded in
+//!   `RpkiTrie::provenance()`: Cloudflare tries send a conditional request with the stored
+//!   ETag/Last-Modified and keep the data on `304 Not Modified`, and tries built in memory
+//!   (`RpkiSource::Manual`) return an error. `RpkiTrie::reload_with_diff()` also reports the changes.
 //!
 //! ## Examples
 //!
@@ -383,13 +387,14 @@ impl BgpkitCommons {
 
     /// Load RPKI data from a specific historical data source
     ///
-    /// This allows you to choose between RIPE NCC and RPKIviews for historical data.
+    /// This allows you to choose between RIPE NCC, RPKIviews, RPKISPOOL and a
+    /// local mirror of one of them for historical data.
     ///
     /// # Example
     ///
     /// ```rust,no_run
     /// use bgpkit_commons::BgpkitCommons;
-    /// use bgpkit_commons::rpki::{HistoricalRpkiSource, RpkiViewsCollector};
+    /// use bgpkit_commons::rpki::{HistoricalRpkiSource, LocalRpkiArchive, RpkiViewsCollector};
     /// use chrono::NaiveDate;
     ///
     /// let mut commons = BgpkitCommons::new();
@@ -401,6 +406,10 @@ impl BgpkitCommons {
     /// // Or load from RPKIviews
     /// let source = HistoricalRpkiSource::RpkiViews(RpkiViewsCollector::KerfuffleNet);
     /// commons.load_rpki_historical(date, source).unwrap();
+    ///
+    /// // Or from a local mirror of the RIPE NCC ftp tree, without network access
+    /// let source = HistoricalRpkiSource::Local(LocalRpkiArchive::ripe("/data/ftp.ripe.net/rpki"));
+    /// commons.load_rpki_historical(date, source).unwrap();
     /// ```
     #[cfg(feature = "rpki")]
     pub fn load_rpki_historical(
@@ -408,36 +417,92 @@ impl BgpkitCommons {
         date: chrono::NaiveDate,
         source: rpki::HistoricalRpkiSource,
     ) -> Result<()> {
-        match source {
-            rpki::HistoricalRpkiSource::Ripe => {
-                self.rpki_trie = Some(rpki::RpkiTrie::from_ripe_historical(date)?);
-            }
-            rpki::HistoricalRpkiSource::RpkiViews(collector) => {
-                self.rpki_trie = Some(rpki::RpkiTrie::from_rpkiviews(collector, date)?);
-            }
-            rpki::HistoricalRpkiSource::RpkiSpools(collector) => {
-                self.rpki_trie = Some(rpki::RpkiTrie::from_rpkispools(collector, date)?);
-            }
-        }
+        self.rpki_trie = Some(rpki::RpkiTrie::from_historical(&source, date)?);
+        Ok(())
+    }
+
+    /// Load historical RPKI data from the first of several sources that loads
+    ///
+    /// Sources are tried in order, so a collector that is down or has no
+    /// data for the date falls back to the next one.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use bgpkit_commons::BgpkitCommons;
+    /// use bgpkit_commons::rpki::{HistoricalRpkiSource, RpkiViewsCollector};
+    /// use chrono::NaiveDate;
+    ///
+    /// let mut commons = BgpkitCommons::new();
+    /// let date = NaiveDate::from_ymd_opt(2024, 1, 4).unwrap();
+    /// let sources: Vec<_> = RpkiViewsCollector::all()
+    ///     .into_iter()
+    ///     .map(HistoricalRpkiSource::RpkiViews)
+    ///     .collect();
+    /// commons.load_rpki_historical_failover(date, &sources).unwrap();
+    /// ```
+    #[cfg(feature = "rpki")]
+    pub fn load_rpki_historical_failover(
+        &mut self,
+        date: chrono::NaiveDate,
+        sources: &[rpki::HistoricalRpkiSource],
+    ) -> Result<()> {
+        self.rpki_trie = Some(rpki::RpkiTrie::from_historical_failover(sources, date)?);
+        Ok(())
+    }
+
+    /// Load the RPKISPOOL state closest to a point in time
+    ///
+    /// RPKISPOOL archives hold many CCR states per day. This streams the
+    /// archive of the day of `time` and loads the state produced closest to
+    /// it, for correlating BGP updates with RPKI state at minute granularity.
+    /// `vantage_point` selects the validator whose states are used; `None`
+    /// uses the vantage point of the first CCR in the archive.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use bgpkit_commons::BgpkitCommons;
+    /// use bgpkit_commons::rpki::RpkiSpoolsCollector;
+    /// use chrono::{TimeZone, Utc};
+    ///
+    /// let mut commons = BgpkitCommons::new();
+    /// let time = Utc.with_ymd_and_hms(2026, 3, 20, 14, 32, 0).unwrap();
+    /// commons.load_rpki_rpkispools_at(RpkiSpoolsCollector::default(), time, None).unwrap();
+    /// ```
+    #[cfg(feature = "rpki")]
+    pub fn load_rpki_rpkispools_at(
+        &mut self,
+        collector: rpki::RpkiSpoolsCollector,
+        time: chrono::DateTime<chrono::Utc>,
+        vantage_point: Option<&str>,
+    ) -> Result<()> {
+        self.rpki_trie = Some(rpki::RpkiTrie::from_rpkispools_at(
+            collector,
+            time,
+            vantage_point,
+        )?);
         Ok(())
     }
 
-    /// Load RPKI data from specific file URLs
+    /// Load RPKI data from specific file URLs or local paths
     ///
     /// This allows loading from specific archive files, which is useful when you want
-    /// to process multiple files or use specific timestamps.
+    /// to process multiple files or use specific timestamps, and from the output of
+    /// a local validator such as Routinator, Fort or OctoRPKI.
     ///
     /// # Arguments
     ///
-    /// * `urls` - A slice of URLs pointing to RPKI data files
-    /// * `source` - The type of data source (RIPE, RPKIviews, or RPKISPOOL) - determines how files are parsed
+    /// * `urls` - A slice of URLs or paths pointing to RPKI data files
+    /// * `format` - How the files are parsed: a [`rpki::RpkiFileFormat`], or a
+    ///   [`rpki::HistoricalRpkiSource`] (RIPE, RPKIviews, or RPKISPOOL) for the archive format it publishes
     /// * `date` - Optional date to associate with the loaded data
     ///
     /// # Example
     ///
     /// ```rust,no_run
     /// use bgpkit_commons::BgpkitCommons;
-    /// use bgpkit_commons::rpki::HistoricalRpkiSource;
+    /// use bgpkit_commons::rpki::{HistoricalRpkiSource, RpkiFileFormat};
     ///
     /// let mut commons = BgpkitCommons::new();
     /// let urls = vec![
@@ -446,28 +511,37 @@ impl BgpkitCommons {
     /// commons.load_rpki_from_files(&urls, HistoricalRpkiSource::RpkiViews(
     ///     bgpkit_commons::rpki::RpkiViewsCollector::KerfuffleNet
     /// ), None).unwrap();
+    ///
+    /// let urls = vec!["/var/lib/routinator/vrps.csv".to_string()];
+    /// commons.load_rpki_from_files(&urls, RpkiFileFormat::RoutinatorCsv, None).unwrap();
     /// ```
     #[cfg(feature = "rpki")]
     pub fn load_rpki_from_files(
         &mut self,
         urls: &[String],
-        source: rpki::HistoricalRpkiSource,
+        format: impl Into<rpki::RpkiFileFormat>,
         date: Option<chrono::NaiveDate>,
     ) -> Result<()> {
-        match source {
-            rpki::HistoricalRpkiSource::Ripe => {
-                self.rpki_trie = Some(rpki::RpkiTrie::from_ripe_files(urls, date)?);
-            }
-            rpki::HistoricalRpkiSource::RpkiViews(_) => {
-                self.rpki_trie = Some(rpki::RpkiTrie::from_rpkiviews_files(urls, date)?);
-            }
-            rpki::HistoricalRpkiSource::RpkiSpools(_) => {
-                // For RPKISPOOL, each URL is a tar.zst archive; load the first one
-                if let Some(url) = urls.first() {
-                    self.rpki_trie = Some(rpki::RpkiTrie::from_rpkispools_url(url, date)?);
-                }
-            }
-        }
+        self.rpki_trie = Some(rpki::RpkiTrie::from_files(urls, format.into(), date)?);
+        Ok(())
+    }
+
+    /// Load RPKI data from a local validator cache over the RPKI-to-Router protocol
+    ///
+    /// Connects to the cache, performs a full synchronization and closes the
+    /// connection. To follow incremental updates, use [`rpki::RtrClient`] directly.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use bgpkit_commons::BgpkitCommons;
+    ///
+    /// let mut commons = BgpkitCommons::new();
+    /// commons.load_rpki_from_rtr("127.0.0.1:3323").unwrap();
+    /// ```
+    #[cfg(feature = "rpki")]
+    pub fn load_rpki_from_rtr(&mut self, addr: impl std::net::ToSocketAddrs) -> Result<()> {
+        self.rpki_trie = Some(rpki::RpkiTrie::from_rtr(addr)?);
         Ok(())
     }
 
@@ -504,6 +578,7 @@ impl BgpkitCommons {
             rpki::HistoricalRpkiSource::RpkiSpools(collector) => {
                 rpki::list_rpkispools_files(collector, date)
             }
+            rpki::HistoricalRpkiSource::Local(archive) => archive.list_files(date),
         }
     }
 
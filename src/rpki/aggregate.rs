@@ -0,0 +1,228 @@
+//! VRP aggregation.
+//!
+//! [`RpkiTrie::aggregate_vrps`] reduces the ROAs of a trie to the smallest
+//! set of VRPs that gives the same [`RpkiTrie::validate`] result for every
+//! route, similar to rpki-client's `-A` option:
+//!
+//! - a VRP is dropped if another VRP of the same ASN covers its prefix with
+//!   an equal or longer max-length (including the same prefix with a longer
+//!   max-length);
+//! - a VRP whose two halves both have VRPs of the same ASN gets its
+//!   max-length raised to the shorter of theirs, which makes the halves
+//!   redundant. Every prefix the raised max-length adds already lies in one
+//!   of the halves, so no route changes state. A VRP with a max-length shorter
+//!   than its prefix matches no route and is never widened.
+//!
+//! Aggregation only looks at prefix, ASN and max-length. Kept VRPs keep their
+//! own RIR and validity period, so results under
+//! [`RpkiTrie::validate_check_expiry`] or a trust-anchor policy can differ
+//! from the original trie.
+
+use std::collections::{HashMap, HashSet};
+
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+
+use super::{Roa, RpkiTrie};
+
+/// What [`RpkiTrie::aggregate_vrps`] changed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VrpAggregationReport {
+    /// VRPs before aggregation
+    pub input_vrps: usize,
+    /// VRPs after aggregation
+    pub output_vrps: usize,
+    /// Kept VRPs whose max-length was raised to absorb more-specifics
+    pub widened_vrps: usize,
+}
+
+impl VrpAggregationReport {
+    /// Number of VRPs removed.
+    pub fn saved_vrps(&self) -> usize {
+        self.input_vrps - self.output_vrps
+    }
+
+    /// Share of VRPs removed, between 0 and 1.
+    pub fn savings_ratio(&self) -> f64 {
+        if self.input_vrps == 0 {
+            return 0.0;
+        }
+        self.saved_vrps() as f64 / self.input_vrps as f64
+    }
+}
+
+impl RpkiTrie {
+    /// Replace the ROAs with the smallest equivalent VRP set; see the [module documentation](self).
+    pub fn aggregate_vrps(&mut self) -> VrpAggregationReport {
+        let roas: Vec<Roa> = self
+            .trie
+            .iter()
+            .flat_map(|(_, roas)| roas.iter().cloned())
+            .collect();
+        let mut report = VrpAggregationReport {
+            input_vrps: roas.len(),
+            ..Default::default()
+        };
+
+        // longest max-length per (ASN, prefix)
+        let mut by_asn: HashMap<u32, HashMap<IpNet, u8>> = HashMap::new();
+        for roa in &roas {
+            let max_length = by_asn
+                .entry(roa.asn)
+                .or_default()
+                .entry(roa.prefix)
+                .or_insert(roa.max_length);
+            *max_length = (*max_length).max(roa.max_length);
+        }
+
+        let mut kept: HashMap<(u32, IpNet), u8> = HashMap::new();
+        let mut widened: HashSet<(u32, IpNet)> = HashSet::new();
+        for (asn, mut vrps) in by_asn {
+            // longest prefixes first, so halves are final before their parent
+            let mut prefixes: Vec<IpNet> = vrps.keys().copied().collect();
+            prefixes.sort_by_key(|p| std::cmp::Reverse(p.prefix_len()));
+            for prefix in prefixes {
+                let Ok(mut halves) = prefix.subnets(prefix.prefix_len() + 1) else {
+                    continue;
+                };
+                let (Some(a), Some(b)) = (halves.next(), halves.next()) else {
+                    continue;
+                };
+                let (Some(max_a), Some(max_b)) = (vrps.get(&a), vrps.get(&b)) else {
+                    continue;
+                };
+                // a max-length shorter than the prefix matches no route; widening
+                // it would make routes for the prefix itself valid
+                let current = vrps[&prefix];
+                if current < prefix.prefix_len() {
+                    continue;
+                }
+                let max_length = *max_a.min(max_b);
+                if max_length > current {
+                    vrps.insert(prefix, max_length);
+                    widened.insert((asn, prefix));
+                }
+            }
+
+            for (prefix, max_length) in &vrps {
+                let covered = std::iter::successors(prefix.supernet(), |p| p.supernet())
+                    .any(|p| vrps.get(&p).is_some_and(|max| max >= max_length));
+                if !covered {
+                    kept.insert((asn, *prefix), *max_length);
+                }
+            }
+        }
+
+        let mut widened_roas: HashMap<(u32, IpNet), Roa> = HashMap::new();
+        for roa in roas {
+            let key = (roa.asn, roa.prefix);
+            if kept.get(&key) == Some(&roa.max_length) {
+                continue;
+            }
+            self.remove_roa(&roa.prefix, roa.asn, roa.max_length);
+            if let Some(max_length) = kept.get(&key).filter(|_| widened.contains(&key)) {
+                widened_roas.entry(key).or_insert(Roa {
+                    max_length: *max_length,
+                    ..roa
+                });
+            }
+        }
+        report.widened_vrps = widened_roas.len();
+        self.insert_roas(widened_roas.into_values().collect());
+
+        report.output_vrps = self.trie.iter().map(|(_, roas)| roas.len()).sum();
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::{RpkiValidation, roa};
+
+    #[test]
+    fn test_aggregate_vrps() {
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roas(vec![
+            // same prefix, shorter max-length is redundant
+            roa("10.0.0.0/16", 64496, 16),
+            roa("10.0.0.0/16", 64496, 20),
+            // covered by the /16-20
+            roa("10.0.16.0/20", 64496, 20),
+            // other ASN, kept
+            roa("10.0.16.0/20", 64497, 20),
+            // both halves present: the /23 is widened to 24
+            roa("192.0.2.0/23", 64496, 23),
+            roa("192.0.2.0/24", 64496, 24),
+            roa("192.0.3.0/24", 64496, 24),
+            // only one half present: nothing to merge
+            roa("198.51.100.0/23", 64496, 23),
+            roa("198.51.100.0/24", 64496, 24),
+            roa("2001:db8::/32", 64496, 48),
+        ]);
+        let routes: Vec<(IpNet, u32)> = [
+            "10.0.0.0/16",
+            "10.0.16.0/20",
+            "10.0.16.0/21",
+            "192.0.2.0/23",
+            "192.0.2.0/24",
+            "192.0.3.0/24",
+            "192.0.2.0/25",
+            "198.51.100.0/23",
+            "198.51.100.0/24",
+            "198.51.101.0/24",
+            "2001:db8:1::/48",
+            "203.0.113.0/24",
+        ]
+        .iter()
+        .flat_map(|p| {
+            let prefix: IpNet = p.parse().unwrap();
+            [(prefix, 64496), (prefix, 64497)]
+        })
+        .collect();
+        let before: Vec<RpkiValidation> = routes
+            .iter()
+            .map(|(prefix, asn)| trie.validate(prefix, *asn))
+            .collect();
+
+        let report = trie.aggregate_vrps();
+        assert_eq!(report.input_vrps, 10);
+        assert_eq!(report.output_vrps, 6);
+        assert_eq!(report.widened_vrps, 1);
+        assert_eq!(report.saved_vrps(), 4);
+        assert!((report.savings_ratio() - 0.4).abs() < 1e-9);
+
+        let after: Vec<RpkiValidation> = routes
+            .iter()
+            .map(|(prefix, asn)| trie.validate(prefix, *asn))
+            .collect();
+        assert_eq!(after, before);
+        let widened = trie.lookup_by_prefix(&"192.0.2.0/24".parse().unwrap());
+        assert_eq!(widened.len(), 1);
+        assert_eq!(widened[0].prefix.to_string(), "192.0.2.0/23");
+        assert_eq!(widened[0].max_length, 24);
+
+        // aggregating again changes nothing
+        let report = trie.aggregate_vrps();
+        assert_eq!(report.saved_vrps(), 0);
+        assert_eq!(report.widened_vrps, 0);
+    }
+
+    #[test]
+    fn test_aggregate_malformed_max_length() {
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roas(vec![
+            // max-length shorter than the prefix: matches nothing
+            roa("192.0.2.0/23", 64496, 22),
+            roa("192.0.2.0/24", 64496, 24),
+            roa("192.0.3.0/24", 64496, 24),
+        ]);
+        let route: IpNet = "192.0.2.0/23".parse().unwrap();
+        assert_eq!(trie.validate(&route, 64496), RpkiValidation::Invalid);
+
+        let report = trie.aggregate_vrps();
+        assert_eq!(report.widened_vrps, 0);
+        assert_eq!(report.output_vrps, 3);
+        assert_eq!(trie.validate(&route, 64496), RpkiValidation::Invalid);
+    }
+}
@@ -0,0 +1,356 @@
+//! ASPA-based AS_PATH verification.
+//!
+//! Implements the AS_PATH verification procedures from
+//! [draft-ietf-sidrops-aspa-verification]. Given the AS_PATH of a received
+//! route and the direction it was received from, the verification decides
+//! whether the path is consistent with the registered ASPA records and can
+//! be used to flag route leaks.
+//!
+//! # Terminology
+//!
+//! - **Upstream** paths are received from a customer or a lateral peer
+//!   (peer, route server, route server client). Every hop must be a
+//!   customer-to-provider hop.
+//! - **Downstream** paths are received from a provider. The path may consist
+//!   of an up-ramp (customer-to-provider hops starting at the origin) followed
+//!   by a down-ramp (provider-to-customer hops ending at the neighbor), with
+//!   at most one lateral peering hop at the apex.
+//!
+//! Paths are given in the order they appear in the BGP `AS_PATH` attribute:
+//! the neighbor AS first and the origin AS last. Consecutive duplicate ASNs
+//! (prepends) are collapsed before verification.
+//!
+//! [draft-ietf-sidrops-aspa-verification]: https://datatracker.ietf.org/doc/draft-ietf-sidrops-aspa-verification/
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use super::{Aspa, RpkiTrie};
+
+/// Direction from which a route was received, relative to the verifying AS.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AspaDirection {
+    /// Route received from a customer or a lateral peer (including route servers)
+    Upstream,
+    /// Route received from a provider
+    Downstream,
+}
+
+/// Result of checking a single customer-to-provider hop against ASPA records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AspaHopCheck {
+    /// The customer has an ASPA and lists the given AS as a provider
+    ProviderPlus,
+    /// The customer has an ASPA that does not list the given AS as a provider
+    NotProviderPlus,
+    /// The customer has no ASPA record
+    NoAttestation,
+}
+
+/// ASPA AS_PATH verification outcome.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AspaValidation {
+    /// The AS_PATH is consistent with all registered ASPAs
+    Valid,
+    /// At least one hop is proven to violate a registered ASPA (route leak)
+    Invalid,
+    /// The AS_PATH cannot be verified because some ASes have no ASPA
+    Unknown,
+}
+
+impl Display for AspaValidation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AspaValidation::Valid => write!(f, "valid"),
+            AspaValidation::Invalid => write!(f, "invalid"),
+            AspaValidation::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// A single hop of an AS_PATH checked in the customer-to-provider direction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AspaHop {
+    /// The AS expected to be the customer in this hop
+    pub customer: u32,
+    /// The AS expected to be the provider in this hop
+    pub provider: u32,
+    /// Outcome of the hop check
+    pub check: AspaHopCheck,
+}
+
+/// Detailed result of an ASPA AS_PATH verification.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AspaVerification {
+    /// Overall verification state
+    pub state: AspaValidation,
+    /// Direction the path was verified for
+    pub direction: AspaDirection,
+    /// The AS_PATH after prepend collapsing, origin first
+    pub path: Vec<u32>,
+    /// Length of the longest up-ramp that is not proven invalid, counted in ASes from the origin
+    pub max_up_ramp: usize,
+    /// Length of the longest up-ramp fully attested by ASPAs
+    pub min_up_ramp: usize,
+    /// Length of the longest down-ramp that is not proven invalid, counted in ASes from the neighbor
+    pub max_down_ramp: usize,
+    /// Length of the longest down-ramp fully attested by ASPAs
+    pub min_down_ramp: usize,
+    /// The hop that made the path Invalid or Unknown, if any
+    pub failed_hop: Option<AspaHop>,
+}
+
+impl RpkiTrie {
+    /// Check whether `provider` is an authorized provider of `customer`.
+    ///
+    /// Implements the `hop(AS(i), AS(j))` function of the ASPA verification draft.
+    pub fn aspa_hop_check(&self, customer: u32, provider: u32) -> AspaHopCheck {
+        hop_check(self.lookup_aspa(customer), provider)
+    }
+
+    /// Verify an AS_PATH against the ASPA records in this trie.
+    ///
+    /// `as_path` is in BGP order (neighbor first, origin last). Prepends are
+    /// collapsed before verification. An empty path yields
+    /// [`AspaValidation::Unknown`].
+    pub fn verify_aspa_path(&self, as_path: &[u32], direction: AspaDirection) -> AspaVerification {
+        verify_path(as_path, direction, |customer, provider| {
+            self.aspa_hop_check(customer, provider)
+        })
+    }
+}
+
+/// The hop check of `provider` against the customer's ASPA, if any.
+pub(super) fn hop_check(aspa: Option<&Aspa>, provider: u32) -> AspaHopCheck {
+    match aspa {
+        None => AspaHopCheck::NoAttestation,
+        Some(aspa) if aspa.providers.contains(&provider) => AspaHopCheck::ProviderPlus,
+        Some(_) => AspaHopCheck::NotProviderPlus,
+    }
+}
+
+/// Verify an AS_PATH with the given `hop(customer, provider)` function.
+pub(super) fn verify_path(
+    as_path: &[u32],
+    direction: AspaDirection,
+    hop_check: impl Fn(u32, u32) -> AspaHopCheck,
+) -> AspaVerification {
+    // Collapse prepends and reverse to origin-first order: path[0] = AS(1)
+    let mut path: Vec<u32> = Vec::with_capacity(as_path.len());
+    for asn in as_path.iter().rev() {
+        if path.last() != Some(asn) {
+            path.push(*asn);
+        }
+    }
+    let n = path.len();
+
+    if n == 0 {
+        return AspaVerification {
+            state: AspaValidation::Unknown,
+            direction,
+            path,
+            max_up_ramp: 0,
+            min_up_ramp: 0,
+            max_down_ramp: 0,
+            min_down_ramp: 0,
+            failed_hop: None,
+        };
+    }
+
+    // Hops in the up-ramp direction: (AS(i), AS(i+1)) for i in 1..N
+    let up_hops: Vec<AspaHop> = path
+        .windows(2)
+        .map(|w| AspaHop {
+            customer: w[0],
+            provider: w[1],
+            check: hop_check(w[0], w[1]),
+        })
+        .collect();
+    // Hops in the down-ramp direction: (AS(j+1), AS(j)) walking from the neighbor
+    let down_hops: Vec<AspaHop> = path
+        .windows(2)
+        .rev()
+        .map(|w| AspaHop {
+            customer: w[1],
+            provider: w[0],
+            check: hop_check(w[1], w[0]),
+        })
+        .collect();
+
+    let ramp = |hops: &[AspaHop], accept: fn(AspaHopCheck) -> bool| -> usize {
+        1 + hops.iter().take_while(|h| accept(h.check)).count()
+    };
+    let not_invalid = |c: AspaHopCheck| c != AspaHopCheck::NotProviderPlus;
+    let attested = |c: AspaHopCheck| c == AspaHopCheck::ProviderPlus;
+
+    let max_up_ramp = ramp(&up_hops, not_invalid);
+    let min_up_ramp = ramp(&up_hops, attested);
+    let (max_down_ramp, min_down_ramp) = match direction {
+        AspaDirection::Upstream => (0, 0),
+        AspaDirection::Downstream => (ramp(&down_hops, not_invalid), ramp(&down_hops, attested)),
+    };
+
+    let (state, failed_hop) = match direction {
+        AspaDirection::Upstream => {
+            if max_up_ramp < n {
+                (AspaValidation::Invalid, Some(up_hops[max_up_ramp - 1]))
+            } else if min_up_ramp < n {
+                (AspaValidation::Unknown, Some(up_hops[min_up_ramp - 1]))
+            } else {
+                (AspaValidation::Valid, None)
+            }
+        }
+        AspaDirection::Downstream => {
+            if n <= 2 {
+                (AspaValidation::Valid, None)
+            } else if max_up_ramp + max_down_ramp < n {
+                // The first proven violation past the up-ramp
+                (AspaValidation::Invalid, Some(up_hops[max_up_ramp - 1]))
+            } else if min_up_ramp + min_down_ramp < n {
+                (AspaValidation::Unknown, Some(up_hops[min_up_ramp - 1]))
+            } else {
+                (AspaValidation::Valid, None)
+            }
+        }
+    };
+
+    AspaVerification {
+        state,
+        direction,
+        path,
+        max_up_ramp,
+        min_up_ramp,
+        max_down_ramp,
+        min_down_ramp,
+        failed_hop,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trie_with_aspas(aspas: &[(u32, &[u32])]) -> RpkiTrie {
+        let mut trie = RpkiTrie::new(None);
+        for (customer, providers) in aspas {
+            trie.insert_aspa(Aspa {
+                customer_asn: *customer,
+                providers: providers.to_vec(),
+                expires: None,
+            });
+        }
+        trie
+    }
+
+    #[test]
+    fn test_hop_check() {
+        let trie = trie_with_aspas(&[(64500, &[64510, 64511]), (64501, &[0])]);
+        assert_eq!(
+            trie.aspa_hop_check(64500, 64510),
+            AspaHopCheck::ProviderPlus
+        );
+        assert_eq!(
+            trie.aspa_hop_check(64500, 64999),
+            AspaHopCheck::NotProviderPlus
+        );
+        // AS0 ASPA: no providers at all
+        assert_eq!(
+            trie.aspa_hop_check(64501, 64510),
+            AspaHopCheck::NotProviderPlus
+        );
+        assert_eq!(
+            trie.aspa_hop_check(64502, 64510),
+            AspaHopCheck::NoAttestation
+        );
+    }
+
+    #[test]
+    fn test_upstream_verification() {
+        // 64500 -> 64510 -> 64520 customer-to-provider chain
+        let trie = trie_with_aspas(&[(64500, &[64510]), (64510, &[64520])]);
+
+        // BGP order: neighbor first, origin last; prepends are collapsed
+        let result = trie.verify_aspa_path(&[64520, 64510, 64500, 64500], AspaDirection::Upstream);
+        assert_eq!(result.state, AspaValidation::Valid);
+        assert_eq!(result.path, vec![64500, 64510, 64520]);
+        assert_eq!(result.failed_hop, None);
+
+        // 64520 has no ASPA: unknown at the last hop
+        let result = trie.verify_aspa_path(&[64530, 64520, 64510, 64500], AspaDirection::Upstream);
+        assert_eq!(result.state, AspaValidation::Unknown);
+        assert_eq!(result.failed_hop.unwrap().customer, 64520);
+
+        // 64599 passes the route of its peer 64500 up to its provider 64510,
+        // but 64500 does not list 64599 as a provider
+        let result = trie.verify_aspa_path(&[64520, 64510, 64599, 64500], AspaDirection::Upstream);
+        assert_eq!(result.state, AspaValidation::Invalid);
+        let hop = result.failed_hop.unwrap();
+        assert_eq!((hop.customer, hop.provider), (64500, 64599));
+        assert_eq!(hop.check, AspaHopCheck::NotProviderPlus);
+
+        // A single-AS path is always valid
+        let result = trie.verify_aspa_path(&[64999], AspaDirection::Upstream);
+        assert_eq!(result.state, AspaValidation::Valid);
+    }
+
+    #[test]
+    fn test_downstream_verification() {
+        // Up-ramp: 64500 -> 64510 -> 64520, down-ramp: 64520 -> 64530 -> 64540
+        let trie = trie_with_aspas(&[
+            (64500, &[64510]),
+            (64510, &[64520]),
+            (64530, &[64520]),
+            (64540, &[64530]),
+        ]);
+
+        let path = [64540, 64530, 64520, 64510, 64500];
+        let result = trie.verify_aspa_path(&path, AspaDirection::Downstream);
+        assert_eq!(result.state, AspaValidation::Valid);
+        assert_eq!(result.min_up_ramp, 3);
+        assert_eq!(result.min_down_ramp, 3);
+
+        // Lateral peering at the apex (64520 <-> 64525) is still valid
+        let trie = trie_with_aspas(&[
+            (64500, &[64510]),
+            (64510, &[64520]),
+            (64530, &[64525]),
+            (64540, &[64530]),
+        ]);
+        let path = [64540, 64530, 64525, 64520, 64510, 64500];
+        let result = trie.verify_aspa_path(&path, AspaDirection::Downstream);
+        assert_eq!(result.state, AspaValidation::Valid);
+
+        // Valley: 64510 learns the route from provider 64520 and leaks it to
+        // its other provider 64521
+        let trie = trie_with_aspas(&[
+            (64500, &[64520]),
+            (64510, &[64520, 64521]),
+            (64520, &[64999]),
+            (64521, &[64999]),
+            (64530, &[64521]),
+        ]);
+        let path = [64530, 64521, 64510, 64520, 64500];
+        let result = trie.verify_aspa_path(&path, AspaDirection::Downstream);
+        assert_eq!(result.state, AspaValidation::Invalid);
+        let hop = result.failed_hop.unwrap();
+        assert_eq!((hop.customer, hop.provider), (64520, 64510));
+
+        // Missing attestations on the down-ramp make the path unknown
+        let trie = trie_with_aspas(&[(64500, &[64510])]);
+        let path = [64540, 64530, 64510, 64500];
+        let result = trie.verify_aspa_path(&path, AspaDirection::Downstream);
+        assert_eq!(result.state, AspaValidation::Unknown);
+
+        // Paths of length two or less are always valid downstream
+        let result = trie.verify_aspa_path(&[64530, 64999], AspaDirection::Downstream);
+        assert_eq!(result.state, AspaValidation::Valid);
+    }
+
+    #[test]
+    fn test_empty_path() {
+        let trie = trie_with_aspas(&[]);
+        let result = trie.verify_aspa_path(&[], AspaDirection::Upstream);
+        assert_eq!(result.state, AspaValidation::Unknown);
+        assert!(result.path.is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+//! Route origin validation of large batches of routes across threads.
+//!
+//! The trie is read-only during validation, so the batch is split into
+//! contiguous chunks that are validated on scoped threads sharing the same
+//! trie. Results keep the input order.
+
+use std::num::NonZeroUsize;
+
+use chrono::{NaiveDateTime, Utc};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+
+use super::{RpkiTrie, RpkiValidation};
+
+/// Batches smaller than this are validated on the calling thread.
+const MIN_ROUTES_PER_THREAD: usize = 1024;
+
+/// Counts of validation states in a batch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpkiBulkSummary {
+    /// Number of routes validated
+    pub total: usize,
+    /// Routes with state `Valid`
+    pub valid: usize,
+    /// Routes with state `Invalid`
+    pub invalid: usize,
+    /// Routes with state `Unknown` (not covered by any ROA)
+    pub unknown: usize,
+}
+
+impl RpkiBulkSummary {
+    fn add(&mut self, state: &RpkiValidation) {
+        self.total += 1;
+        match state {
+            RpkiValidation::Valid => self.valid += 1,
+            RpkiValidation::Invalid => self.invalid += 1,
+            RpkiValidation::Unknown => self.unknown += 1,
+        }
+    }
+}
+
+/// Result of validating a batch of routes.
+#[derive(Clone, Debug)]
+pub struct RpkiBulkValidation {
+    /// One state per input route, in input order
+    pub results: Vec<RpkiValidation>,
+    /// Counts over all results
+    pub summary: RpkiBulkSummary,
+}
+
+impl RpkiTrie {
+    /// Validate many (prefix, origin ASN) pairs using all available CPU cores.
+    ///
+    /// Returns the same states as calling [`RpkiTrie::validate`] on each pair.
+    pub fn validate_bulk<I>(&self, routes: I) -> RpkiBulkValidation
+    where
+        I: IntoIterator<Item = (IpNet, u32)>,
+    {
+        let threads = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        self.validate_bulk_inner(routes, threads, None)
+    }
+
+    /// Validate many (prefix, origin ASN) pairs with ROA expiry checking, using all available CPU cores.
+    ///
+    /// Returns the same states as calling [`RpkiTrie::validate_check_expiry`]
+    /// on each pair with the same `check_time` (default: now).
+    pub fn validate_bulk_check_expiry<I>(
+        &self,
+        routes: I,
+        check_time: Option<NaiveDateTime>,
+    ) -> RpkiBulkValidation
+    where
+        I: IntoIterator<Item = (IpNet, u32)>,
+    {
+        let threads = std::thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let check_time = check_time.unwrap_or_else(|| Utc::now().naive_utc());
+        self.validate_bulk_inner(routes, threads, Some(check_time))
+    }
+
+    /// Validate many (prefix, origin ASN) pairs on at most `threads` threads.
+    pub fn validate_bulk_with_threads<I>(&self, routes: I, threads: usize) -> RpkiBulkValidation
+    where
+        I: IntoIterator<Item = (IpNet, u32)>,
+    {
+        self.validate_bulk_inner(routes, threads, None)
+    }
+
+    fn validate_bulk_inner<I>(
+        &self,
+        routes: I,
+        threads: usize,
+        check_time: Option<NaiveDateTime>,
+    ) -> RpkiBulkValidation
+    where
+        I: IntoIterator<Item = (IpNet, u32)>,
+    {
+        let routes: Vec<(IpNet, u32)> = routes.into_iter().collect();
+        let threads = threads.min(routes.len() / MIN_ROUTES_PER_THREAD).max(1);
+        let validate_chunk = |chunk: &[(IpNet, u32)]| -> Vec<RpkiValidation> {
+            chunk
+                .iter()
+                .map(|(prefix, asn)| self.validation_state(prefix, *asn, check_time))
+                .collect()
+        };
+
+        let results = if threads == 1 {
+            validate_chunk(&routes)
+        } else {
+            let chunk_size = routes.len().div_ceil(threads);
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = routes
+                    .chunks(chunk_size)
+                    .map(|chunk| scope.spawn(move || validate_chunk(chunk)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .flat_map(|handle| handle.join().expect("validation thread panicked"))
+                    .collect()
+            })
+        };
+
+        let mut summary = RpkiBulkSummary::default();
+        for state in &results {
+            summary.add(state);
+        }
+        RpkiBulkValidation { results, summary }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::Roa;
+
+    #[test]
+    fn test_validate_bulk_matches_validate() {
+        let mut trie = RpkiTrie::new(None);
+        for i in 0..=255u8 {
+            trie.insert_roa(Roa {
+                prefix: format!("10.{}.0.0/16", i).parse().unwrap(),
+                asn: 64496 + (i % 4) as u32,
+                max_length: 20,
+                rir: None,
+                not_before: None,
+                not_after: None,
+            });
+        }
+        // 10.x.y.0/20 for every x and four origins, plus uncovered routes
+        let mut routes = vec![];
+        for x in 0..=255u32 {
+            for y in (0..=255u32).step_by(16) {
+                let prefix: IpNet = format!("10.{}.{}.0/20", x, y).parse().unwrap();
+                routes.push((prefix, 64496 + (x + y / 16) % 4));
+            }
+            routes.push((format!("192.0.{}.0/24", x).parse().unwrap(), 64496));
+        }
+
+        let bulk = trie.validate_bulk_with_threads(routes.iter().copied(), 4);
+        assert_eq!(bulk.results.len(), routes.len());
+        for ((prefix, asn), state) in routes.iter().zip(&bulk.results) {
+            assert_eq!(*state, trie.validate_detailed(prefix, *asn).state);
+        }
+        assert_eq!(bulk.summary.total, routes.len());
+        assert_eq!(bulk.summary.unknown, 256);
+        assert_eq!(bulk.summary.valid, 256 * 16 / 4);
+        assert_eq!(
+            bulk.summary.valid + bulk.summary.invalid + bulk.summary.unknown,
+            bulk.summary.total
+        );
+
+        let serial = trie.validate_bulk_with_threads(routes.iter().copied(), 1);
+        assert_eq!(serial.results, bulk.results);
+        assert_eq!(serial.summary, bulk.summary);
+    }
+}
@@ -3,8 +3,8 @@
 use crate::errors::data_sources;
 use crate::{BgpkitCommonsError, Result};
 
-use super::RpkiTrie;
 use super::rpki_client::RpkiClientData;
+use super::{RpkiProvenance, RpkiSource, RpkiTrie};
 
 const CLOUDFLARE_RPKI_URL: &str = "https://rpki.cloudflare.com/rpki.json";
 
@@ -90,7 +90,15 @@ impl RpkiTrie {
         else {
             return Ok(None);
         };
-        let trie = Self::from_rpki_client_data(fetch.data, None)?;
+        let mut trie = Self::from_rpki_client_data(fetch.data, None)?;
+        trie.provenance = RpkiProvenance {
+            source: RpkiSource::Cloudflare,
+            urls: vec![CLOUDFLARE_RPKI_URL.to_string()],
+            etag: fetch.etag.clone(),
+            last_modified: fetch.last_modified.clone(),
+            time: None,
+            vantage_point: None,
+        };
         Ok(Some(RpkiLoad {
             trie,
             etag: fetch.etag,
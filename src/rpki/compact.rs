@@ -0,0 +1,415 @@
+//! A compact, read-only representation of an [`RpkiTrie`].
+//!
+//! [`CompactRpkiTrie`] stores each prefix once in the trie, pointing at a
+//! range of a flat ROA array. A ROA takes 12 bytes: origin ASN, max-length
+//! and an index into a table of interned (RIR, validity period) tuples,
+//! which are shared by the many ROAs published under the same certificate.
+//! ASPAs are kept as sorted customer ASNs with their provider lists packed
+//! into one array. Lookups return borrowed [`RoaRef`]s instead of cloned
+//! [`Roa`]s, and validation gives the same results as [`RpkiTrie::validate`]
+//! and [`RpkiTrie::validate_check_expiry`].
+
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use ipnet::IpNet;
+use ipnet_trie::IpnetTrie;
+
+use super::policy::{RovRoa, validate_origin};
+use super::{Aspa, Rir, Roa, RpkiTrie, RpkiValidation, RpkiValidationPolicy};
+
+/// Metadata shared by ROAs: trust anchor and validity period.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RoaMeta {
+    /// Regional Internet Registry (trust anchor)
+    pub rir: Option<Rir>,
+    /// ROA validity start time
+    pub not_before: Option<NaiveDateTime>,
+    /// ROA validity end time
+    pub not_after: Option<NaiveDateTime>,
+}
+
+impl RoaMeta {
+    /// Whether `time` is within the validity period; unset bounds are open.
+    pub fn is_valid_at(&self, time: NaiveDateTime) -> bool {
+        self.not_before.is_none_or(|not_before| time >= not_before)
+            && self.not_after.is_none_or(|not_after| time <= not_after)
+    }
+}
+
+/// A ROA borrowed from a [`CompactRpkiTrie`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RoaRef<'a> {
+    /// The ROA prefix
+    pub prefix: IpNet,
+    /// The authorized origin ASN
+    pub asn: u32,
+    /// Maximum prefix length
+    pub max_length: u8,
+    /// Trust anchor and validity period
+    pub meta: &'a RoaMeta,
+}
+
+impl RovRoa for RoaRef<'_> {
+    fn asn(&self) -> u32 {
+        self.asn
+    }
+
+    fn max_length(&self) -> u8 {
+        self.max_length
+    }
+
+    fn rir(&self) -> Option<Rir> {
+        self.meta.rir
+    }
+
+    fn is_valid_at(&self, time: NaiveDateTime) -> bool {
+        self.meta.is_valid_at(time)
+    }
+}
+
+impl RoaRef<'_> {
+    /// An owned copy of the ROA.
+    pub fn to_roa(&self) -> Roa {
+        Roa {
+            prefix: self.prefix,
+            asn: self.asn,
+            max_length: self.max_length,
+            rir: self.meta.rir,
+            not_before: self.meta.not_before,
+            not_after: self.meta.not_after,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct PackedRoa {
+    asn: u32,
+    meta: u32,
+    max_length: u8,
+}
+
+/// Position of the ROAs of one prefix in [`CompactRpkiTrie::roas`].
+#[derive(Clone, Copy, Debug)]
+struct RoaRange {
+    start: u32,
+    len: u32,
+}
+
+/// A read-only RPKI trie with a compact memory layout.
+///
+/// Build it with [`RpkiTrie::to_compact`]; convert back with [`CompactRpkiTrie::to_trie`].
+#[derive(Clone)]
+pub struct CompactRpkiTrie {
+    date: Option<NaiveDate>,
+    trie: IpnetTrie<RoaRange>,
+    roas: Vec<PackedRoa>,
+    metas: Vec<RoaMeta>,
+    aspa_customers: Vec<u32>,
+    /// `aspa_providers[aspa_offsets[i]..aspa_offsets[i + 1]]` are the providers of `aspa_customers[i]`
+    aspa_offsets: Vec<u32>,
+    aspa_providers: Vec<u32>,
+    aspa_expires: Vec<Option<NaiveDateTime>>,
+}
+
+impl From<&RpkiTrie> for CompactRpkiTrie {
+    fn from(trie: &RpkiTrie) -> Self {
+        let mut compact = CompactRpkiTrie {
+            date: trie.date,
+            trie: IpnetTrie::new(),
+            roas: vec![],
+            metas: vec![],
+            aspa_customers: vec![],
+            aspa_offsets: vec![0],
+            aspa_providers: vec![],
+            aspa_expires: vec![],
+        };
+
+        let mut meta_ids: HashMap<RoaMeta, u32> = HashMap::new();
+        for (prefix, roas) in trie.trie.iter() {
+            if roas.is_empty() {
+                continue;
+            }
+            let start = compact.roas.len() as u32;
+            for roa in roas {
+                let meta = RoaMeta {
+                    rir: roa.rir,
+                    not_before: roa.not_before,
+                    not_after: roa.not_after,
+                };
+                let meta = *meta_ids.entry(meta).or_insert_with(|| {
+                    compact.metas.push(meta);
+                    (compact.metas.len() - 1) as u32
+                });
+                compact.roas.push(PackedRoa {
+                    asn: roa.asn,
+                    meta,
+                    max_length: roa.max_length,
+                });
+            }
+            compact.trie.insert(
+                prefix,
+                RoaRange {
+                    start,
+                    len: roas.len() as u32,
+                },
+            );
+        }
+        compact.roas.shrink_to_fit();
+        compact.metas.shrink_to_fit();
+
+        let mut aspas: Vec<&Aspa> = trie.aspas.iter().collect();
+        aspas.sort_by_key(|aspa| aspa.customer_asn);
+        for aspa in aspas {
+            compact.aspa_customers.push(aspa.customer_asn);
+            compact.aspa_providers.extend_from_slice(&aspa.providers);
+            compact
+                .aspa_offsets
+                .push(compact.aspa_providers.len() as u32);
+            compact.aspa_expires.push(aspa.expires);
+        }
+        compact
+    }
+}
+
+impl RpkiTrie {
+    /// A compact read-only copy of this trie's ROAs and ASPAs.
+    pub fn to_compact(&self) -> CompactRpkiTrie {
+        CompactRpkiTrie::from(self)
+    }
+}
+
+impl CompactRpkiTrie {
+    /// The date of the data, if historical.
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.date
+    }
+
+    /// Number of ROAs.
+    pub fn roa_count(&self) -> usize {
+        self.roas.len()
+    }
+
+    /// Number of distinct (RIR, validity period) tuples.
+    pub fn meta_count(&self) -> usize {
+        self.metas.len()
+    }
+
+    /// Number of ASPAs.
+    pub fn aspa_count(&self) -> usize {
+        self.aspa_customers.len()
+    }
+
+    fn roas_of(&self, prefix: IpNet, range: &RoaRange) -> impl Iterator<Item = RoaRef<'_>> {
+        let start = range.start as usize;
+        self.roas[start..start + range.len as usize]
+            .iter()
+            .map(move |roa| RoaRef {
+                prefix,
+                asn: roa.asn,
+                max_length: roa.max_length,
+                meta: &self.metas[roa.meta as usize],
+            })
+    }
+
+    /// All ROAs.
+    pub fn roas(&self) -> impl Iterator<Item = RoaRef<'_>> {
+        self.trie
+            .iter()
+            .flat_map(|(prefix, range)| self.roas_of(prefix, range))
+    }
+
+    /// ROAs whose prefix covers `prefix`, regardless of max-length.
+    pub fn covering_roas(&self, prefix: &IpNet) -> impl Iterator<Item = RoaRef<'_>> {
+        let prefix = *prefix;
+        self.trie
+            .matches(&prefix)
+            .into_iter()
+            .filter(move |(p, _)| p.contains(&prefix))
+            .flat_map(|(p, range)| self.roas_of(p, range))
+    }
+
+    /// ROAs that authorize `prefix` (covering it with a sufficient max-length),
+    /// the same ROAs [`RpkiTrie::lookup_by_prefix`] returns.
+    pub fn lookup_by_prefix(&self, prefix: &IpNet) -> impl Iterator<Item = RoaRef<'_>> {
+        let len = prefix.prefix_len();
+        self.covering_roas(prefix)
+            .filter(move |roa| roa.max_length >= len)
+    }
+
+    /// Validate a prefix with an ASN; same result as [`RpkiTrie::validate`].
+    pub fn validate(&self, prefix: &IpNet, asn: u32) -> RpkiValidation {
+        self.validation_state(prefix, asn, None)
+    }
+
+    /// Validate a prefix with an ASN, checking ROA validity periods at
+    /// `check_time` (default: now); same result as [`RpkiTrie::validate_check_expiry`].
+    pub fn validate_check_expiry(
+        &self,
+        prefix: &IpNet,
+        asn: u32,
+        check_time: Option<NaiveDateTime>,
+    ) -> RpkiValidation {
+        let check_time = check_time.unwrap_or_else(|| Utc::now().naive_utc());
+        self.validation_state(prefix, asn, Some(check_time))
+    }
+
+    fn validation_state(
+        &self,
+        prefix: &IpNet,
+        asn: u32,
+        check_time: Option<NaiveDateTime>,
+    ) -> RpkiValidation {
+        let policy = RpkiValidationPolicy::check_expiry(check_time);
+        validate_origin(self.covering_roas(prefix), prefix, asn, &policy, |_| {}).0
+    }
+
+    /// The providers in the ASPA of a customer ASN.
+    pub fn lookup_aspa_providers(&self, customer_asn: u32) -> Option<&[u32]> {
+        let i = self.aspa_customers.binary_search(&customer_asn).ok()?;
+        Some(self.aspa_providers_at(i))
+    }
+
+    fn aspa_providers_at(&self, i: usize) -> &[u32] {
+        &self.aspa_providers[self.aspa_offsets[i] as usize..self.aspa_offsets[i + 1] as usize]
+    }
+
+    /// All ASPAs as (customer ASN, providers, expiry), sorted by customer ASN.
+    pub fn aspas(&self) -> impl Iterator<Item = (u32, &[u32], Option<NaiveDateTime>)> {
+        (0..self.aspa_customers.len()).map(|i| {
+            (
+                self.aspa_customers[i],
+                self.aspa_providers_at(i),
+                self.aspa_expires[i],
+            )
+        })
+    }
+
+    /// An [`RpkiTrie`] with the same ROAs and ASPAs.
+    pub fn to_trie(&self) -> RpkiTrie {
+        let mut trie = RpkiTrie::new(self.date);
+        trie.insert_roas(self.roas().map(|roa| roa.to_roa()).collect());
+        for (customer_asn, providers, expires) in self.aspas() {
+            trie.insert_aspa(Aspa {
+                customer_asn,
+                providers: providers.to_vec(),
+                expires,
+            });
+        }
+        trie
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+
+    fn sample_trie() -> RpkiTrie {
+        let past = DateTime::from_timestamp(1_600_000_000, 0).map(|t| t.naive_utc());
+        let future = DateTime::from_timestamp(4_000_000_000, 0).map(|t| t.naive_utc());
+        let mut trie = RpkiTrie::new(None);
+        for i in 0..64u32 {
+            trie.insert_roa(Roa {
+                prefix: format!("10.{}.0.0/16", i).parse().unwrap(),
+                asn: 64496 + i % 3,
+                max_length: 16 + (i % 9) as u8,
+                rir: Some(Rir::ARIN),
+                not_before: past,
+                // every fourth ROA has expired
+                not_after: if i % 4 == 0 { past } else { future },
+            });
+        }
+        trie.insert_roa(Roa {
+            prefix: "10.0.0.0/8".parse().unwrap(),
+            asn: 0,
+            max_length: 8,
+            rir: None,
+            not_before: None,
+            not_after: None,
+        });
+        trie.insert_roa(Roa {
+            prefix: "2001:db8::/32".parse().unwrap(),
+            asn: 64496,
+            max_length: 48,
+            rir: Some(Rir::RIPENCC),
+            not_before: None,
+            not_after: None,
+        });
+        trie.insert_aspa(Aspa {
+            customer_asn: 64497,
+            providers: vec![64511, 64510],
+            expires: future,
+        });
+        trie.insert_aspa(Aspa {
+            customer_asn: 64496,
+            providers: vec![64510],
+            expires: None,
+        });
+        trie
+    }
+
+    #[test]
+    fn test_compact_validation_matches() {
+        let trie = sample_trie();
+        let compact = trie.to_compact();
+        assert_eq!(compact.roa_count(), 66);
+        assert_eq!(compact.meta_count(), 4);
+
+        let now = Utc::now().naive_utc();
+        let mut routes: Vec<(IpNet, u32)> = vec![];
+        for i in 0..70u32 {
+            for len in [16, 20, 24] {
+                for asn in [0, 64496, 64497, 64498] {
+                    routes.push((format!("10.{}.0.0/{}", i, len).parse().unwrap(), asn));
+                }
+            }
+        }
+        routes.push(("2001:db8:1::/48".parse().unwrap(), 64496));
+        routes.push(("2001:db8:1::/56".parse().unwrap(), 64496));
+        routes.push(("192.0.2.0/24".parse().unwrap(), 64496));
+
+        for (prefix, asn) in &routes {
+            assert_eq!(compact.validate(prefix, *asn), trie.validate(prefix, *asn));
+            assert_eq!(
+                compact.validate_check_expiry(prefix, *asn, Some(now)),
+                trie.validate_check_expiry(prefix, *asn, Some(now))
+            );
+            let mut expected: Vec<(IpNet, u32, u8)> = trie
+                .lookup_by_prefix(prefix)
+                .iter()
+                .map(|r| (r.prefix, r.asn, r.max_length))
+                .collect();
+            let mut found: Vec<(IpNet, u32, u8)> = compact
+                .lookup_by_prefix(prefix)
+                .map(|r| (r.prefix, r.asn, r.max_length))
+                .collect();
+            expected.sort();
+            found.sort();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn test_compact_aspas_and_roundtrip() {
+        let trie = sample_trie();
+        let compact = trie.to_compact();
+        assert_eq!(compact.aspa_count(), 2);
+        assert_eq!(
+            compact.lookup_aspa_providers(64497),
+            Some(&[64511, 64510][..])
+        );
+        assert_eq!(compact.lookup_aspa_providers(64496), Some(&[64510][..]));
+        assert_eq!(compact.lookup_aspa_providers(64498), None);
+        assert_eq!(compact.aspas().next().unwrap().0, 64496);
+
+        let restored = compact.to_trie();
+        assert!(trie.diff(&restored).is_empty());
+        let roa = compact
+            .lookup_by_prefix(&"2001:db8::/40".parse().unwrap())
+            .next()
+            .unwrap();
+        assert_eq!(roa.meta.rir, Some(Rir::RIPENCC));
+        assert_eq!(roa.to_roa().max_length, 48);
+    }
+}
@@ -0,0 +1,314 @@
+//! Loading historical RPKI data from several sources.
+//!
+//! The public archives are published by several independent collectors, each
+//! running its own validator. [`RpkiTrie::from_historical_failover`] tries
+//! sources in order and returns the first that loads, so a collector outage
+//! does not stop an analysis. [`rpki_consensus`] loads all of them and tags
+//! every ROA and ASPA with the vantage points that saw it; entries not seen
+//! everywhere point at validator-side anomalies such as a stale repository
+//! fetch or a validator bug.
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use ipnet::IpNet;
+use tracing::{info, warn};
+
+use super::{Aspa, HistoricalRpkiSource, Roa, RpkiTrie};
+use crate::{BgpkitCommonsError, Result};
+
+/// A ROA and the vantage points that saw it.
+#[derive(Clone, Debug)]
+pub struct RoaObservation {
+    /// The ROA, with the metadata of the first vantage point that saw it
+    pub roa: Roa,
+    /// Indexes into [`RpkiConsensus::vantage_points`]
+    pub seen_by: Vec<usize>,
+}
+
+/// An ASPA and the vantage points that saw it.
+///
+/// The same customer ASN with different provider sets gives one observation per provider set.
+#[derive(Clone, Debug)]
+pub struct AspaObservation {
+    /// The ASPA, with providers sorted
+    pub aspa: Aspa,
+    /// Indexes into [`RpkiConsensus::vantage_points`]
+    pub seen_by: Vec<usize>,
+}
+
+/// ROAs and ASPAs of several vantage points, tagged with who saw them.
+#[derive(Clone, Debug, Default)]
+pub struct RpkiConsensus {
+    /// The date of the data, taken from the first vantage point
+    pub date: Option<NaiveDate>,
+    /// Names of the vantage points that loaded
+    pub vantage_points: Vec<String>,
+    /// Vantage points that failed to load, with the error
+    pub failures: Vec<(String, String)>,
+    /// All ROAs, sorted by prefix, origin ASN and max-length
+    pub roas: Vec<RoaObservation>,
+    /// All ASPAs, sorted by customer ASN
+    pub aspas: Vec<AspaObservation>,
+}
+
+impl RpkiConsensus {
+    /// Compare tries loaded from different vantage points, given as (name, trie) pairs.
+    pub fn from_tries(tries: Vec<(String, RpkiTrie)>) -> Self {
+        let mut consensus = RpkiConsensus {
+            date: tries.first().and_then(|(_, trie)| trie.date),
+            ..Default::default()
+        };
+        let mut roas: BTreeMap<(IpNet, u32, u8), RoaObservation> = BTreeMap::new();
+        let mut aspas: BTreeMap<(u32, Vec<u32>), AspaObservation> = BTreeMap::new();
+
+        for (index, (name, trie)) in tries.into_iter().enumerate() {
+            consensus.vantage_points.push(name);
+            for (_, trie_roas) in trie.trie.iter() {
+                for roa in trie_roas {
+                    let observation = roas
+                        .entry((roa.prefix, roa.asn, roa.max_length))
+                        .or_insert_with(|| RoaObservation {
+                            roa: roa.clone(),
+                            seen_by: vec![],
+                        });
+                    // a vantage point may list the same VRP under several TAs
+                    if observation.seen_by.last() != Some(&index) {
+                        observation.seen_by.push(index);
+                    }
+                }
+            }
+            for mut aspa in trie.aspas {
+                aspa.providers.sort_unstable();
+                aspa.providers.dedup();
+                let observation = aspas
+                    .entry((aspa.customer_asn, aspa.providers.clone()))
+                    .or_insert_with(|| AspaObservation {
+                        aspa,
+                        seen_by: vec![],
+                    });
+                if observation.seen_by.last() != Some(&index) {
+                    observation.seen_by.push(index);
+                }
+            }
+        }
+
+        consensus.roas = roas.into_values().collect();
+        consensus.aspas = aspas.into_values().collect();
+        consensus
+    }
+
+    /// ROAs not seen by every vantage point.
+    pub fn roa_disagreements(&self) -> Vec<&RoaObservation> {
+        self.roas
+            .iter()
+            .filter(|o| o.seen_by.len() < self.vantage_points.len())
+            .collect()
+    }
+
+    /// ASPAs (customer and provider set) not seen by every vantage point.
+    pub fn aspa_disagreements(&self) -> Vec<&AspaObservation> {
+        self.aspas
+            .iter()
+            .filter(|o| o.seen_by.len() < self.vantage_points.len())
+            .collect()
+    }
+
+    /// Whether all vantage points saw exactly the same ROAs and ASPAs.
+    pub fn is_unanimous(&self) -> bool {
+        self.roa_disagreements().is_empty() && self.aspa_disagreements().is_empty()
+    }
+
+    /// Build a trie from the entries seen by at least `min_vantage_points` vantage points.
+    ///
+    /// If several provider sets of one customer ASN qualify, the one seen by
+    /// the most vantage points is used.
+    pub fn to_trie(&self, min_vantage_points: usize) -> RpkiTrie {
+        let mut trie = RpkiTrie::new(self.date);
+        trie.insert_roas(
+            self.roas
+                .iter()
+                .filter(|o| o.seen_by.len() >= min_vantage_points)
+                .map(|o| o.roa.clone())
+                .collect(),
+        );
+        let mut aspas: Vec<&AspaObservation> = self
+            .aspas
+            .iter()
+            .filter(|o| o.seen_by.len() >= min_vantage_points)
+            .collect();
+        aspas.sort_by_key(|o| std::cmp::Reverse(o.seen_by.len()));
+        for observation in aspas {
+            if trie.lookup_aspa(observation.aspa.customer_asn).is_none() {
+                trie.insert_aspa(observation.aspa.clone());
+            }
+        }
+        trie
+    }
+}
+
+impl RpkiTrie {
+    /// Load RPKI data for a date from the first of `sources` that loads.
+    ///
+    /// Sources are tried in order; a failing source is logged and the next
+    /// one tried. The error lists the failure of every source.
+    pub fn from_historical_failover(
+        sources: &[HistoricalRpkiSource],
+        date: NaiveDate,
+    ) -> Result<Self> {
+        let mut failures = vec![];
+        for source in sources {
+            match RpkiTrie::from_historical(source, date) {
+                Ok(trie) => {
+                    info!("loaded RPKI data for {} from {}", date, source);
+                    return Ok(trie);
+                }
+                Err(e) => {
+                    warn!("failed to load RPKI data from {}: {}", source, e);
+                    failures.push(format!("{}: {}", source, e));
+                }
+            }
+        }
+        Err(BgpkitCommonsError::data_source_error(
+            "RPKI",
+            format!(
+                "no source could be loaded for {}: [{}]",
+                date,
+                failures.join("; ")
+            ),
+        ))
+    }
+}
+
+/// Load RPKI data for a date from every source and compare them.
+///
+/// Sources that fail to load are recorded in [`RpkiConsensus::failures`];
+/// it is an error only if none loads.
+pub fn rpki_consensus(sources: &[HistoricalRpkiSource], date: NaiveDate) -> Result<RpkiConsensus> {
+    let mut tries = vec![];
+    let mut failures = vec![];
+    for source in sources {
+        match RpkiTrie::from_historical(source, date) {
+            Ok(trie) => tries.push((source.to_string(), trie)),
+            Err(e) => {
+                warn!("failed to load RPKI data from {}: {}", source, e);
+                failures.push((source.to_string(), e.to_string()));
+            }
+        }
+    }
+    if tries.is_empty() {
+        return Err(BgpkitCommonsError::data_source_error(
+            "RPKI",
+            format!("no source could be loaded for {}", date),
+        ));
+    }
+    let mut consensus = RpkiConsensus::from_tries(tries);
+    consensus.failures = failures;
+    Ok(consensus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::{LocalRpkiArchive, roa};
+
+    fn trie(roas: Vec<Roa>, providers: Vec<u32>) -> RpkiTrie {
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roas(roas);
+        trie.insert_aspa(Aspa {
+            customer_asn: 64496,
+            providers,
+            expires: None,
+        });
+        trie
+    }
+
+    #[test]
+    fn test_consensus() {
+        let consensus = RpkiConsensus::from_tries(vec![
+            (
+                "a".to_string(),
+                trie(
+                    vec![
+                        roa("192.0.2.0/24", 64496, 24),
+                        roa("198.51.100.0/24", 64497, 24),
+                    ],
+                    vec![64511, 64510],
+                ),
+            ),
+            (
+                "b".to_string(),
+                trie(vec![roa("192.0.2.0/24", 64496, 24)], vec![64510, 64511]),
+            ),
+            (
+                "c".to_string(),
+                trie(vec![roa("192.0.2.0/24", 64496, 24)], vec![64510]),
+            ),
+        ]);
+
+        assert_eq!(consensus.vantage_points, vec!["a", "b", "c"]);
+        assert_eq!(consensus.roas.len(), 2);
+        assert_eq!(consensus.roas[0].seen_by, vec![0, 1, 2]);
+        let roa_disagreements = consensus.roa_disagreements();
+        assert_eq!(roa_disagreements.len(), 1);
+        assert_eq!(roa_disagreements[0].roa.asn, 64497);
+        assert_eq!(roa_disagreements[0].seen_by, vec![0]);
+
+        // provider order does not matter, provider sets do
+        assert_eq!(consensus.aspas.len(), 2);
+        let aspa_disagreements = consensus.aspa_disagreements();
+        assert_eq!(aspa_disagreements.len(), 2);
+        assert_eq!(aspa_disagreements[0].aspa.providers, vec![64510]);
+        assert_eq!(aspa_disagreements[1].seen_by, vec![0, 1]);
+        assert!(!consensus.is_unanimous());
+
+        let majority = consensus.to_trie(2);
+        assert_eq!(majority.lookup_roas_by_asn(64496).len(), 1);
+        assert!(majority.lookup_roas_by_asn(64497).is_empty());
+        assert_eq!(
+            majority.lookup_aspa(64496).unwrap().providers,
+            vec![64510, 64511]
+        );
+        assert_eq!(consensus.to_trie(1).lookup_roas_by_asn(64497).len(), 1);
+    }
+
+    #[test]
+    fn test_failover_and_consensus_errors() {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 4).unwrap();
+        let missing = std::env::temp_dir().join("bgpkit-commons-no-such-mirror");
+        let sources = vec![
+            HistoricalRpkiSource::Local(LocalRpkiArchive::ripe(&missing)),
+            HistoricalRpkiSource::Local(LocalRpkiArchive::rpkiviews(&missing)),
+        ];
+
+        let err = RpkiTrie::from_historical_failover(&sources, date)
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("RIPE NCC"));
+        assert!(err.contains("RPKIviews"));
+        assert!(RpkiTrie::from_historical_failover(&[], date).is_err());
+        assert!(rpki_consensus(&sources, date).is_err());
+
+        let root =
+            std::env::temp_dir().join(format!("bgpkit-commons-failover-{}", std::process::id()));
+        let dir = root.join("ripencc.tal/2024/01/04");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("output.json"),
+            r#"{"roas":[{"prefix":"192.0.2.0/24","maxLength":24,"asn":64496,"ta":"ripe","expires":0}]}"#,
+        )
+        .unwrap();
+        let mut sources = sources;
+        sources.push(HistoricalRpkiSource::Local(LocalRpkiArchive::ripe(&root)));
+
+        let trie = RpkiTrie::from_historical_failover(&sources, date).unwrap();
+        assert_eq!(trie.lookup_roas_by_asn(64496).len(), 1);
+        let consensus = rpki_consensus(&sources, date).unwrap();
+        assert_eq!(consensus.vantage_points.len(), 1);
+        assert_eq!(consensus.failures.len(), 2);
+        assert!(consensus.is_unanimous());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,305 @@
+//! RPKI adoption statistics.
+//!
+//! [`RpkiTrie::coverage_report`] aggregates the ROAs of a trie into address
+//! space covered per trust anchor, per origin ASN and, given a country
+//! lookup, per country. With a list of announced routes it also reports how
+//! many of them are covered, valid and invalid. ASPA adoption is counted
+//! from the ASPA records.
+//!
+//! Address space is the union of the ROA prefixes of a group: a /16 and a
+//! /24 inside it count as 65536 addresses, not 65792.
+
+use std::collections::{HashMap, HashSet};
+
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+
+use super::{Rir, RpkiTrie, RpkiValidation};
+
+/// ROA count and unique address space covered by those ROAs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoaSpace {
+    /// Number of ROAs (VRPs)
+    pub roas: usize,
+    /// IPv4 addresses covered by at least one ROA
+    pub ipv4_addresses: u64,
+    /// IPv6 addresses covered by at least one ROA, saturating at `u128::MAX`
+    /// (2^128, all of `::/0`, does not fit)
+    pub ipv6_addresses: u128,
+}
+
+/// Route origin validation of announced routes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnouncedCoverage {
+    /// Number of announced routes
+    pub routes: usize,
+    /// Routes covered by at least one ROA (valid or invalid)
+    pub covered: usize,
+    /// Routes with state `Valid`
+    pub valid: usize,
+    /// Routes with state `Invalid`
+    pub invalid: usize,
+}
+
+impl AnnouncedCoverage {
+    fn add(&mut self, state: &RpkiValidation) {
+        self.routes += 1;
+        match state {
+            RpkiValidation::Valid => {
+                self.covered += 1;
+                self.valid += 1;
+            }
+            RpkiValidation::Invalid => {
+                self.covered += 1;
+                self.invalid += 1;
+            }
+            RpkiValidation::Unknown => {}
+        }
+    }
+}
+
+/// ASPA adoption counts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AspaAdoption {
+    /// Customer ASNs with an ASPA
+    pub customers: usize,
+    /// Customers whose ASPA lists no provider other than AS0 (no upstreams)
+    pub as0_customers: usize,
+    /// Provider entries summed over all ASPAs
+    pub provider_entries: usize,
+    /// Distinct ASNs named as provider
+    pub unique_providers: usize,
+    /// Distinct origin ASNs in the announced routes
+    pub announcing_origins: usize,
+    /// Announcing origin ASNs that have an ASPA
+    pub announcing_origins_with_aspa: usize,
+}
+
+/// RPKI coverage statistics, see [`RpkiTrie::coverage_report`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RpkiCoverageReport {
+    /// All ROAs
+    pub roas: RoaSpace,
+    /// ROAs per trust anchor; ROAs without a known RIR are only counted in `roas`
+    pub roas_by_rir: HashMap<Rir, RoaSpace>,
+    /// ROAs per origin ASN
+    pub roas_by_origin_asn: HashMap<u32, RoaSpace>,
+    /// ROAs per country of the ROA; empty without a country lookup
+    pub roas_by_country: HashMap<String, RoaSpace>,
+    /// All announced routes; `None` without announced routes
+    pub announced: Option<AnnouncedCoverage>,
+    /// Announced routes per origin ASN
+    pub announced_by_origin_asn: HashMap<u32, AnnouncedCoverage>,
+    /// Announced routes per country; empty without a country lookup
+    pub announced_by_country: HashMap<String, AnnouncedCoverage>,
+    /// ASPA adoption
+    pub aspa: AspaAdoption,
+}
+
+/// Look up the country of a prefix originated by an ASN, e.g. from asinfo or RIR delegation data.
+pub type CountryLookup<'a> = &'a dyn Fn(&IpNet, u32) -> Option<String>;
+
+impl RpkiTrie {
+    /// Compute RPKI coverage statistics.
+    ///
+    /// - `announced`: (prefix, origin ASN) pairs seen in BGP; enables the
+    ///   `announced*` fields and the announcing-origin ASPA counts
+    /// - `country_of`: country of a (prefix, origin ASN); enables the
+    ///   per-country fields. For ROAs it is called with the ROA prefix and ASN.
+    pub fn coverage_report(
+        &self,
+        announced: Option<&[(IpNet, u32)]>,
+        country_of: Option<CountryLookup>,
+    ) -> RpkiCoverageReport {
+        let mut report = RpkiCoverageReport::default();
+
+        let mut all = vec![];
+        let mut by_rir: HashMap<Rir, Vec<IpNet>> = HashMap::new();
+        let mut by_asn: HashMap<u32, Vec<IpNet>> = HashMap::new();
+        let mut by_country: HashMap<String, Vec<IpNet>> = HashMap::new();
+        for (_, roas) in self.trie.iter() {
+            for roa in roas {
+                all.push(roa.prefix);
+                if let Some(rir) = roa.rir {
+                    by_rir.entry(rir).or_default().push(roa.prefix);
+                }
+                by_asn.entry(roa.asn).or_default().push(roa.prefix);
+                if let Some(country) = country_of.and_then(|f| f(&roa.prefix, roa.asn)) {
+                    by_country.entry(country).or_default().push(roa.prefix);
+                }
+            }
+        }
+        report.roas = roa_space(all);
+        report.roas_by_rir = by_rir.into_iter().map(|(k, v)| (k, roa_space(v))).collect();
+        report.roas_by_origin_asn = by_asn.into_iter().map(|(k, v)| (k, roa_space(v))).collect();
+        report.roas_by_country = by_country
+            .into_iter()
+            .map(|(k, v)| (k, roa_space(v)))
+            .collect();
+
+        let mut providers = HashSet::new();
+        for aspa in &self.aspas {
+            report.aspa.customers += 1;
+            report.aspa.provider_entries += aspa.providers.len();
+            if aspa.providers.iter().all(|p| *p == 0) {
+                report.aspa.as0_customers += 1;
+            }
+            providers.extend(aspa.providers.iter().copied().filter(|p| *p != 0));
+        }
+        report.aspa.unique_providers = providers.len();
+
+        if let Some(routes) = announced {
+            let states = self.validate_bulk(routes.iter().copied()).results;
+            let mut total = AnnouncedCoverage::default();
+            for ((prefix, origin), state) in routes.iter().zip(&states) {
+                total.add(state);
+                report
+                    .announced_by_origin_asn
+                    .entry(*origin)
+                    .or_default()
+                    .add(state);
+                if let Some(country) = country_of.and_then(|f| f(prefix, *origin)) {
+                    report
+                        .announced_by_country
+                        .entry(country)
+                        .or_default()
+                        .add(state);
+                }
+            }
+            report.announced = Some(total);
+
+            let origins: HashSet<u32> = routes.iter().map(|(_, origin)| *origin).collect();
+            report.aspa.announcing_origins = origins.len();
+            report.aspa.announcing_origins_with_aspa = origins
+                .iter()
+                .filter(|origin| self.lookup_aspa(**origin).is_some())
+                .count();
+        }
+
+        report
+    }
+}
+
+/// ROA count and the size of the union of the prefixes.
+fn roa_space(mut prefixes: Vec<IpNet>) -> RoaSpace {
+    let mut space = RoaSpace {
+        roas: prefixes.len(),
+        ..Default::default()
+    };
+    // CIDR blocks either nest or are disjoint; after sorting by address and
+    // then length, a prefix is covered iff the last kept prefix contains it
+    prefixes.sort();
+    let mut last: Option<IpNet> = None;
+    for prefix in prefixes {
+        if last.is_some_and(|l| l.contains(&prefix)) {
+            continue;
+        }
+        match prefix {
+            IpNet::V4(p) => space.ipv4_addresses += 1u64 << (32 - p.prefix_len()),
+            IpNet::V6(p) => {
+                // ::/0 has 2^128 addresses, one more than u128 holds
+                let addresses = match p.prefix_len() {
+                    0 => u128::MAX,
+                    len => 1u128 << (128 - len),
+                };
+                space.ipv6_addresses = space.ipv6_addresses.saturating_add(addresses);
+            }
+        }
+        last = Some(prefix);
+    }
+    space
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::{Aspa, Roa, roa};
+
+    #[test]
+    fn test_roa_space_union() {
+        let prefixes = ["10.0.0.0/16", "10.0.1.0/24", "10.0.0.0/16", "10.1.0.0/24"]
+            .iter()
+            .map(|p| p.parse().unwrap())
+            .collect();
+        let space = roa_space(prefixes);
+        assert_eq!(space.roas, 4);
+        assert_eq!(space.ipv4_addresses, 65536 + 256);
+
+        let space = roa_space(vec!["2001:db8::/32".parse().unwrap()]);
+        assert_eq!(space.ipv6_addresses, 1u128 << 96);
+
+        // the IPv6 total saturates
+        let space = roa_space(vec!["::/0".parse().unwrap()]);
+        assert_eq!(space.ipv6_addresses, u128::MAX);
+        let space = roa_space(vec!["::/1".parse().unwrap(), "8000::/1".parse().unwrap()]);
+        assert_eq!(space.ipv6_addresses, u128::MAX);
+    }
+
+    #[test]
+    fn test_coverage_report() {
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roas(vec![
+            Roa {
+                rir: Some(Rir::ARIN),
+                ..roa("192.0.2.0/24", 64496, 24)
+            },
+            Roa {
+                rir: Some(Rir::RIPENCC),
+                ..roa("198.51.100.0/24", 64497, 24)
+            },
+            Roa {
+                rir: Some(Rir::RIPENCC),
+                ..roa("198.51.100.0/25", 64497, 25)
+            },
+            Roa {
+                rir: Some(Rir::RIPENCC),
+                ..roa("2001:db8::/48", 64497, 48)
+            },
+        ]);
+        trie.insert_aspa(Aspa {
+            customer_asn: 64496,
+            providers: vec![64510, 64511],
+            expires: None,
+        });
+        trie.insert_aspa(Aspa {
+            customer_asn: 64500,
+            providers: vec![0],
+            expires: None,
+        });
+
+        let announced: Vec<(IpNet, u32)> = vec![
+            ("192.0.2.0/24".parse().unwrap(), 64496),
+            ("198.51.100.0/24".parse().unwrap(), 64498),
+            ("203.0.113.0/24".parse().unwrap(), 64496),
+        ];
+        let country =
+            |_: &IpNet, asn: u32| Some(if asn == 64496 { "US" } else { "NL" }.to_string());
+        let report = trie.coverage_report(Some(&announced), Some(&country));
+
+        assert_eq!(report.roas.roas, 4);
+        assert_eq!(report.roas.ipv4_addresses, 512);
+        assert_eq!(report.roas_by_rir[&Rir::RIPENCC].ipv4_addresses, 256);
+        assert_eq!(report.roas_by_rir[&Rir::RIPENCC].ipv6_addresses, 1 << 80);
+        assert_eq!(report.roas_by_origin_asn[&64497].roas, 3);
+        assert_eq!(report.roas_by_country["US"].ipv4_addresses, 256);
+
+        let announced_total = report.announced.unwrap();
+        assert_eq!(announced_total.routes, 3);
+        assert_eq!(announced_total.covered, 2);
+        assert_eq!(announced_total.valid, 1);
+        assert_eq!(announced_total.invalid, 1);
+        assert_eq!(report.announced_by_origin_asn[&64496].routes, 2);
+        assert_eq!(report.announced_by_country["NL"].invalid, 1);
+
+        assert_eq!(report.aspa.customers, 2);
+        assert_eq!(report.aspa.as0_customers, 1);
+        assert_eq!(report.aspa.provider_entries, 3);
+        assert_eq!(report.aspa.unique_providers, 2);
+        assert_eq!(report.aspa.announcing_origins, 2);
+        assert_eq!(report.aspa.announcing_origins_with_aspa, 1);
+
+        let report = trie.coverage_report(None, None);
+        assert!(report.announced.is_none());
+        assert!(report.roas_by_country.is_empty());
+    }
+}
@@ -0,0 +1,480 @@
+//! Snapshot comparison between two [`RpkiTrie`] instances.
+//!
+//! [`RpkiTrie::diff`] compares an older snapshot with a newer one and reports
+//! what changed: ROAs added and removed, ROAs whose max-length changed, ASPAs
+//! added, removed or with a different provider set, and expiry changes for
+//! records present in both snapshots. The result can be split per RIR or per
+//! origin ASN to track churn in a specific part of the RPKI.
+
+use chrono::NaiveDateTime;
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+use super::{Aspa, Rir, Roa, RpkiTrie};
+
+/// A ROA whose max-length changed between two snapshots.
+///
+/// Reported when the same (prefix, origin ASN) pair is authorized in both
+/// snapshots but with a different max-length.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoaMaxLengthChange {
+    /// The ROA prefix
+    pub prefix: IpNet,
+    /// The authorized origin ASN
+    pub asn: u32,
+    /// Regional Internet Registry that issued the ROA in the newer snapshot
+    pub rir: Option<Rir>,
+    /// Max-length in the older snapshot
+    pub old_max_length: u8,
+    /// Max-length in the newer snapshot
+    pub new_max_length: u8,
+}
+
+/// A ROA present in both snapshots whose expiry changed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RoaExpiryChange {
+    /// The ROA as found in the newer snapshot
+    pub roa: Roa,
+    /// Expiry in the older snapshot
+    pub old_not_after: Option<NaiveDateTime>,
+    /// Expiry in the newer snapshot
+    pub new_not_after: Option<NaiveDateTime>,
+}
+
+/// An ASPA whose provider set changed between two snapshots.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AspaProviderChange {
+    /// The customer AS number
+    pub customer_asn: u32,
+    /// Providers present only in the newer snapshot
+    pub providers_added: Vec<u32>,
+    /// Providers present only in the older snapshot
+    pub providers_removed: Vec<u32>,
+}
+
+/// An ASPA present in both snapshots whose expiry changed.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AspaExpiryChange {
+    /// The customer AS number
+    pub customer_asn: u32,
+    /// Expiry in the older snapshot
+    pub old_expires: Option<NaiveDateTime>,
+    /// Expiry in the newer snapshot
+    pub new_expires: Option<NaiveDateTime>,
+}
+
+/// Differences between two RPKI snapshots, as returned by [`RpkiTrie::diff`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RpkiDiff {
+    /// ROAs only present in the newer snapshot
+    pub roas_added: Vec<Roa>,
+    /// ROAs only present in the older snapshot
+    pub roas_removed: Vec<Roa>,
+    /// ROAs whose max-length changed
+    pub max_length_changes: Vec<RoaMaxLengthChange>,
+    /// ROAs whose expiry changed
+    pub roa_expiry_changes: Vec<RoaExpiryChange>,
+    /// ASPAs only present in the newer snapshot
+    pub aspas_added: Vec<Aspa>,
+    /// ASPAs only present in the older snapshot
+    pub aspas_removed: Vec<Aspa>,
+    /// ASPAs whose provider set changed
+    pub aspa_provider_changes: Vec<AspaProviderChange>,
+    /// ASPAs whose expiry changed
+    pub aspa_expiry_changes: Vec<AspaExpiryChange>,
+}
+
+impl RpkiDiff {
+    /// Returns true if the two snapshots hold the same data.
+    pub fn is_empty(&self) -> bool {
+        self.roas_added.is_empty()
+            && self.roas_removed.is_empty()
+            && self.max_length_changes.is_empty()
+            && self.roa_expiry_changes.is_empty()
+            && self.aspas_added.is_empty()
+            && self.aspas_removed.is_empty()
+            && self.aspa_provider_changes.is_empty()
+            && self.aspa_expiry_changes.is_empty()
+    }
+
+    /// Split the ROA changes per issuing RIR.
+    ///
+    /// ROAs without RIR information are grouped under `None`. ASPA records
+    /// carry no RIR and are not included.
+    pub fn by_rir(&self) -> HashMap<Option<Rir>, RpkiDiff> {
+        let mut groups: HashMap<Option<Rir>, RpkiDiff> = HashMap::new();
+        for roa in &self.roas_added {
+            groups
+                .entry(roa.rir)
+                .or_default()
+                .roas_added
+                .push(roa.clone());
+        }
+        for roa in &self.roas_removed {
+            groups
+                .entry(roa.rir)
+                .or_default()
+                .roas_removed
+                .push(roa.clone());
+        }
+        for change in &self.max_length_changes {
+            groups
+                .entry(change.rir)
+                .or_default()
+                .max_length_changes
+                .push(change.clone());
+        }
+        for change in &self.roa_expiry_changes {
+            groups
+                .entry(change.roa.rir)
+                .or_default()
+                .roa_expiry_changes
+                .push(change.clone());
+        }
+        groups
+    }
+
+    /// Split the changes per origin ASN.
+    ///
+    /// ROA changes are grouped by the authorized origin ASN, ASPA changes by
+    /// the customer ASN.
+    pub fn by_origin_asn(&self) -> HashMap<u32, RpkiDiff> {
+        let mut groups: HashMap<u32, RpkiDiff> = HashMap::new();
+        for roa in &self.roas_added {
+            groups
+                .entry(roa.asn)
+                .or_default()
+                .roas_added
+                .push(roa.clone());
+        }
+        for roa in &self.roas_removed {
+            groups
+                .entry(roa.asn)
+                .or_default()
+                .roas_removed
+                .push(roa.clone());
+        }
+        for change in &self.max_length_changes {
+            groups
+                .entry(change.asn)
+                .or_default()
+                .max_length_changes
+                .push(change.clone());
+        }
+        for change in &self.roa_expiry_changes {
+            groups
+                .entry(change.roa.asn)
+                .or_default()
+                .roa_expiry_changes
+                .push(change.clone());
+        }
+        for aspa in &self.aspas_added {
+            groups
+                .entry(aspa.customer_asn)
+                .or_default()
+                .aspas_added
+                .push(aspa.clone());
+        }
+        for aspa in &self.aspas_removed {
+            groups
+                .entry(aspa.customer_asn)
+                .or_default()
+                .aspas_removed
+                .push(aspa.clone());
+        }
+        for change in &self.aspa_provider_changes {
+            groups
+                .entry(change.customer_asn)
+                .or_default()
+                .aspa_provider_changes
+                .push(change.clone());
+        }
+        for change in &self.aspa_expiry_changes {
+            groups
+                .entry(change.customer_asn)
+                .or_default()
+                .aspa_expiry_changes
+                .push(change.clone());
+        }
+        groups
+    }
+}
+
+impl RpkiTrie {
+    /// Compare this snapshot (the older one) with `newer`.
+    ///
+    /// ROAs are identified by (prefix, origin ASN, max-length). When the same
+    /// (prefix, origin ASN) pair loses one max-length and gains another, the
+    /// pair is reported as a [`RoaMaxLengthChange`] instead of a removal and
+    /// an addition. ASPAs are identified by customer ASN.
+    ///
+    /// The issuing RIR is not part of a ROA's identity, as a trie holds a
+    /// single ROA per (prefix, origin ASN, max-length). A ROA that moves to
+    /// another trust anchor with the same max-length and expiry is therefore
+    /// not reported.
+    ///
+    /// Every list in the result is sorted: ROAs and ROA changes by prefix,
+    /// origin ASN and max-length, ASPAs and ASPA changes by customer ASN.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use bgpkit_commons::rpki::RpkiTrie;
+    /// use chrono::NaiveDate;
+    ///
+    /// let old = RpkiTrie::from_ripe_historical(NaiveDate::from_ymd_opt(2024, 1, 4).unwrap()).unwrap();
+    /// let new = RpkiTrie::from_ripe_historical(NaiveDate::from_ymd_opt(2024, 1, 5).unwrap()).unwrap();
+    /// let diff = old.diff(&new);
+    /// println!("+{} -{} ROAs", diff.roas_added.len(), diff.roas_removed.len());
+    /// for (rir, changes) in diff.by_rir() {
+    ///     println!("{:?}: +{} -{}", rir, changes.roas_added.len(), changes.roas_removed.len());
+    /// }
+    /// ```
+    pub fn diff(&self, newer: &RpkiTrie) -> RpkiDiff {
+        let mut diff = RpkiDiff::default();
+        self.diff_roas(newer, &mut diff);
+        self.diff_aspas(newer, &mut diff);
+        diff
+    }
+
+    fn diff_roas(&self, newer: &RpkiTrie, diff: &mut RpkiDiff) {
+        let old_roas = roa_map(self);
+        let new_roas = roa_map(newer);
+
+        // (prefix, asn) -> ROAs only in the older / only in the newer snapshot
+        let mut removed: HashMap<(IpNet, u32), Vec<&Roa>> = HashMap::new();
+        let mut added: HashMap<(IpNet, u32), Vec<&Roa>> = HashMap::new();
+
+        for (key, old) in &old_roas {
+            match new_roas.get(key) {
+                Some(new) => {
+                    if old.not_after != new.not_after {
+                        diff.roa_expiry_changes.push(RoaExpiryChange {
+                            roa: (*new).clone(),
+                            old_not_after: old.not_after,
+                            new_not_after: new.not_after,
+                        });
+                    }
+                }
+                None => removed.entry((key.0, key.1)).or_default().push(old),
+            }
+        }
+        for (key, new) in &new_roas {
+            if !old_roas.contains_key(key) {
+                added.entry((key.0, key.1)).or_default().push(new);
+            }
+        }
+
+        for (key, mut old) in removed {
+            let mut new = added.remove(&key).unwrap_or_default();
+            old.sort_by_key(|roa| roa.max_length);
+            new.sort_by_key(|roa| roa.max_length);
+            let paired = old.len().min(new.len());
+            for (old, new) in old.iter().zip(new.iter()) {
+                diff.max_length_changes.push(RoaMaxLengthChange {
+                    prefix: key.0,
+                    asn: key.1,
+                    rir: new.rir,
+                    old_max_length: old.max_length,
+                    new_max_length: new.max_length,
+                });
+            }
+            diff.roas_removed
+                .extend(old[paired..].iter().map(|roa| (*roa).clone()));
+            diff.roas_added
+                .extend(new[paired..].iter().map(|roa| (*roa).clone()));
+        }
+        diff.roas_added
+            .extend(added.into_values().flatten().cloned());
+
+        diff.roas_added.sort_by_key(roa_key);
+        diff.roas_removed.sort_by_key(roa_key);
+        diff.max_length_changes
+            .sort_by_key(|change| (change.prefix, change.asn, change.old_max_length));
+        diff.roa_expiry_changes
+            .sort_by_key(|change| roa_key(&change.roa));
+    }
+
+    fn diff_aspas(&self, newer: &RpkiTrie, diff: &mut RpkiDiff) {
+        let old_aspas: HashMap<u32, &Aspa> =
+            self.aspas.iter().map(|a| (a.customer_asn, a)).collect();
+        let new_aspas: HashMap<u32, &Aspa> =
+            newer.aspas.iter().map(|a| (a.customer_asn, a)).collect();
+
+        for (customer_asn, old) in &old_aspas {
+            let Some(new) = new_aspas.get(customer_asn) else {
+                diff.aspas_removed.push((*old).clone());
+                continue;
+            };
+            let old_providers: BTreeSet<u32> = old.providers.iter().copied().collect();
+            let new_providers: BTreeSet<u32> = new.providers.iter().copied().collect();
+            if old_providers != new_providers {
+                diff.aspa_provider_changes.push(AspaProviderChange {
+                    customer_asn: *customer_asn,
+                    providers_added: new_providers.difference(&old_providers).copied().collect(),
+                    providers_removed: old_providers.difference(&new_providers).copied().collect(),
+                });
+            }
+            if old.expires != new.expires {
+                diff.aspa_expiry_changes.push(AspaExpiryChange {
+                    customer_asn: *customer_asn,
+                    old_expires: old.expires,
+                    new_expires: new.expires,
+                });
+            }
+        }
+        for (customer_asn, new) in &new_aspas {
+            if !old_aspas.contains_key(customer_asn) {
+                diff.aspas_added.push((*new).clone());
+            }
+        }
+
+        diff.aspas_added.sort_by_key(|aspa| aspa.customer_asn);
+        diff.aspas_removed.sort_by_key(|aspa| aspa.customer_asn);
+        diff.aspa_provider_changes
+            .sort_by_key(|change| change.customer_asn);
+        diff.aspa_expiry_changes
+            .sort_by_key(|change| change.customer_asn);
+    }
+}
+
+fn roa_key(roa: &Roa) -> (IpNet, u32, u8) {
+    (roa.prefix, roa.asn, roa.max_length)
+}
+
+fn roa_map(trie: &RpkiTrie) -> HashMap<(IpNet, u32, u8), &Roa> {
+    trie.trie
+        .iter()
+        .flat_map(|(_, roas)| roas.iter())
+        .map(|roa| (roa_key(roa), roa))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::roa;
+    use chrono::DateTime;
+
+    fn expires(timestamp: i64) -> Option<NaiveDateTime> {
+        DateTime::from_timestamp(timestamp, 0).map(|dt| dt.naive_utc())
+    }
+
+    fn aspa(customer_asn: u32, providers: &[u32], timestamp: i64) -> Aspa {
+        Aspa {
+            customer_asn,
+            providers: providers.to_vec(),
+            expires: expires(timestamp),
+        }
+    }
+
+    #[test]
+    fn test_diff_snapshots() {
+        let mut old = RpkiTrie::new(None);
+        old.insert_roas(vec![
+            Roa {
+                rir: Some(Rir::APNIC),
+                not_after: expires(1000),
+                ..roa("192.0.2.0/24", 64496, 24)
+            },
+            Roa {
+                rir: Some(Rir::ARIN),
+                not_after: expires(1000),
+                ..roa("198.51.100.0/22", 64497, 22)
+            },
+            Roa {
+                rir: Some(Rir::RIPENCC),
+                not_after: expires(1000),
+                ..roa("203.0.113.0/24", 64498, 24)
+            },
+        ]);
+        old.insert_aspa(aspa(64496, &[64500, 64501], 1000));
+        old.insert_aspa(aspa(64497, &[64500], 1000));
+
+        let mut new = RpkiTrie::new(None);
+        new.insert_roas(vec![
+            Roa {
+                rir: Some(Rir::APNIC),
+                not_after: expires(2000),
+                ..roa("192.0.2.0/24", 64496, 24)
+            },
+            Roa {
+                rir: Some(Rir::ARIN),
+                not_after: expires(1000),
+                ..roa("198.51.100.0/22", 64497, 24)
+            },
+            Roa {
+                rir: Some(Rir::RIPENCC),
+                not_after: expires(1000),
+                ..roa("2001:db8::/32", 64499, 48)
+            },
+        ]);
+        new.insert_aspa(aspa(64496, &[64501, 64502], 1000));
+        new.insert_aspa(aspa(64498, &[64500], 1000));
+
+        let diff = old.diff(&new);
+        assert!(!diff.is_empty());
+
+        assert_eq!(diff.roas_added.len(), 1);
+        assert_eq!(diff.roas_added[0].asn, 64499);
+        assert_eq!(diff.roas_removed.len(), 1);
+        assert_eq!(diff.roas_removed[0].asn, 64498);
+
+        assert_eq!(diff.max_length_changes.len(), 1);
+        let change = &diff.max_length_changes[0];
+        assert_eq!(change.asn, 64497);
+        assert_eq!((change.old_max_length, change.new_max_length), (22, 24));
+
+        assert_eq!(diff.roa_expiry_changes.len(), 1);
+        assert_eq!(diff.roa_expiry_changes[0].roa.asn, 64496);
+
+        assert_eq!(diff.aspas_added.len(), 1);
+        assert_eq!(diff.aspas_added[0].customer_asn, 64498);
+        assert_eq!(diff.aspas_removed.len(), 1);
+        assert_eq!(diff.aspas_removed[0].customer_asn, 64497);
+        assert_eq!(diff.aspa_provider_changes.len(), 1);
+        assert_eq!(diff.aspa_provider_changes[0].providers_added, vec![64502]);
+        assert_eq!(diff.aspa_provider_changes[0].providers_removed, vec![64500]);
+        assert!(diff.aspa_expiry_changes.is_empty());
+
+        let by_rir = diff.by_rir();
+        assert_eq!(by_rir[&Some(Rir::RIPENCC)].roas_added.len(), 1);
+        assert_eq!(by_rir[&Some(Rir::RIPENCC)].roas_removed.len(), 1);
+        assert_eq!(by_rir[&Some(Rir::ARIN)].max_length_changes.len(), 1);
+        assert!(!by_rir.contains_key(&None));
+
+        let by_asn = diff.by_origin_asn();
+        assert_eq!(by_asn[&64496].roa_expiry_changes.len(), 1);
+        assert_eq!(by_asn[&64496].aspa_provider_changes.len(), 1);
+        assert_eq!(by_asn[&64497].aspas_removed.len(), 1);
+
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn test_diff_is_sorted() {
+        let old = RpkiTrie::new(None);
+        let mut new = RpkiTrie::new(None);
+        for i in (0..50u32).rev() {
+            let prefix = format!("10.{}.0.0/16", i);
+            new.insert_roa(roa(&prefix, 64500 + i % 7, 24));
+            new.insert_roa(roa(&prefix, 64500 + i % 7, 16));
+            new.insert_aspa(aspa(65000 + i * 13 % 50, &[64500], 1000));
+        }
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.roas_added.len(), 100);
+        assert!(diff.roas_added.is_sorted_by_key(roa_key));
+        assert_eq!(diff.aspas_added.len(), 50);
+        assert!(diff.aspas_added.is_sorted_by_key(|aspa| aspa.customer_asn));
+
+        let reverse = new.diff(&old);
+        assert!(reverse.roas_removed.is_sorted_by_key(roa_key));
+        assert!(
+            reverse
+                .aspas_removed
+                .is_sorted_by_key(|aspa| aspa.customer_asn)
+        );
+    }
+}
@@ -0,0 +1,267 @@
+//! Forecasting ROA and ASPA expiry.
+//!
+//! [`RpkiTrie::expiring_between`] lists the ROAs and ASPAs whose `not_after`
+//! or `expires` falls in a time window, so that operators can be warned
+//! before an object lapses. [`RpkiTrie::expiry_impact`] projects which
+//! announcements that are valid now would no longer be valid at a future
+//! time, and which ROAs cause it.
+//!
+//! Validators drop objects once they expire, so the projection treats a
+//! lapsed ROA as withdrawn: a route whose only matching ROA lapsed becomes
+//! `Invalid` if other ROAs still cover it and `Unknown` otherwise. This is
+//! unlike [`RpkiTrie::validate_check_expiry`], which reports a route matched
+//! only by a lapsed ROA as `Unknown` while the ROA is still published.
+
+use std::collections::HashMap;
+
+use chrono::{NaiveDateTime, TimeDelta, Utc};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+
+use super::validation::within_validity;
+use super::{Aspa, Rir, Roa, RpkiTrie, RpkiValidation};
+
+/// ROAs and ASPAs expiring in a time window, see [`RpkiTrie::expiring_between`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RpkiExpiryReport {
+    /// Start of the window (inclusive)
+    pub from: NaiveDateTime,
+    /// End of the window (inclusive)
+    pub until: NaiveDateTime,
+    /// ROAs with `not_after` in the window, soonest first
+    pub roas: Vec<Roa>,
+    /// ASPAs with `expires` in the window, soonest first
+    pub aspas: Vec<Aspa>,
+}
+
+impl RpkiExpiryReport {
+    /// Expiring ROAs per trust anchor; ROAs without a known RIR are left out.
+    pub fn roas_by_rir(&self) -> HashMap<Rir, Vec<&Roa>> {
+        let mut groups: HashMap<Rir, Vec<&Roa>> = HashMap::new();
+        for roa in &self.roas {
+            if let Some(rir) = roa.rir {
+                groups.entry(rir).or_default().push(roa);
+            }
+        }
+        groups
+    }
+
+    /// Expiring ROAs per origin ASN.
+    pub fn roas_by_origin_asn(&self) -> HashMap<u32, Vec<&Roa>> {
+        let mut groups: HashMap<u32, Vec<&Roa>> = HashMap::new();
+        for roa in &self.roas {
+            groups.entry(roa.asn).or_default().push(roa);
+        }
+        groups
+    }
+
+    /// Expiring ASPAs per customer ASN.
+    pub fn aspas_by_customer_asn(&self) -> HashMap<u32, &Aspa> {
+        self.aspas
+            .iter()
+            .map(|aspa| (aspa.customer_asn, aspa))
+            .collect()
+    }
+
+    /// Whether nothing expires in the window.
+    pub fn is_empty(&self) -> bool {
+        self.roas.is_empty() && self.aspas.is_empty()
+    }
+}
+
+/// An announcement that is valid now but would not be at a future time.
+#[derive(Clone, Debug)]
+pub struct RpkiExpiryImpact {
+    /// The announced prefix
+    pub prefix: IpNet,
+    /// The origin ASN
+    pub origin_asn: u32,
+    /// State at the projected time, `Invalid` or `Unknown`
+    pub projected: RpkiValidation,
+    /// Matching ROAs that are valid now and lapse before the projected time
+    pub lapsing_roas: Vec<Roa>,
+}
+
+impl RpkiTrie {
+    /// ROAs and ASPAs expiring between `from` and `until`.
+    ///
+    /// Objects without an expiry time are never listed.
+    pub fn expiring_between(&self, from: NaiveDateTime, until: NaiveDateTime) -> RpkiExpiryReport {
+        let in_window = |time: Option<NaiveDateTime>| time.is_some_and(|t| t >= from && t <= until);
+
+        let mut roas: Vec<Roa> = self
+            .trie
+            .iter()
+            .flat_map(|(_, roas)| roas)
+            .filter(|roa| in_window(roa.not_after))
+            .cloned()
+            .collect();
+        roas.sort_by_key(|roa| (roa.not_after, roa.prefix, roa.asn));
+
+        let mut aspas: Vec<Aspa> = self
+            .aspas
+            .iter()
+            .filter(|aspa| in_window(aspa.expires))
+            .cloned()
+            .collect();
+        aspas.sort_by_key(|aspa| (aspa.expires, aspa.customer_asn));
+
+        RpkiExpiryReport {
+            from,
+            until,
+            roas,
+            aspas,
+        }
+    }
+
+    /// ROAs and ASPAs expiring from now until `window` from now.
+    pub fn expiring_within(&self, window: TimeDelta) -> RpkiExpiryReport {
+        let now = Utc::now().naive_utc();
+        self.expiring_between(now, now + window)
+    }
+
+    /// Announcements valid at `now` (default: the current time) that would
+    /// no longer be valid at `at` because their matching ROAs lapse.
+    ///
+    /// Results keep the input order; routes that stay valid or are not
+    /// valid now are left out.
+    pub fn expiry_impact(
+        &self,
+        routes: &[(IpNet, u32)],
+        at: NaiveDateTime,
+        now: Option<NaiveDateTime>,
+    ) -> Vec<RpkiExpiryImpact> {
+        let now = now.unwrap_or_else(|| Utc::now().naive_utc());
+        let current = self.validate_bulk_check_expiry(routes.iter().copied(), Some(now));
+
+        let mut impacts = vec![];
+        for ((prefix, asn), state) in routes.iter().zip(&current.results) {
+            if *state != RpkiValidation::Valid {
+                continue;
+            }
+            let covering: Vec<Roa> = self
+                .lookup_covering_roas(prefix)
+                .into_iter()
+                .filter(|roa| within_validity(roa, at))
+                .collect();
+            let matches = |roa: &Roa| roa.asn == *asn && roa.max_length >= prefix.prefix_len();
+            if covering.iter().any(matches) {
+                continue;
+            }
+
+            let lapsing_roas = self
+                .lookup_covering_roas(prefix)
+                .into_iter()
+                .filter(|roa| matches(roa) && within_validity(roa, now))
+                .collect();
+            impacts.push(RpkiExpiryImpact {
+                prefix: *prefix,
+                origin_asn: *asn,
+                projected: if covering.is_empty() {
+                    RpkiValidation::Unknown
+                } else {
+                    RpkiValidation::Invalid
+                },
+                lapsing_roas,
+            });
+        }
+        impacts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::roa;
+    use chrono::NaiveDate;
+
+    fn time(day: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, 11, day)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    }
+
+    fn sample_trie() -> RpkiTrie {
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roas(vec![
+            Roa {
+                rir: Some(Rir::ARIN),
+                not_after: Some(time(5)),
+                ..roa("192.0.2.0/24", 64496, 24)
+            },
+            Roa {
+                rir: Some(Rir::ARIN),
+                not_after: Some(time(20)),
+                ..roa("192.0.2.0/24", 64497, 24)
+            },
+            Roa {
+                rir: Some(Rir::RIPENCC),
+                not_after: Some(time(3)),
+                ..roa("198.51.100.0/24", 64498, 24)
+            },
+            Roa {
+                rir: Some(Rir::APNIC),
+                ..roa("203.0.113.0/24", 64496, 24)
+            },
+        ]);
+        trie.insert_aspa(Aspa {
+            customer_asn: 64496,
+            providers: vec![64510],
+            expires: Some(time(4)),
+        });
+        trie.insert_aspa(Aspa {
+            customer_asn: 64497,
+            providers: vec![64510],
+            expires: None,
+        });
+        trie
+    }
+
+    #[test]
+    fn test_expiring_between() {
+        let trie = sample_trie();
+        let report = trie.expiring_between(time(1), time(10));
+        assert_eq!(report.roas.len(), 2);
+        assert_eq!(report.roas[0].asn, 64498);
+        assert_eq!(report.roas[1].asn, 64496);
+        assert_eq!(report.roas_by_rir()[&Rir::ARIN].len(), 1);
+        assert_eq!(report.roas_by_origin_asn()[&64498].len(), 1);
+        assert_eq!(report.aspas.len(), 1);
+        assert!(report.aspas_by_customer_asn().contains_key(&64496));
+
+        assert!(trie.expiring_between(time(21), time(30)).is_empty());
+    }
+
+    #[test]
+    fn test_expiry_impact() {
+        let trie = sample_trie();
+        let routes: Vec<(IpNet, u32)> = vec![
+            ("192.0.2.0/24".parse().unwrap(), 64496),
+            ("192.0.2.0/24".parse().unwrap(), 64497),
+            ("198.51.100.0/24".parse().unwrap(), 64498),
+            ("203.0.113.0/24".parse().unwrap(), 64496),
+            ("192.0.2.0/24".parse().unwrap(), 64499),
+        ];
+
+        let impacts = trie.expiry_impact(&routes, time(10), Some(time(1)));
+        assert_eq!(impacts.len(), 2);
+        // still covered by the ROA for AS64497
+        assert_eq!(impacts[0].origin_asn, 64496);
+        assert_eq!(impacts[0].projected, RpkiValidation::Invalid);
+        assert_eq!(impacts[0].lapsing_roas.len(), 1);
+        // no ROA left
+        assert_eq!(impacts[1].origin_asn, 64498);
+        assert_eq!(impacts[1].projected, RpkiValidation::Unknown);
+
+        assert!(
+            trie.expiry_impact(&routes, time(2), Some(time(1)))
+                .is_empty()
+        );
+        // routes already lapsed at `now` are not reported again
+        assert_eq!(
+            trie.expiry_impact(&routes, time(25), Some(time(6))).len(),
+            1
+        );
+    }
+}
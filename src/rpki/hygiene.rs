@@ -0,0 +1,269 @@
+//! ROA hygiene analysis of one origin AS.
+//!
+//! A ROA whose max-length is longer than its prefix authorizes every
+//! more-specific prefix up to that length. If the AS does not announce all of
+//! them, an attacker can announce an unannounced more-specific with the AS as
+//! forged origin and the route is RPKI-valid (RFC 9319, section 4).
+//! [`RpkiTrie::roa_hygiene`] compares the ROAs of an AS with the prefixes it
+//! announces, reports such exposed ROAs, and suggests a minimal ROA set that
+//! authorizes exactly the announced prefixes.
+
+use std::collections::{HashMap, HashSet};
+
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+
+use super::{Roa, RpkiTrie, RpkiValidation};
+
+/// A ROA that authorizes more-specifics the AS does not announce.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MaxLengthExposure {
+    /// The ROA
+    pub roa: Roa,
+    /// Number of prefixes the ROA authorizes (saturating)
+    pub authorized_prefixes: u128,
+    /// Announced prefixes the ROA authorizes
+    pub announced_prefixes: Vec<IpNet>,
+    /// Number of authorized prefixes that are not announced (saturating)
+    pub unannounced_prefixes: u128,
+}
+
+/// ROA hygiene of one origin AS, see [`RpkiTrie::roa_hygiene`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RoaHygieneReport {
+    /// The origin ASN
+    pub asn: u32,
+    /// ROAs with a max-length longer than the prefix that authorize unannounced prefixes
+    pub exposed_roas: Vec<MaxLengthExposure>,
+    /// ROAs that authorize none of the announced prefixes
+    pub unused_roas: Vec<Roa>,
+    /// Announced prefixes that are not RPKI-valid for the AS
+    pub unauthorized_prefixes: Vec<IpNet>,
+    /// The smallest ROA set authorizing exactly the announced prefixes
+    pub minimal_roas: Vec<Roa>,
+}
+
+impl RoaHygieneReport {
+    /// Whether the current ROAs neither expose unannounced prefixes nor miss announced ones.
+    pub fn is_clean(&self) -> bool {
+        self.exposed_roas.is_empty() && self.unauthorized_prefixes.is_empty()
+    }
+}
+
+impl RpkiTrie {
+    /// Analyze the ROAs of `asn` against the prefixes it announces.
+    ///
+    /// The suggested [`RoaHygieneReport::minimal_roas`] use a max-length
+    /// longer than the prefix only where every more-specific up to that
+    /// length is announced, so they authorize nothing unannounced. Suggested
+    /// ROAs take the RIR of an existing ROA of the AS covering the prefix.
+    pub fn roa_hygiene(&self, asn: u32, announced: &[IpNet]) -> RoaHygieneReport {
+        let announced: HashSet<IpNet> = announced.iter().map(|p| p.trunc()).collect();
+        let mut sorted: Vec<IpNet> = announced.iter().copied().collect();
+        sorted.sort();
+
+        let mut report = RoaHygieneReport {
+            asn,
+            ..Default::default()
+        };
+
+        let mut roas = self.lookup_roas_by_asn(asn);
+        roas.sort_by_key(|roa| (roa.prefix, roa.max_length));
+        for roa in &roas {
+            let authorized: Vec<IpNet> = sorted
+                .iter()
+                .filter(|p| roa.prefix.contains(*p) && p.prefix_len() <= roa.max_length)
+                .copied()
+                .collect();
+            if authorized.is_empty() {
+                report.unused_roas.push(roa.clone());
+            }
+            if roa.max_length <= roa.prefix.prefix_len() {
+                continue;
+            }
+            let total = authorized_count(roa);
+            let unannounced = total.saturating_sub(authorized.len() as u128);
+            if unannounced > 0 {
+                report.exposed_roas.push(MaxLengthExposure {
+                    roa: roa.clone(),
+                    authorized_prefixes: total,
+                    announced_prefixes: authorized,
+                    unannounced_prefixes: unannounced,
+                });
+            }
+        }
+
+        report.unauthorized_prefixes = sorted
+            .iter()
+            .filter(|p| self.validate(p, asn) != RpkiValidation::Valid)
+            .copied()
+            .collect();
+
+        report.minimal_roas = minimal_roas(&sorted, &announced)
+            .into_iter()
+            .map(|(prefix, max_length)| Roa {
+                prefix,
+                asn,
+                max_length,
+                rir: roas
+                    .iter()
+                    .find(|roa| roa.prefix.contains(&prefix))
+                    .and_then(|roa| roa.rir),
+                not_before: None,
+                not_after: None,
+            })
+            .collect();
+        report
+    }
+}
+
+/// Number of prefixes a ROA authorizes: 2^(max_length - prefix_len + 1) - 1.
+fn authorized_count(roa: &Roa) -> u128 {
+    let depth = roa.max_length.saturating_sub(roa.prefix.prefix_len()) as u32;
+    1u128.checked_shl(depth + 1).map_or(u128::MAX, |n| n - 1)
+}
+
+/// (prefix, max-length) pairs covering exactly `announced`.
+///
+/// `sorted` is `announced` sorted by address and then prefix length, so a
+/// prefix comes before its more-specifics.
+fn minimal_roas(sorted: &[IpNet], announced: &HashSet<IpNet>) -> Vec<(IpNet, u8)> {
+    let mut depths: HashMap<IpNet, u8> = HashMap::new();
+    // longest prefixes first, so both children are computed before their parent
+    let mut by_length = sorted.to_vec();
+    by_length.sort_by_key(|p| std::cmp::Reverse(p.prefix_len()));
+    for prefix in by_length {
+        let depth = match prefix.subnets(prefix.prefix_len() + 1) {
+            Ok(mut halves) => match (halves.next(), halves.next()) {
+                (Some(a), Some(b)) if announced.contains(&a) && announced.contains(&b) => {
+                    1 + depths[&a].min(depths[&b])
+                }
+                _ => 0,
+            },
+            Err(_) => 0,
+        };
+        depths.insert(prefix, depth);
+    }
+
+    let mut chosen: HashMap<IpNet, u8> = HashMap::new();
+    let mut roas = vec![];
+    for prefix in sorted {
+        let max_length = prefix.prefix_len() + depths[prefix];
+        // a chosen supernet may cover the prefix but not all of its announced more-specifics
+        let covered = prefix
+            .supernet()
+            .into_iter()
+            .flat_map(|p| std::iter::successors(Some(p), |p| p.supernet()))
+            .any(|p| chosen.get(&p).is_some_and(|max| *max >= max_length));
+        if covered {
+            continue;
+        }
+        chosen.insert(*prefix, max_length);
+        roas.push((*prefix, max_length));
+    }
+    roas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::{Rir, roa};
+
+    fn prefixes(list: &[&str]) -> Vec<IpNet> {
+        list.iter().map(|p| p.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_roa_hygiene() {
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roas(vec![
+            Roa {
+                rir: Some(Rir::RIPENCC),
+                ..roa("10.0.0.0/16", 64496, 24)
+            },
+            roa("10.1.0.0/16", 64496, 16),
+            roa("10.2.0.0/23", 64496, 24),
+            roa("10.9.0.0/16", 64496, 16),
+            roa("10.0.0.0/16", 64497, 16),
+        ]);
+        let announced = prefixes(&[
+            "10.0.0.0/16",
+            "10.0.1.0/24",
+            "10.1.0.0/16",
+            "10.2.0.0/23",
+            "10.2.0.0/24",
+            "10.2.1.0/24",
+            "10.3.0.0/16",
+        ]);
+
+        let report = trie.roa_hygiene(64496, &announced);
+        assert_eq!(report.exposed_roas.len(), 1);
+        let exposure = &report.exposed_roas[0];
+        assert_eq!(exposure.roa.prefix.to_string(), "10.0.0.0/16");
+        assert_eq!(exposure.authorized_prefixes, 511);
+        assert_eq!(exposure.announced_prefixes.len(), 2);
+        assert_eq!(exposure.unannounced_prefixes, 509);
+
+        assert_eq!(report.unused_roas.len(), 1);
+        assert_eq!(report.unused_roas[0].prefix.to_string(), "10.9.0.0/16");
+        assert_eq!(report.unauthorized_prefixes, prefixes(&["10.3.0.0/16"]));
+        assert!(!report.is_clean());
+
+        let minimal: Vec<(String, u8)> = report
+            .minimal_roas
+            .iter()
+            .map(|roa| (roa.prefix.to_string(), roa.max_length))
+            .collect();
+        assert_eq!(
+            minimal,
+            vec![
+                ("10.0.0.0/16".to_string(), 16),
+                ("10.0.1.0/24".to_string(), 24),
+                ("10.1.0.0/16".to_string(), 16),
+                ("10.2.0.0/23".to_string(), 24),
+                ("10.3.0.0/16".to_string(), 16),
+            ]
+        );
+        assert_eq!(report.minimal_roas[0].rir, Some(Rir::RIPENCC));
+        assert_eq!(report.minimal_roas[4].rir, None);
+
+        // the suggested ROAs authorize exactly the announced prefixes
+        let mut minimal_trie = RpkiTrie::new(None);
+        minimal_trie.insert_roas(report.minimal_roas.clone());
+        let report = minimal_trie.roa_hygiene(64496, &announced);
+        assert!(report.is_clean());
+        assert!(report.unused_roas.is_empty());
+
+        // the /24 with both /25s needs its own ROA even though the /23 covers it
+        let announced = prefixes(&[
+            "10.0.0.0/23",
+            "10.0.0.0/24",
+            "10.0.1.0/24",
+            "10.0.0.0/25",
+            "10.0.0.128/25",
+        ]);
+        let report = RpkiTrie::new(None).roa_hygiene(64496, &announced);
+        let minimal: Vec<(String, u8)> = report
+            .minimal_roas
+            .iter()
+            .map(|roa| (roa.prefix.to_string(), roa.max_length))
+            .collect();
+        assert_eq!(
+            minimal,
+            vec![
+                ("10.0.0.0/23".to_string(), 24),
+                ("10.0.0.0/24".to_string(), 25),
+            ]
+        );
+        let mut minimal_trie = RpkiTrie::new(None);
+        minimal_trie.insert_roas(report.minimal_roas);
+        assert!(minimal_trie.roa_hygiene(64496, &announced).is_clean());
+    }
+
+    #[test]
+    fn test_authorized_count() {
+        assert_eq!(authorized_count(&roa("10.0.0.0/24", 64496, 24)), 1);
+        assert_eq!(authorized_count(&roa("10.0.0.0/22", 64496, 24)), 7);
+        assert_eq!(authorized_count(&roa("::/0", 64496, 128)), u128::MAX);
+    }
+}
@@ -0,0 +1,267 @@
+//! Secondary indexes over the ROAs and ASPAs of an [`RpkiTrie`].
+//!
+//! The prefix trie answers prefix queries; per-AS queries (all ROAs for an
+//! origin ASN, the ASPA of a customer, the customers naming an AS as
+//! provider) go through these indexes instead of scanning all data. The
+//! indexes are kept up to date by the `insert_*`/`remove_*` methods of
+//! [`RpkiTrie`]. Code that modifies the public `trie` or `aspas` fields
+//! directly must call [`RpkiTrie::rebuild_indexes`] afterwards.
+//!
+//! The ASPA positions are checked before use: if `aspas` no longer matches
+//! them, [`RpkiTrie::lookup_aspa`] falls back to scanning `aspas` and the
+//! ASPA `insert`/`remove` methods rebuild the ASPA index first.
+
+use std::collections::{BTreeSet, HashMap};
+
+use ipnet::IpNet;
+
+use super::{Aspa, Roa, RpkiTrie};
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RpkiIndex {
+    /// Prefixes with at least one ROA for the origin ASN
+    roa_prefixes_by_asn: HashMap<u32, BTreeSet<IpNet>>,
+    /// Position of each customer's ASPA in `RpkiTrie::aspas`
+    aspa_by_customer: HashMap<u32, usize>,
+    /// Customer ASNs whose ASPA lists the provider ASN
+    customers_by_provider: HashMap<u32, BTreeSet<u32>>,
+    /// Length and last customer ASN of `RpkiTrie::aspas` when the positions
+    /// were last updated, to detect direct changes to the field
+    aspa_shape: (usize, Option<u32>),
+}
+
+impl RpkiIndex {
+    pub(crate) fn add_roa(&mut self, roa: &Roa) {
+        self.roa_prefixes_by_asn
+            .entry(roa.asn)
+            .or_default()
+            .insert(roa.prefix);
+    }
+
+    /// Drop `prefix` from the ASN's entry unless `remaining` still has a ROA for the ASN.
+    pub(crate) fn remove_roa(&mut self, prefix: &IpNet, asn: u32, remaining: &[Roa]) {
+        if remaining.iter().any(|roa| roa.asn == asn) {
+            return;
+        }
+        if let Some(prefixes) = self.roa_prefixes_by_asn.get_mut(&asn) {
+            prefixes.remove(prefix);
+            if prefixes.is_empty() {
+                self.roa_prefixes_by_asn.remove(&asn);
+            }
+        }
+    }
+
+    pub(crate) fn add_aspa(&mut self, aspa: &Aspa, position: usize) {
+        self.aspa_by_customer.insert(aspa.customer_asn, position);
+        for provider in &aspa.providers {
+            self.customers_by_provider
+                .entry(*provider)
+                .or_default()
+                .insert(aspa.customer_asn);
+        }
+    }
+
+    /// Forget the providers of an ASPA that is being replaced or removed.
+    pub(crate) fn remove_aspa_providers(&mut self, aspa: &Aspa) {
+        for provider in &aspa.providers {
+            if let Some(customers) = self.customers_by_provider.get_mut(provider) {
+                customers.remove(&aspa.customer_asn);
+                if customers.is_empty() {
+                    self.customers_by_provider.remove(provider);
+                }
+            }
+        }
+    }
+
+    /// Remove the ASPA at `position` after a `swap_remove`, which moved
+    /// `moved` from the old last position into `position`.
+    pub(crate) fn remove_aspa(&mut self, aspa: &Aspa, position: usize, moved: Option<&Aspa>) {
+        self.remove_aspa_providers(aspa);
+        self.aspa_by_customer.remove(&aspa.customer_asn);
+        if let Some(moved) = moved {
+            let last = self.aspa_shape.0 - 1;
+            if let Some(index) = self.aspa_by_customer.get_mut(&moved.customer_asn) {
+                // a duplicate record of an indexed customer keeps the indexed position
+                if *index == last {
+                    *index = position;
+                }
+            }
+        }
+    }
+
+    pub(crate) fn aspa_position(&self, customer_asn: u32) -> Option<usize> {
+        self.aspa_by_customer.get(&customer_asn).copied()
+    }
+}
+
+impl RpkiTrie {
+    /// Rebuild the per-AS indexes from the current ROAs and ASPAs.
+    ///
+    /// Only needed after modifying the public `trie` or `aspas` fields
+    /// directly; the `insert_*` and `remove_*` methods keep the indexes current.
+    pub fn rebuild_indexes(&mut self) {
+        let mut index = RpkiIndex::default();
+        for (_, roas) in self.trie.iter() {
+            for roa in roas {
+                index.add_roa(roa);
+            }
+        }
+        self.index = index;
+        self.rebuild_aspa_index();
+    }
+
+    fn rebuild_aspa_index(&mut self) {
+        self.index.aspa_by_customer.clear();
+        self.index.customers_by_provider.clear();
+        for (position, aspa) in self.aspas.iter().enumerate() {
+            // keep the first ASPA of a customer, as `lookup_aspa` did before indexing
+            if self.index.aspa_position(aspa.customer_asn).is_none() {
+                self.index.add_aspa(aspa, position);
+            }
+        }
+        self.sync_aspa_shape();
+    }
+
+    fn aspa_shape(&self) -> (usize, Option<u32>) {
+        (
+            self.aspas.len(),
+            self.aspas.last().map(|aspa| aspa.customer_asn),
+        )
+    }
+
+    /// Record that the ASPA positions match the current `aspas`.
+    pub(super) fn sync_aspa_shape(&mut self) {
+        self.index.aspa_shape = self.aspa_shape();
+    }
+
+    /// Whether the indexed position of the customer's ASPA can be trusted.
+    fn aspa_index_matches(&self, customer_asn: u32) -> bool {
+        self.index.aspa_shape == self.aspa_shape()
+            && self
+                .index
+                .aspa_position(customer_asn)
+                .is_none_or(|position| self.aspas[position].customer_asn == customer_asn)
+    }
+
+    /// Position of the customer's ASPA in `aspas`, rebuilding the ASPA index
+    /// first if `aspas` was modified directly.
+    pub(super) fn aspa_position_mut(&mut self, customer_asn: u32) -> Option<usize> {
+        if !self.aspa_index_matches(customer_asn) {
+            self.rebuild_aspa_index();
+        }
+        self.index.aspa_position(customer_asn)
+    }
+
+    /// Look up all ROAs with the given origin ASN, ordered by prefix.
+    pub fn lookup_roas_by_asn(&self, asn: u32) -> Vec<Roa> {
+        let Some(prefixes) = self.index.roa_prefixes_by_asn.get(&asn) else {
+            return vec![];
+        };
+        prefixes
+            .iter()
+            .filter_map(|prefix| self.trie.exact_match(*prefix))
+            .flat_map(|roas| roas.iter().filter(|roa| roa.asn == asn))
+            .cloned()
+            .collect()
+    }
+
+    /// Look up the ASPA record of a customer ASN.
+    pub fn lookup_aspa(&self, customer_asn: u32) -> Option<&Aspa> {
+        if self.aspa_index_matches(customer_asn) {
+            return self
+                .index
+                .aspa_position(customer_asn)
+                .map(|position| &self.aspas[position]);
+        }
+        // `aspas` was modified directly since the index was built
+        self.aspas
+            .iter()
+            .find(|aspa| aspa.customer_asn == customer_asn)
+    }
+
+    /// Look up the customer ASNs whose ASPA lists `provider_asn` as a provider, in ascending order.
+    pub fn lookup_aspa_customers(&self, provider_asn: u32) -> Vec<u32> {
+        self.index
+            .customers_by_provider
+            .get(&provider_asn)
+            .map(|customers| customers.iter().copied().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::roa;
+
+    fn aspa(customer_asn: u32, providers: &[u32]) -> Aspa {
+        Aspa {
+            customer_asn,
+            providers: providers.to_vec(),
+            expires: None,
+        }
+    }
+
+    #[test]
+    fn test_roa_index() {
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roas(vec![
+            roa("198.51.100.0/24", 64496, 24),
+            roa("192.0.2.0/24", 64496, 24),
+            roa("192.0.2.0/24", 64496, 25),
+            roa("192.0.2.0/24", 64497, 24),
+        ]);
+
+        let roas = trie.lookup_roas_by_asn(64496);
+        assert_eq!(roas.len(), 3);
+        assert_eq!(roas[0].prefix, "192.0.2.0/24".parse::<IpNet>().unwrap());
+        assert_eq!(trie.lookup_roas_by_asn(64497).len(), 1);
+
+        let p: IpNet = "192.0.2.0/24".parse().unwrap();
+        assert!(trie.remove_roa(&p, 64496, 24));
+        assert_eq!(trie.lookup_roas_by_asn(64496).len(), 2);
+        assert!(trie.remove_roa(&p, 64496, 25));
+        assert_eq!(trie.lookup_roas_by_asn(64496).len(), 1);
+        assert!(trie.remove_roa(&p, 64497, 24));
+        assert!(trie.lookup_roas_by_asn(64497).is_empty());
+        assert!(!trie.index.roa_prefixes_by_asn.contains_key(&64497));
+    }
+
+    #[test]
+    fn test_aspa_index() {
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_aspa(aspa(64500, &[64510, 64511]));
+        trie.insert_aspa(aspa(64501, &[64510]));
+        trie.insert_aspa(aspa(64502, &[64512]));
+
+        assert_eq!(trie.lookup_aspa(64501).unwrap().providers, vec![64510]);
+        assert_eq!(trie.lookup_aspa_customers(64510), vec![64500, 64501]);
+
+        // replacing an ASPA updates the provider index
+        trie.insert_aspa(aspa(64500, &[64511]));
+        assert_eq!(trie.lookup_aspa_customers(64510), vec![64501]);
+        assert_eq!(trie.lookup_aspa_customers(64511), vec![64500]);
+
+        // removing moves the last record into the freed position
+        trie.remove_aspa(64500).unwrap();
+        assert!(trie.lookup_aspa(64500).is_none());
+        assert_eq!(trie.lookup_aspa(64502).unwrap().customer_asn, 64502);
+        assert_eq!(trie.lookup_aspa(64501).unwrap().customer_asn, 64501);
+        assert!(trie.lookup_aspa_customers(64511).is_empty());
+
+        // direct field changes are still found by `lookup_aspa`
+        trie.aspas.remove(0);
+        assert!(trie.lookup_aspa(64502).is_none());
+        assert_eq!(trie.lookup_aspa(64501).unwrap().customer_asn, 64501);
+        trie.aspas.push(aspa(64503, &[64510]));
+        assert_eq!(trie.lookup_aspa(64503).unwrap().providers, vec![64510]);
+
+        // the provider index is current after a rebuild or an ASPA update
+        trie.rebuild_indexes();
+        assert_eq!(trie.lookup_aspa_customers(64510), vec![64501, 64503]);
+        trie.aspas.push(aspa(64504, &[64512]));
+        assert!(trie.remove_aspa(64501).is_some());
+        assert_eq!(trie.lookup_aspa_customers(64510), vec![64503]);
+        assert_eq!(trie.lookup_aspa_customers(64512), vec![64504]);
+    }
+}
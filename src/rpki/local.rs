@@ -0,0 +1,295 @@
+//! Offline historical RPKI data from local mirrors.
+//!
+//! A [`LocalRpkiArchive`] points at a directory laid out like one of the
+//! public archives, so that dates resolve to files without network access:
+//!
+//! - [`LocalRpkiLayout::Ripe`]: a mirror of <https://ftp.ripe.net/rpki/>,
+//!   i.e. `<root>/<rir>.tal/YYYY/MM/DD/output.json.xz`
+//! - [`LocalRpkiLayout::RpkiViews`]: a mirror of one RPKIviews collector,
+//!   i.e. `<root>/YYYY/MM/DD/rpki-YYYYMMDDTHHMMSSZ.tgz`
+//! - [`LocalRpkiLayout::RpkiSpools`]: a mirror of one RPKISPOOL collector,
+//!   i.e. `<root>/YYYY/MM/DD/YYYYMMDD-rpkispool.tar.zst`
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::rpkispools::rpkispool_path;
+use super::{HistoricalRpkiSource, Rir, RpkiFile, RpkiFileFormat, RpkiSource, RpkiTrie};
+use crate::{BgpkitCommonsError, Result};
+
+/// Directory layout of a local RPKI archive mirror.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LocalRpkiLayout {
+    /// RIPE NCC ftp tree with one `<rir>.tal` directory per RIR
+    Ripe,
+    /// RPKIviews collector tree with `.tgz` snapshots
+    RpkiViews,
+    /// RPKISPOOL collector tree with daily `.tar.zst` archives
+    RpkiSpools,
+}
+
+impl LocalRpkiLayout {
+    /// The format of the files in this layout.
+    pub fn file_format(&self) -> RpkiFileFormat {
+        match self {
+            LocalRpkiLayout::Ripe => RpkiFileFormat::RpkiClientJson,
+            LocalRpkiLayout::RpkiViews => RpkiFileFormat::RpkiViewsArchive,
+            LocalRpkiLayout::RpkiSpools => RpkiFileFormat::RpkiSpoolsArchive,
+        }
+    }
+}
+
+impl std::fmt::Display for LocalRpkiLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LocalRpkiLayout::Ripe => write!(f, "RIPE NCC"),
+            LocalRpkiLayout::RpkiViews => write!(f, "RPKIviews"),
+            LocalRpkiLayout::RpkiSpools => write!(f, "RPKISPOOL"),
+        }
+    }
+}
+
+/// A local directory mirroring a historical RPKI archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalRpkiArchive {
+    /// How the directory is organized
+    pub layout: LocalRpkiLayout,
+    /// The mirror's root directory
+    pub root: PathBuf,
+}
+
+impl std::fmt::Display for LocalRpkiArchive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "local {} mirror ({})", self.layout, self.root.display())
+    }
+}
+
+impl LocalRpkiArchive {
+    /// A local archive with the given layout.
+    pub fn new(layout: LocalRpkiLayout, root: impl Into<PathBuf>) -> Self {
+        Self {
+            layout,
+            root: root.into(),
+        }
+    }
+
+    /// A mirror of the RIPE NCC ftp tree (the directory containing `ripencc.tal`, `arin.tal`, ...).
+    pub fn ripe(root: impl Into<PathBuf>) -> Self {
+        Self::new(LocalRpkiLayout::Ripe, root)
+    }
+
+    /// A mirror of an RPKIviews collector (the directory containing the year directories).
+    pub fn rpkiviews(root: impl Into<PathBuf>) -> Self {
+        Self::new(LocalRpkiLayout::RpkiViews, root)
+    }
+
+    /// A mirror of an RPKISPOOL collector (the directory containing the year directories).
+    pub fn rpkispools(root: impl Into<PathBuf>) -> Self {
+        Self::new(LocalRpkiLayout::RpkiSpools, root)
+    }
+
+    /// List the files of a date in the mirror, sorted by timestamp.
+    ///
+    /// Returns an empty list if the mirror has no data for the date, and an
+    /// error if the root directory does not exist.
+    pub fn list_files(&self, date: NaiveDate) -> Result<Vec<RpkiFile>> {
+        if !self.root.is_dir() {
+            return Err(BgpkitCommonsError::data_source_error(
+                self.to_string(),
+                "root directory does not exist",
+            ));
+        }
+        let day_dir = format!("{:04}/{:02}/{:02}", date.year(), date.month(), date.day());
+        let midnight = date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+
+        let mut files = vec![];
+        match self.layout {
+            LocalRpkiLayout::Ripe => {
+                for rir in [
+                    Rir::AFRINIC,
+                    Rir::APNIC,
+                    Rir::ARIN,
+                    Rir::LACNIC,
+                    Rir::RIPENCC,
+                ] {
+                    let root_url = rir.to_ripe_ftp_root_url();
+                    let tal_dir = root_url.rsplit('/').next().unwrap_or_default();
+                    let dir = self.root.join(tal_dir).join(&day_dir);
+                    // prefer the file as published; accept a decompressed copy
+                    let Some(path) = ["output.json.xz", "output.json"]
+                        .iter()
+                        .map(|name| dir.join(name))
+                        .find(|path| path.is_file())
+                    else {
+                        continue;
+                    };
+                    files.push(local_file(&path, midnight, Some(rir)));
+                }
+            }
+            LocalRpkiLayout::RpkiViews => {
+                let dir = self.root.join(&day_dir);
+                let Ok(entries) = std::fs::read_dir(&dir) else {
+                    return Ok(vec![]);
+                };
+                for entry in entries.flatten() {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    let Some(stamp) = name
+                        .strip_prefix("rpki-")
+                        .and_then(|n| n.strip_suffix(".tgz"))
+                    else {
+                        continue;
+                    };
+                    let timestamp = NaiveDateTime::parse_from_str(stamp, "%Y%m%dT%H%M%SZ")
+                        .map(|t| t.and_utc())
+                        .unwrap_or(midnight);
+                    files.push(local_file(&entry.path(), timestamp, None));
+                }
+            }
+            LocalRpkiLayout::RpkiSpools => {
+                let path = self.root.join(rpkispool_path(date));
+                if path.is_file() {
+                    files.push(local_file(&path, midnight, None));
+                }
+            }
+        }
+
+        files.sort_by(|a, b| (a.timestamp, &a.url).cmp(&(b.timestamp, &b.url)));
+        Ok(files)
+    }
+}
+
+fn local_file(path: &Path, timestamp: DateTime<Utc>, rir: Option<Rir>) -> RpkiFile {
+    RpkiFile {
+        url: path.to_string_lossy().to_string(),
+        timestamp,
+        size: std::fs::metadata(path).ok().map(|m| m.len()),
+        rir,
+        collector: None,
+    }
+}
+
+impl RpkiTrie {
+    /// Load RPKI data for a date from a local mirror.
+    ///
+    /// Like the online sources, this loads all per-RIR files of a RIPE NCC
+    /// mirror and the earliest snapshot of the day of an RPKIviews or
+    /// RPKISPOOL mirror.
+    pub fn from_local_archive(archive: &LocalRpkiArchive, date: NaiveDate) -> Result<Self> {
+        let files = archive.list_files(date)?;
+        let urls: Vec<String> = match archive.layout {
+            LocalRpkiLayout::Ripe => files.into_iter().map(|f| f.url).collect(),
+            LocalRpkiLayout::RpkiViews | LocalRpkiLayout::RpkiSpools => {
+                files.into_iter().take(1).map(|f| f.url).collect()
+            }
+        };
+        if urls.is_empty() {
+            return Err(BgpkitCommonsError::data_source_error(
+                archive.to_string(),
+                format!("no snapshot for {}", date),
+            ));
+        }
+        info!("loading RPKI data for {} from {}", date, archive);
+
+        let mut trie = RpkiTrie::from_files(&urls, archive.layout.file_format(), Some(date))?;
+        trie.provenance.source =
+            RpkiSource::Historical(HistoricalRpkiSource::Local(archive.clone()));
+        Ok(trie)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp directory.
+    fn temp_root(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("bgpkit-commons-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn touch(path: PathBuf, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_local_ripe_archive() {
+        let root = temp_root("ripe");
+        let date = NaiveDate::from_ymd_opt(2024, 1, 4).unwrap();
+        touch(
+            root.join("arin.tal/2024/01/04/output.json"),
+            r#"{"roas":[{"prefix":"192.0.2.0/24","maxLength":24,"asn":"AS64496","ta":"arin","expires":0}]}"#,
+        );
+        touch(
+            root.join("ripencc.tal/2024/01/04/output.json"),
+            r#"{"roas":[{"prefix":"198.51.100.0/24","maxLength":24,"asn":"AS64497","ta":"ripe","expires":0}]}"#,
+        );
+
+        let archive = LocalRpkiArchive::ripe(&root);
+        let files = archive.list_files(date).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].rir, Some(Rir::ARIN));
+        assert!(files[0].size.is_some());
+        assert!(
+            archive
+                .list_files(date.succ_opt().unwrap())
+                .unwrap()
+                .is_empty()
+        );
+
+        let trie = RpkiTrie::from_local_archive(&archive, date).unwrap();
+        assert_eq!(trie.lookup_roas_by_asn(64496)[0].rir, Some(Rir::ARIN));
+        assert_eq!(trie.lookup_roas_by_asn(64497).len(), 1);
+        assert!(matches!(
+            &trie.provenance().source,
+            RpkiSource::Historical(HistoricalRpkiSource::Local(a)) if *a == archive
+        ));
+        assert!(RpkiTrie::from_local_archive(&archive, date.succ_opt().unwrap()).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_local_rpkiviews_and_rpkispools_listing() {
+        let root = temp_root("mirrors");
+        let date = NaiveDate::from_ymd_opt(2024, 1, 4).unwrap();
+        touch(root.join("views/2024/01/04/rpki-20240104T144128Z.tgz"), "");
+        touch(root.join("views/2024/01/04/rpki-20240104T002001Z.tgz"), "");
+        touch(root.join("views/2024/01/04/index.html"), "");
+        touch(
+            root.join("spools/2024/01/04/20240104-rpkispool.tar.zst"),
+            "",
+        );
+        touch(
+            root.join("spools/2024/01/04/20240104-initstate.tar.zst"),
+            "",
+        );
+
+        let files = LocalRpkiArchive::rpkiviews(root.join("views"))
+            .list_files(date)
+            .unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files[0].url.ends_with("rpki-20240104T002001Z.tgz"));
+        assert_eq!(files[1].timestamp.to_rfc3339(), "2024-01-04T14:41:28+00:00");
+
+        let files = LocalRpkiArchive::rpkispools(root.join("spools"))
+            .list_files(date)
+            .unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].url.ends_with("20240104-rpkispool.tar.zst"));
+
+        assert!(
+            LocalRpkiArchive::rpkispools(root.join("missing"))
+                .list_files(date)
+                .is_err()
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -49,6 +49,31 @@
 //! - **Format**: `.tar.zst` archives containing CCR (Canonical Cache Representation) files
 //! - **Use Case**: Efficient historical ROA/ASPA snapshots from collector-specific mirrors
 //! - **Collectors**: SobornostNet, AttnJp, and KerfuffleNet
+//! - **Intra-day States**: each archive holds many CCR states per day; [`list_rpkispools_states`]
+//!   lists them, [`RpkiTrie::from_rpkispools_at`] loads the state of one vantage point closest to
+//!   a point in time and [`list_rpkispools_deltas`] returns the changes between consecutive states
+//!
+//! ## Multiple Collectors
+//! [`RpkiTrie::from_historical_failover`] tries a list of historical sources in order and
+//! returns the first that loads. [`rpki_consensus`] loads all of them and tags each ROA and
+//! ASPA with the vantage points that saw it, exposing disagreements between validators.
+//!
+//! ## Local Mirrors
+//! - **Source**: `HistoricalRpkiSource::Local(`[`LocalRpkiArchive`]`)`, a directory laid out like
+//!   the RIPE NCC ftp tree, an RPKIviews collector or an RPKISPOOL collector
+//! - **Use Case**: Offline research clusters; dates resolve to local files without network access
+//!
+//! ## Local Validator Cache (RTR)
+//! - **Protocol**: RPKI-to-Router, versions 0 (RFC 6810), 1 (RFC 8210) and 2 (8210bis, with ASPA)
+//! - **Use Case**: Analyze exactly the VRP set a local validator hands to routers
+//! - **Incremental Updates**: [`RtrClient`] follows Serial Notify PDUs and applies deltas
+//! - **Serving**: [`RtrServer`] hands any loaded trie to routers, e.g. to replay a historical snapshot
+//!
+//! ## Validator Output Files
+//! - **Formats**: Routinator JSON, extended JSON and CSV, Fort JSON and CSV, StayRTR/OctoRPKI
+//!   `vrps` JSON and plain `ASN,prefix,maxlen` CSV (see [`RpkiFileFormat`])
+//! - **Use Case**: Compare the output of your own validators with the public archives
+//! - **Loading**: [`RpkiTrie::from_files`] for URLs and paths, [`RpkiTrie::from_reader`] for any reader
 //!
 //! # Core Data Structures
 //!
@@ -58,6 +83,10 @@
 //! - **ASPAs**: `Vec<Aspa>` - AS Provider Authorization records
 //! - **Date**: `Option<NaiveDate>` - Optional date for historical data
 //!
+//! Per-AS queries use indexes instead of scanning: `lookup_roas_by_asn()` returns
+//! all ROAs of an origin ASN, `lookup_aspa()` the ASPA of a customer, and
+//! `lookup_aspa_customers()` the customers that list an AS as provider.
+//!
 //! ## Roa
 //! Represents a Route Origin Authorization with the following fields:
 //! - `prefix: IpNet` - The IP prefix (e.g., 192.0.2.0/24)
@@ -73,12 +102,100 @@
 //! - `providers: Vec<u32>` - List of provider AS numbers
 //! - `expires: Option<NaiveDateTime>` - When this ASPA expires
 //!
+//! ## RouterKey
+//! Represents a BGPsec router key with the following fields:
+//! - `asn: u32` - The AS number the key is certified for
+//! - `ski: String` - Subject Key Identifier (hex)
+//! - `pubkey: String` - DER-encoded SubjectPublicKeyInfo (base64)
+//! - `ta: Option<String>` - Trust anchor the key was validated under
+//! - `expires: Option<NaiveDateTime>` - When the router key certificate expires
+//!
 //! ## Validation Results
 //! RPKI validation returns one of three states:
 //! - **Valid**: The prefix-ASN pair is explicitly authorized by a valid ROA
 //! - **Invalid**: The prefix has ROAs but none authorize the given ASN
 //! - **Unknown**: No ROAs exist for the prefix, or all ROAs are outside their validity period
 //!
+//! For incident reports, [`RpkiTrie::validate_detailed`] and
+//! [`RpkiTrie::validate_detailed_check_expiry`] return an [`RpkiValidationDetail`] with the
+//! covering ROAs, the matched ROA, the [`RpkiInvalidReason`] (origin mismatch, max-length
+//! exceeded, AS0 ROA) and the ROAs skipped because they were outside their validity period.
+//!
+//! ## Validation Policies
+//! [`RpkiTrie::validate_with_policy`] (or `BgpkitCommons::rpki_validate_with_policy()`)
+//! validates under an [`RpkiValidationPolicy`] to reproduce a specific validator deployment:
+//! RFC 6483/7607 AS0 semantics, trust-anchor allow and deny lists, and whether expired
+//! ROAs are ignored, make a route `Unknown`, still cover the prefix, or are discarded.
+//!
+//! ## ASPA Path Verification
+//! [`RpkiTrie::verify_aspa_path`] checks a full AS_PATH against the loaded ASPAs following
+//! [draft-ietf-sidrops-aspa-verification](https://datatracker.ietf.org/doc/draft-ietf-sidrops-aspa-verification/).
+//! Routes received from customers or peers are verified as [`AspaDirection::Upstream`],
+//! routes received from providers as [`AspaDirection::Downstream`]. The result carries an
+//! [`AspaValidation`] state, the computed up-ramp and down-ramp, and the hop that failed.
+//!
+//! ## Snapshot Comparison
+//! [`RpkiTrie::diff`] compares two snapshots (e.g. two historical dates) and returns an
+//! [`RpkiDiff`] with ROAs added and removed, max-length changes, ASPA provider-set changes
+//! and expiry changes, which can be grouped per RIR or per origin ASN.
+//!
+//! For large batches such as full RIB dumps, [`RpkiTrie::validate_bulk`] validates
+//! (prefix, ASN) pairs on all CPU cores and returns per-route states with summary counts.
+//!
+//! ## Compact Tries
+//! [`RpkiTrie::to_compact`] builds a read-only [`CompactRpkiTrie`] that stores each ROA as
+//! origin ASN, max-length and an index into shared (RIR, validity period) metadata, and
+//! returns borrowed [`RoaRef`]s from lookups. Validation results are the same as for the
+//! source trie, which makes it suitable for keeping many daily snapshots in memory.
+//!
+//! ## Coverage Statistics
+//! [`RpkiTrie::coverage_report`] summarizes RPKI adoption: ROA count and unique address space
+//! per RIR, origin ASN and country, the share of announced routes that are covered, valid or
+//! invalid, and ASPA adoption. `BgpkitCommons::rpki_coverage_report()` takes countries from
+//! the loaded asinfo data.
+//!
+//! ## Expiry Forecasting
+//! [`RpkiTrie::expiring_between`] and [`RpkiTrie::expiring_within`] list the ROAs and ASPAs
+//! that expire in a time window, grouped per RIR and origin ASN by [`RpkiExpiryReport`].
+//! [`RpkiTrie::expiry_impact`] projects which currently valid announcements would become
+//! `Invalid` or `Unknown` at a future time once their ROAs lapse.
+//!
+//! ## ROA Hygiene
+//! [`RpkiTrie::roa_hygiene`] compares the ROAs of an AS with the prefixes it announces. It
+//! reports ROAs whose max-length authorizes unannounced more-specifics, which a forged-origin
+//! hijack could use (RFC 9319), unused ROAs and unauthorized announcements, and suggests a
+//! minimal ROA set that authorizes exactly the announced prefixes.
+//!
+//! ## Validation Timelines
+//! [`validation_timeline`] (or `BgpkitCommons::rpki_validation_timeline()`) checks a batch of
+//! routes against one archived snapshot per day of a date range and returns the ROV and ASPA
+//! state of each route on each day; [`RpkiTimeline::route_changes`] lists the days a route's
+//! state changed. Each day's snapshot is loaded once for all routes and dropped afterwards.
+//!
+//! ## What-if Simulation
+//! [`RpkiTrie::what_if`] (or `BgpkitCommons::rpki_what_if()`) returns an [`RpkiWhatIf`] view
+//! that records hypothetical ROA and ASPA additions and removals on top of the trie without
+//! copying it. [`RpkiWhatIf::impact`] re-validates a batch of routes and returns the
+//! [`RpkiRouteChange`]s whose route origin validation or ASPA state would change.
+//!
+//! ## Local Exceptions (SLURM)
+//! [`RpkiTrie::apply_slurm`] applies the prefix and BGPsec filters and assertions of a
+//! [`Slurm`] file (RFC 8416), so that validation matches what routers fed by a validator
+//! with the same overrides enforce. Files applied one after another are combined: the filters
+//! of all files never remove an assertion of any of them. [`RpkiTrie::slurm_report`] lists
+//! the VRPs and router keys that were added or suppressed.
+//!
+//! ## VRP Aggregation
+//! [`RpkiTrie::aggregate_vrps`] (or `BgpkitCommons::rpki_aggregate_vrps()`) replaces the ROAs
+//! with the smallest VRP set that validates every route the same way, dropping VRPs covered by
+//! another VRP of the same ASN and widening max-lengths where both halves of a prefix are
+//! authorized. The returned [`VrpAggregationReport`] counts the VRPs saved.
+//!
+//! ## Provenance and Reloading
+//! Every loader records an [`RpkiProvenance`] (source or collector, file URLs, HTTP validators).
+//! [`RpkiTrie::reload`] re-fetches from the same origin, and [`RpkiTrie::reload_with_diff`]
+//! additionally returns an [`RpkiDiff`] of what changed.
+//!
 //! # Usage Examples
 //!
 //! ## Loading Real-time Data (Cloudflare)
@@ -126,6 +243,24 @@
 //! # }
 //! ```
 //!
+//! ## Verifying an AS_PATH with ASPA
+//! ```rust,no_run
+//! use bgpkit_commons::BgpkitCommons;
+//! use bgpkit_commons::rpki::{AspaDirection, AspaValidation};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut commons = BgpkitCommons::new();
+//! commons.load_rpki(None)?;
+//!
+//! // AS_PATH as received from a customer: neighbor first, origin last
+//! let result = commons.rpki_verify_aspa_path(&[64500, 64510, 64520], AspaDirection::Upstream)?;
+//! if result.state == AspaValidation::Invalid {
+//!     println!("route leak detected at hop {:?}", result.failed_hop);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! ## Listing Available Files
 //! ```rust,no_run
 //! use bgpkit_commons::BgpkitCommons;
@@ -146,11 +281,32 @@
 //! # }
 //! ```
 
+mod aggregate;
+mod aspa;
+mod bulk;
 mod cloudflare;
+mod compact;
+mod consensus;
+mod coverage;
+mod diff;
+mod expiry;
+mod hygiene;
+mod index;
+mod local;
+mod policy;
+mod provenance;
 mod ripe_historical;
+mod router_key;
 pub(crate) mod rpki_client;
 mod rpkispools;
 mod rpkiviews;
+pub mod rtr;
+mod slurm;
+mod timeline;
+mod validation;
+mod validator_output;
+mod what_if;
+mod writer;
 
 use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
 use ipnet::IpNet;
@@ -158,16 +314,44 @@ use ipnet_trie::IpnetTrie;
 
 use crate::errors::{load_methods, modules};
 use crate::{BgpkitCommons, BgpkitCommonsError, LazyLoadable, Result};
+pub use aggregate::VrpAggregationReport;
+pub use aspa::{AspaDirection, AspaHop, AspaHopCheck, AspaValidation, AspaVerification};
+pub use bulk::{RpkiBulkSummary, RpkiBulkValidation};
 pub use cloudflare::RpkiLoad;
+pub use compact::{CompactRpkiTrie, RoaMeta, RoaRef};
+pub use consensus::{AspaObservation, RoaObservation, RpkiConsensus, rpki_consensus};
+pub use coverage::{AnnouncedCoverage, AspaAdoption, CountryLookup, RoaSpace, RpkiCoverageReport};
+pub use diff::{
+    AspaExpiryChange, AspaProviderChange, RoaExpiryChange, RoaMaxLengthChange, RpkiDiff,
+};
+pub use expiry::{RpkiExpiryImpact, RpkiExpiryReport};
+pub use hygiene::{MaxLengthExposure, RoaHygieneReport};
+use index::RpkiIndex;
+pub use local::{LocalRpkiArchive, LocalRpkiLayout};
+pub use policy::{ExpiredRoaPolicy, RpkiValidationPolicy};
+pub use provenance::{RpkiFileFormat, RpkiProvenance, RpkiSource};
 pub use ripe_historical::list_ripe_files;
+pub use router_key::RouterKey;
 use rpki_client::RpkiClientData;
 pub use rpkispools::{
-    RpkiSpoolsCollector, RpkiSpoolsData, list_rpkispools_files, parse_ccr, parse_rpkispools_archive,
+    CcrManifest, CcrManifestState, CcrTrustAnchorState, RpkiSpoolsCollector, RpkiSpoolsData,
+    RpkiSpoolsDelta, RpkiSpoolsState, list_rpkispools_deltas, list_rpkispools_files,
+    list_rpkispools_states, parse_ccr, parse_rpkispools_archive,
 };
 pub use rpkiviews::{RpkiViewsCollector, list_rpkiviews_files};
+pub use rtr::{RtrClient, RtrServer, RtrSyncSummary};
 use serde::{Deserialize, Serialize};
+pub use slurm::{
+    Slurm, SlurmAssertions, SlurmBgpsecAssertion, SlurmBgpsecFilter, SlurmFilters,
+    SlurmPrefixAssertion, SlurmPrefixFilter, SlurmReport,
+};
 use std::fmt::Display;
 use std::str::FromStr;
+pub use timeline::{
+    RpkiRouteStatus, RpkiTimeline, RpkiTimelineDay, RpkiTimelineRoute, validation_timeline,
+};
+pub use validation::{RpkiInvalidReason, RpkiValidationDetail};
+pub use what_if::{RpkiRouteChange, RpkiWhatIf};
 
 // ============================================================================
 // Public Data Structures
@@ -241,6 +425,8 @@ pub enum HistoricalRpkiSource {
     RpkiViews(RpkiViewsCollector),
     /// RPKISPOOL collector (tar.zst archives with CCR files)
     RpkiSpools(RpkiSpoolsCollector),
+    /// Local mirror of one of the archives above, read without network access
+    Local(LocalRpkiArchive),
 }
 
 impl std::fmt::Display for HistoricalRpkiSource {
@@ -251,12 +437,13 @@ impl std::fmt::Display for HistoricalRpkiSource {
             HistoricalRpkiSource::RpkiSpools(collector) => {
                 write!(f, "RPKISPOOL ({})", collector)
             }
+            HistoricalRpkiSource::Local(archive) => write!(f, "{}", archive),
         }
     }
 }
 
 /// Regional Internet Registry (RIR).
-#[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Rir {
     AFRINIC,
     APNIC,
@@ -341,12 +528,26 @@ pub type RoaEntry = Roa;
 /// The main RPKI data structure storing ROAs and ASPAs.
 #[derive(Clone)]
 pub struct RpkiTrie {
-    /// Trie mapping IP prefixes to ROA entries
+    /// Trie mapping IP prefixes to ROA entries.
+    ///
+    /// Call [`RpkiTrie::rebuild_indexes`] after modifying it directly.
     pub trie: IpnetTrie<Vec<Roa>>,
-    /// AS Provider Authorizations
+    /// AS Provider Authorizations.
+    ///
+    /// Call [`RpkiTrie::rebuild_indexes`] after modifying it directly.
     pub aspas: Vec<Aspa>,
+    /// BGPsec router keys
+    pub router_keys: Vec<RouterKey>,
     /// Date for historical data (None for real-time)
     date: Option<NaiveDate>,
+    /// Where the data came from, used by `reload`
+    provenance: RpkiProvenance,
+    /// SLURM files applied to the data, re-applied by `reload`
+    slurm: Vec<Slurm>,
+    /// What the applied SLURM files added and suppressed
+    slurm_report: SlurmReport,
+    /// Per-AS indexes over `trie` and `aspas`
+    index: RpkiIndex,
 }
 
 impl Default for RpkiTrie {
@@ -354,6 +555,11 @@ impl Default for RpkiTrie {
         Self {
             trie: IpnetTrie::new(),
             aspas: vec![],
+            router_keys: vec![],
+            provenance: RpkiProvenance::default(),
+            slurm: vec![],
+            slurm_report: SlurmReport::default(),
+            index: RpkiIndex::default(),
             date: None,
         }
     }
@@ -365,6 +571,11 @@ impl RpkiTrie {
         Self {
             trie: IpnetTrie::new(),
             aspas: vec![],
+            router_keys: vec![],
+            provenance: RpkiProvenance::default(),
+            slurm: vec![],
+            slurm_report: SlurmReport::default(),
+            index: RpkiIndex::default(),
             date,
         }
     }
@@ -378,11 +589,13 @@ impl RpkiTrie {
                 if !existing_roas.iter().any(|existing| {
                     existing.asn == roa.asn && existing.max_length == roa.max_length
                 }) {
+                    self.index.add_roa(&roa);
                     existing_roas.push(roa);
                 }
                 false
             }
             None => {
+                self.index.add_roa(&roa);
                 self.trie.insert(roa.prefix, vec![roa]);
                 true
             }
@@ -396,6 +609,53 @@ impl RpkiTrie {
         }
     }
 
+    /// Remove a ROA identified by (prefix, asn, max_length). Returns true if it was present.
+    pub fn remove_roa(&mut self, prefix: &IpNet, asn: u32, max_length: u8) -> bool {
+        let Some(existing_roas) = self.trie.exact_match_mut(*prefix) else {
+            return false;
+        };
+        let before = existing_roas.len();
+        existing_roas.retain(|roa| !(roa.asn == asn && roa.max_length == max_length));
+        let removed = existing_roas.len() != before;
+        if removed {
+            self.index.remove_roa(prefix, asn, existing_roas);
+        }
+        if existing_roas.is_empty() {
+            self.trie.remove(*prefix);
+        }
+        removed
+    }
+
+    /// Insert an ASPA, replacing any existing record for the same customer ASN.
+    ///
+    /// Returns the replaced record, if any.
+    pub fn insert_aspa(&mut self, aspa: Aspa) -> Option<Aspa> {
+        match self.aspa_position_mut(aspa.customer_asn) {
+            Some(position) => {
+                let replaced = std::mem::replace(&mut self.aspas[position], aspa);
+                self.index.remove_aspa_providers(&replaced);
+                self.index.add_aspa(&self.aspas[position], position);
+                Some(replaced)
+            }
+            None => {
+                self.index.add_aspa(&aspa, self.aspas.len());
+                self.aspas.push(aspa);
+                self.sync_aspa_shape();
+                None
+            }
+        }
+    }
+
+    /// Remove the ASPA for a customer ASN. Returns the removed record, if any.
+    pub fn remove_aspa(&mut self, customer_asn: u32) -> Option<Aspa> {
+        let position = self.aspa_position_mut(customer_asn)?;
+        let removed = self.aspas.swap_remove(position);
+        self.index
+            .remove_aspa(&removed, position, self.aspas.get(position));
+        self.sync_aspa_shape();
+        Some(removed)
+    }
+
     /// Convert rpki-client data into an RpkiTrie.
     ///
     /// This is a shared conversion function used by all data sources
@@ -412,8 +672,8 @@ impl RpkiTrie {
 
     /// Merge rpki-client data into this trie.
     ///
-    /// This converts ROAs and ASPAs from rpki-client format and inserts them,
-    /// avoiding duplicates for ASPAs based on customer_asn.
+    /// This converts ROAs, ASPAs and BGPsec router keys from rpki-client format
+    /// and inserts them, avoiding duplicates for ASPAs based on customer_asn.
     pub(crate) fn merge_rpki_client_data(&mut self, data: RpkiClientData) {
         // Convert and insert ROAs
         for roa in data.roas {
@@ -422,8 +682,11 @@ impl RpkiTrie {
                 Err(_) => continue,
             };
             let rir = Rir::from_str(&roa.ta).ok();
-            let not_after =
-                DateTime::from_timestamp(roa.expires as i64, 0).map(|dt| dt.naive_utc());
+            // an expiry of 0 means the source did not record one
+            let not_after = (roa.expires > 0)
+                .then(|| DateTime::from_timestamp(roa.expires as i64, 0))
+                .flatten()
+                .map(|dt| dt.naive_utc());
 
             self.insert_roa(Roa {
                 prefix,
@@ -437,19 +700,33 @@ impl RpkiTrie {
 
         // Convert and merge ASPAs (avoiding duplicates based on customer_asn)
         for aspa in data.aspas {
-            if !self
-                .aspas
-                .iter()
-                .any(|a| a.customer_asn == aspa.customer_asid)
-            {
-                let expires = DateTime::from_timestamp(aspa.expires, 0).map(|dt| dt.naive_utc());
-                self.aspas.push(Aspa {
+            if self.lookup_aspa(aspa.customer_asid).is_none() {
+                let expires = (aspa.expires > 0)
+                    .then(|| DateTime::from_timestamp(aspa.expires, 0))
+                    .flatten()
+                    .map(|dt| dt.naive_utc());
+                self.insert_aspa(Aspa {
                     customer_asn: aspa.customer_asid,
                     providers: aspa.providers,
                     expires,
                 });
             }
         }
+
+        // Convert and insert BGPsec router keys
+        for key in data.bgpsec_keys {
+            let expires = (key.expires > 0)
+                .then(|| DateTime::from_timestamp(key.expires, 0))
+                .flatten()
+                .map(|dt| dt.naive_utc());
+            self.insert_router_key(RouterKey {
+                asn: key.asn,
+                ski: key.ski.to_ascii_uppercase(),
+                pubkey: key.pubkey,
+                ta: (!key.ta.is_empty()).then_some(key.ta),
+                expires,
+            });
+        }
     }
 
     /// Lookup all ROAs that authorize a given prefix (matching ASN and max_length).
@@ -491,21 +768,7 @@ impl RpkiTrie {
     /// - `RpkiValidation::Invalid` if the prefix-asn pair is invalid
     /// - `RpkiValidation::Unknown` if the prefix-asn pair is not found in RPKI
     pub fn validate(&self, prefix: &IpNet, asn: u32) -> RpkiValidation {
-        // First check if there are ANY covering ROAs (regardless of max_length)
-        let covering_roas = self.lookup_covering_roas(prefix);
-        if covering_roas.is_empty() {
-            return RpkiValidation::Unknown;
-        }
-
-        // Now check for valid matches (matching ASN and max_length)
-        let matches = self.lookup_by_prefix(prefix);
-        for roa in matches {
-            if roa.asn == asn && roa.max_length >= prefix.prefix_len() {
-                return RpkiValidation::Valid;
-            }
-        }
-        // There are covering ROAs but none authorize this prefix/ASN
-        RpkiValidation::Invalid
+        self.validation_state(prefix, asn, None)
     }
 
     /// Validate a prefix with an ASN, checking expiry dates.
@@ -520,76 +783,8 @@ impl RpkiTrie {
         asn: u32,
         check_time: Option<NaiveDateTime>,
     ) -> RpkiValidation {
-        // First check if there are ANY covering ROAs (regardless of max_length)
-        let covering_roas = self.lookup_covering_roas(prefix);
-        if covering_roas.is_empty() {
-            return RpkiValidation::Unknown;
-        }
-
         let check_time = check_time.unwrap_or_else(|| Utc::now().naive_utc());
-
-        let mut found_matching_asn = false;
-
-        // Check for valid matches (matching ASN and max_length)
-        let matches = self.lookup_by_prefix(prefix);
-        for roa in matches {
-            if roa.asn == asn && roa.max_length >= prefix.prefix_len() {
-                found_matching_asn = true;
-
-                // Check if ROA is within valid time period
-                let is_valid_time = {
-                    if let Some(not_before) = roa.not_before {
-                        if check_time < not_before {
-                            false // ROA not yet valid
-                        } else {
-                            true
-                        }
-                    } else {
-                        true // no not_before constraint
-                    }
-                } && {
-                    if let Some(not_after) = roa.not_after {
-                        if check_time > not_after {
-                            false // ROA expired
-                        } else {
-                            true
-                        }
-                    } else {
-                        true // no not_after constraint
-                    }
-                };
-
-                if is_valid_time {
-                    return RpkiValidation::Valid;
-                }
-            }
-        }
-
-        // If we found matching ASN but all ROAs are outside valid time range, return Unknown
-        if found_matching_asn {
-            return RpkiValidation::Unknown;
-        }
-
-        // There are covering ROAs but none authorize this prefix/ASN
-        RpkiValidation::Invalid
-    }
-
-    /// Reload the RPKI data from its original source.
-    pub fn reload(&mut self) -> Result<()> {
-        match self.date {
-            Some(date) => {
-                let trie = RpkiTrie::from_ripe_historical(date)?;
-                self.trie = trie.trie;
-                self.aspas = trie.aspas;
-            }
-            None => {
-                let trie = RpkiTrie::from_cloudflare()?;
-                self.trie = trie.trie;
-                self.aspas = trie.aspas;
-            }
-        }
-
-        Ok(())
+        self.validation_state(prefix, asn, Some(check_time))
     }
 }
 
@@ -640,6 +835,20 @@ impl BgpkitCommons {
         Ok(self.rpki_trie.as_ref().unwrap().validate(&prefix, asn))
     }
 
+    /// Validate a prefix-ASN pair under a validation policy.
+    ///
+    /// See [`RpkiTrie::validate_with_policy`] for details.
+    pub fn rpki_validate_with_policy(
+        &self,
+        asn: u32,
+        prefix: &str,
+        policy: &RpkiValidationPolicy,
+    ) -> Result<RpkiValidation> {
+        let trie = self.rpki_trie()?;
+        let prefix = prefix.parse()?;
+        Ok(trie.validate_with_policy(&prefix, asn, policy))
+    }
+
     pub fn rpki_validate_check_expiry(
         &self,
         asn: u32,
@@ -660,6 +869,29 @@ impl BgpkitCommons {
             .validate_check_expiry(&prefix, asn, check_time))
     }
 
+    /// Validate a prefix-ASN pair and explain the result.
+    ///
+    /// See [`RpkiTrie::validate_detailed`] for details.
+    pub fn rpki_validate_detailed(&self, asn: u32, prefix: &str) -> Result<RpkiValidationDetail> {
+        let trie = self.rpki_trie()?;
+        let prefix = prefix.parse()?;
+        Ok(trie.validate_detailed(&prefix, asn))
+    }
+
+    /// Validate a prefix-ASN pair with ROA expiry checking and explain the result.
+    ///
+    /// See [`RpkiTrie::validate_detailed_check_expiry`] for details.
+    pub fn rpki_validate_detailed_check_expiry(
+        &self,
+        asn: u32,
+        prefix: &str,
+        check_time: Option<NaiveDateTime>,
+    ) -> Result<RpkiValidationDetail> {
+        let trie = self.rpki_trie()?;
+        let prefix = prefix.parse()?;
+        Ok(trie.validate_detailed_check_expiry(&prefix, asn, check_time))
+    }
+
     /// Look up ASPA records for a given customer ASN.
     ///
     /// Returns the ASPA record if one exists for the given customer ASN,
@@ -675,17 +907,161 @@ impl BgpkitCommons {
             .rpki_trie
             .as_ref()
             .unwrap()
-            .aspas
-            .iter()
-            .find(|a| a.customer_asn == customer_asn)
+            .lookup_aspa(customer_asn)
             .cloned())
     }
+
+    /// Look up the customer ASNs whose ASPA lists `provider_asn` as a provider.
+    pub fn rpki_lookup_aspa_customers(&self, provider_asn: u32) -> Result<Vec<u32>> {
+        Ok(self.rpki_trie()?.lookup_aspa_customers(provider_asn))
+    }
+
+    /// Validate many (prefix, origin ASN) pairs in parallel.
+    ///
+    /// Takes parsed prefixes to avoid per-call string parsing; see [`RpkiTrie::validate_bulk`].
+    pub fn rpki_validate_bulk<I>(&self, routes: I) -> Result<RpkiBulkValidation>
+    where
+        I: IntoIterator<Item = (IpNet, u32)>,
+    {
+        Ok(self.rpki_trie()?.validate_bulk(routes))
+    }
+
+    /// Compute RPKI coverage statistics of the loaded RPKI data.
+    ///
+    /// `announced` are (prefix, origin ASN) pairs seen in BGP. If asinfo data
+    /// is loaded, ROAs and routes are grouped by the country of their origin
+    /// ASN; see [`RpkiTrie::coverage_report`] to supply another country lookup.
+    pub fn rpki_coverage_report(
+        &self,
+        announced: Option<&[(IpNet, u32)]>,
+    ) -> Result<RpkiCoverageReport> {
+        let trie = self.rpki_trie()?;
+        #[cfg(feature = "asinfo")]
+        if let Some(asinfo) = self.asinfo.as_ref() {
+            let country_of = |_: &IpNet, asn: u32| asinfo.get(asn).map(|info| info.country.clone());
+            return Ok(trie.coverage_report(announced, Some(&country_of)));
+        }
+        Ok(trie.coverage_report(announced, None))
+    }
+
+    /// List the ROAs and ASPAs of the loaded RPKI data expiring within `window` from now.
+    pub fn rpki_expiring_within(&self, window: chrono::TimeDelta) -> Result<RpkiExpiryReport> {
+        Ok(self.rpki_trie()?.expiring_within(window))
+    }
+
+    /// Announcements valid now that would no longer be valid at `at`; see [`RpkiTrie::expiry_impact`].
+    pub fn rpki_expiry_impact(
+        &self,
+        routes: &[(IpNet, u32)],
+        at: NaiveDateTime,
+    ) -> Result<Vec<RpkiExpiryImpact>> {
+        Ok(self.rpki_trie()?.expiry_impact(routes, at, None))
+    }
+
+    /// Analyze the ROAs of an AS against the prefixes it announces; see [`RpkiTrie::roa_hygiene`].
+    pub fn rpki_roa_hygiene(&self, asn: u32, announced: &[IpNet]) -> Result<RoaHygieneReport> {
+        Ok(self.rpki_trie()?.roa_hygiene(asn, announced))
+    }
+
+    /// Start a what-if view of hypothetical ROA and ASPA changes on the loaded RPKI data.
+    ///
+    /// See [`RpkiTrie::what_if`] for details.
+    pub fn rpki_what_if(&self) -> Result<RpkiWhatIf<'_>> {
+        Ok(self.rpki_trie()?.what_if())
+    }
+
+    /// Validate routes against the archived snapshot of each day in a date range.
+    ///
+    /// Does not require RPKI data to be loaded. One snapshot is loaded per
+    /// day and all routes are checked against it; see [`validation_timeline`].
+    pub fn rpki_validation_timeline(
+        &self,
+        source: HistoricalRpkiSource,
+        start: NaiveDate,
+        end: NaiveDate,
+        routes: &[RpkiTimelineRoute],
+    ) -> Result<RpkiTimeline> {
+        validation_timeline(source, start, end, routes)
+    }
+
+    /// Get the loaded RPKI data.
+    pub fn rpki_trie(&self) -> Result<&RpkiTrie> {
+        self.rpki_trie.as_ref().ok_or_else(|| {
+            BgpkitCommonsError::module_not_loaded(modules::RPKI, load_methods::LOAD_RPKI)
+        })
+    }
+
+    /// Look up all ROAs with the given origin ASN.
+    pub fn rpki_lookup_roas_by_asn(&self, asn: u32) -> Result<Vec<Roa>> {
+        Ok(self.rpki_trie()?.lookup_roas_by_asn(asn))
+    }
+
+    /// Look up BGPsec router keys certified for an AS number.
+    pub fn rpki_lookup_router_keys(&self, asn: u32) -> Result<Vec<RouterKey>> {
+        Ok(self.rpki_trie()?.lookup_router_keys_by_asn(asn))
+    }
+
+    /// Look up BGPsec router keys by Subject Key Identifier (hex, case-insensitive).
+    pub fn rpki_lookup_router_keys_by_ski(&self, ski: &str) -> Result<Vec<RouterKey>> {
+        Ok(self.rpki_trie()?.lookup_router_keys_by_ski(ski))
+    }
+
+    /// Apply a SLURM file (RFC 8416) to the loaded RPKI data.
+    ///
+    /// See [`RpkiTrie::apply_slurm`] for details.
+    pub fn rpki_apply_slurm(&mut self, slurm: &Slurm) -> Result<SlurmReport> {
+        match self.rpki_trie.as_mut() {
+            Some(trie) => trie.apply_slurm(slurm),
+            None => Err(BgpkitCommonsError::module_not_loaded(
+                modules::RPKI,
+                load_methods::LOAD_RPKI,
+            )),
+        }
+    }
+
+    /// Aggregate the loaded ROAs into the smallest equivalent VRP set.
+    ///
+    /// See [`RpkiTrie::aggregate_vrps`] for details.
+    pub fn rpki_aggregate_vrps(&mut self) -> Result<VrpAggregationReport> {
+        match self.rpki_trie.as_mut() {
+            Some(trie) => Ok(trie.aggregate_vrps()),
+            None => Err(BgpkitCommonsError::module_not_loaded(
+                modules::RPKI,
+                load_methods::LOAD_RPKI,
+            )),
+        }
+    }
+
+    /// Verify an AS_PATH against the loaded ASPA records.
+    ///
+    /// `as_path` is in BGP order (neighbor first, origin last). See
+    /// [`RpkiTrie::verify_aspa_path`] for details.
+    pub fn rpki_verify_aspa_path(
+        &self,
+        as_path: &[u32],
+        direction: AspaDirection,
+    ) -> Result<AspaVerification> {
+        Ok(self.rpki_trie()?.verify_aspa_path(as_path, direction))
+    }
 }
 
 // ============================================================================
 // Tests
 // ============================================================================
 
+/// A ROA without RIR or validity period, shared by the test modules.
+#[cfg(test)]
+pub(crate) fn roa(prefix: &str, asn: u32, max_length: u8) -> Roa {
+    Roa {
+        prefix: prefix.parse().unwrap(),
+        asn,
+        max_length,
+        rir: None,
+        not_before: None,
+        not_after: None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
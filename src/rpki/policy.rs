@@ -0,0 +1,348 @@
+//! Configurable route origin validation.
+//!
+//! Validator deployments differ in details that change ROV results:
+//!
+//! - AS0 (RFC 6483, RFC 7607): an AS0 ROA says the prefix must not be
+//!   originated. It covers the prefix but never matches a route, and a route
+//!   with origin AS0 is never `Valid`. [`RpkiTrie::validate`] compares AS0
+//!   like any other ASN, so a route originated by AS0 matches an AS0 ROA.
+//! - Trust anchors: operators may run without a TAL (for example ARIN's
+//!   before its relying party agreement was accepted), or use only some.
+//! - Expired ROAs: validators drop them, while archived snapshots may still
+//!   list them.
+//!
+//! [`RpkiValidationPolicy`] captures these choices for
+//! [`RpkiTrie::validate_with_policy`]. The default policy gives the same
+//! results as [`RpkiTrie::validate`].
+//!
+//! Every validation in this crate, with or without a policy and on
+//! [`RpkiTrie`], [`CompactRpkiTrie`](super::CompactRpkiTrie) or
+//! [`RpkiWhatIf`](super::RpkiWhatIf), runs the same loop over the covering
+//! ROAs, so their results cannot drift apart.
+
+use chrono::{NaiveDateTime, Utc};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+
+use super::validation::within_validity;
+use super::{Rir, Roa, RpkiTrie, RpkiValidation};
+
+/// How ROAs outside their validity period are treated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExpiredRoaPolicy {
+    /// Validity periods are not checked, as in [`RpkiTrie::validate`]
+    #[default]
+    Ignore,
+    /// A route matched only by expired ROAs is `Unknown`, as in [`RpkiTrie::validate_check_expiry`]
+    Unknown,
+    /// Expired ROAs still cover the prefix but never match it
+    Covering,
+    /// Expired ROAs are disregarded, as if the validator had dropped them
+    Discard,
+}
+
+/// Options of a route origin validation, see the [module documentation](self).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpkiValidationPolicy {
+    /// Apply RFC 6483/7607 AS0 semantics
+    pub strict_as0: bool,
+    /// Only use ROAs of these trust anchors; `None` uses all.
+    /// ROAs without a known RIR are not used when this is set.
+    pub allowed_rirs: Option<Vec<Rir>>,
+    /// Do not use ROAs of these trust anchors
+    pub denied_rirs: Vec<Rir>,
+    /// Treatment of ROAs outside their validity period
+    pub expired_roas: ExpiredRoaPolicy,
+    /// Time to check validity periods at; `None` means now
+    pub check_time: Option<NaiveDateTime>,
+}
+
+impl RpkiValidationPolicy {
+    /// A policy following the RFCs the way deployed validators do: AS0
+    /// semantics on and expired ROAs disregarded.
+    pub fn rfc() -> Self {
+        Self::default()
+            .with_strict_as0()
+            .with_expired_roas(ExpiredRoaPolicy::Discard)
+    }
+
+    /// Apply RFC 6483/7607 AS0 semantics.
+    pub fn with_strict_as0(mut self) -> Self {
+        self.strict_as0 = true;
+        self
+    }
+
+    /// Only use ROAs of the given trust anchors.
+    pub fn with_allowed_rirs(mut self, rirs: impl IntoIterator<Item = Rir>) -> Self {
+        self.allowed_rirs = Some(rirs.into_iter().collect());
+        self
+    }
+
+    /// Do not use ROAs of the given trust anchors.
+    pub fn with_denied_rirs(mut self, rirs: impl IntoIterator<Item = Rir>) -> Self {
+        self.denied_rirs = rirs.into_iter().collect();
+        self
+    }
+
+    /// Set the treatment of ROAs outside their validity period.
+    pub fn with_expired_roas(mut self, expired_roas: ExpiredRoaPolicy) -> Self {
+        self.expired_roas = expired_roas;
+        self
+    }
+
+    /// Check validity periods at `check_time` instead of now.
+    pub fn with_check_time(mut self, check_time: NaiveDateTime) -> Self {
+        self.check_time = Some(check_time);
+        self
+    }
+
+    /// Whether ROAs of `rir` are used.
+    pub fn uses_rir(&self, rir: Option<Rir>) -> bool {
+        if rir.is_some_and(|rir| self.denied_rirs.contains(&rir)) {
+            return false;
+        }
+        match &self.allowed_rirs {
+            Some(allowed) => rir.is_some_and(|rir| allowed.contains(&rir)),
+            None => true,
+        }
+    }
+
+    /// The policy of [`RpkiTrie::validate`] (`None`) or of
+    /// [`RpkiTrie::validate_check_expiry`] at `check_time`.
+    pub(super) fn check_expiry(check_time: Option<NaiveDateTime>) -> Self {
+        match check_time {
+            None => Self::default(),
+            Some(check_time) => Self::default()
+                .with_expired_roas(ExpiredRoaPolicy::Unknown)
+                .with_check_time(check_time),
+        }
+    }
+}
+
+/// The fields of a ROA that route origin validation looks at.
+pub(super) trait RovRoa {
+    fn asn(&self) -> u32;
+    fn max_length(&self) -> u8;
+    fn rir(&self) -> Option<Rir>;
+    fn is_valid_at(&self, time: NaiveDateTime) -> bool;
+}
+
+impl RovRoa for &Roa {
+    fn asn(&self) -> u32 {
+        self.asn
+    }
+
+    fn max_length(&self) -> u8 {
+        self.max_length
+    }
+
+    fn rir(&self) -> Option<Rir> {
+        self.rir
+    }
+
+    fn is_valid_at(&self, time: NaiveDateTime) -> bool {
+        within_validity(self, time)
+    }
+}
+
+/// Validate a prefix with an ASN against the ROAs covering the prefix.
+///
+/// Returns the state and, for `Valid`, the matching ROA. `on_expired`
+/// receives each ROA that would match but is outside its validity period.
+pub(super) fn validate_origin<R: RovRoa>(
+    covering_roas: impl IntoIterator<Item = R>,
+    prefix: &IpNet,
+    asn: u32,
+    policy: &RpkiValidationPolicy,
+    mut on_expired: impl FnMut(R),
+) -> (RpkiValidation, Option<R>) {
+    let check_time = (policy.expired_roas != ExpiredRoaPolicy::Ignore)
+        .then(|| policy.check_time.unwrap_or_else(|| Utc::now().naive_utc()));
+    let mut covered = false;
+    let mut expired_match = false;
+    for roa in covering_roas {
+        if !policy.uses_rir(roa.rir()) {
+            continue;
+        }
+        let expired = check_time.is_some_and(|time| !roa.is_valid_at(time));
+        if expired && policy.expired_roas == ExpiredRoaPolicy::Discard {
+            continue;
+        }
+        covered = true;
+        if policy.strict_as0 && (roa.asn() == 0 || asn == 0) {
+            continue;
+        }
+        if roa.asn() != asn || roa.max_length() < prefix.prefix_len() {
+            continue;
+        }
+        if !expired {
+            return (RpkiValidation::Valid, Some(roa));
+        }
+        if policy.expired_roas == ExpiredRoaPolicy::Unknown {
+            expired_match = true;
+        }
+        on_expired(roa);
+    }
+    if !covered || expired_match {
+        (RpkiValidation::Unknown, None)
+    } else {
+        (RpkiValidation::Invalid, None)
+    }
+}
+
+impl RpkiTrie {
+    /// Validate a prefix with an ASN under a validation policy.
+    ///
+    /// With [`RpkiValidationPolicy::default`] this is the same as [`RpkiTrie::validate`].
+    pub fn validate_with_policy(
+        &self,
+        prefix: &IpNet,
+        asn: u32,
+        policy: &RpkiValidationPolicy,
+    ) -> RpkiValidation {
+        let covering_roas = self
+            .trie
+            .matches(prefix)
+            .into_iter()
+            .filter(|(p, _)| p.contains(prefix))
+            .flat_map(|(_, roas)| roas);
+        validate_origin(covering_roas, prefix, asn, policy, |_| {}).0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::roa;
+
+    #[test]
+    fn test_as0_and_rir_policies() {
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roas(vec![
+            Roa {
+                rir: Some(Rir::ARIN),
+                ..roa("192.0.2.0/24", 0, 24)
+            },
+            Roa {
+                rir: Some(Rir::ARIN),
+                ..roa("198.51.100.0/24", 64496, 24)
+            },
+            Roa {
+                rir: Some(Rir::RIPENCC),
+                ..roa("198.51.100.0/24", 64497, 24)
+            },
+        ]);
+        let as0: IpNet = "192.0.2.0/24".parse().unwrap();
+        let both: IpNet = "198.51.100.0/24".parse().unwrap();
+
+        let default = RpkiValidationPolicy::default();
+        for (prefix, asn) in [(as0, 0), (as0, 64496), (both, 64496), (both, 64498)] {
+            assert_eq!(
+                trie.validate_with_policy(&prefix, asn, &default),
+                trie.validate(&prefix, asn)
+            );
+        }
+        assert_eq!(trie.validate(&as0, 0), RpkiValidation::Valid);
+
+        let strict = RpkiValidationPolicy::default().with_strict_as0();
+        assert_eq!(
+            trie.validate_with_policy(&as0, 0, &strict),
+            RpkiValidation::Invalid
+        );
+        assert_eq!(
+            trie.validate_with_policy(&as0, 64496, &strict),
+            RpkiValidation::Invalid
+        );
+
+        let no_arin = RpkiValidationPolicy::default().with_denied_rirs([Rir::ARIN]);
+        assert_eq!(
+            trie.validate_with_policy(&as0, 64496, &no_arin),
+            RpkiValidation::Unknown
+        );
+        assert_eq!(
+            trie.validate_with_policy(&both, 64496, &no_arin),
+            RpkiValidation::Invalid
+        );
+        assert_eq!(
+            trie.validate_with_policy(&both, 64497, &no_arin),
+            RpkiValidation::Valid
+        );
+
+        let arin_only = RpkiValidationPolicy::default().with_allowed_rirs([Rir::ARIN]);
+        assert_eq!(
+            trie.validate_with_policy(&both, 64497, &arin_only),
+            RpkiValidation::Invalid
+        );
+        assert!(!arin_only.uses_rir(None));
+        assert!(no_arin.uses_rir(None));
+    }
+
+    #[test]
+    fn test_expired_roa_policies() {
+        let past = chrono::DateTime::from_timestamp(1_600_000_000, 0).map(|t| t.naive_utc());
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roas(vec![
+            Roa {
+                rir: Some(Rir::ARIN),
+                not_after: past,
+                ..roa("192.0.2.0/24", 64496, 24)
+            },
+            Roa {
+                rir: Some(Rir::ARIN),
+                ..roa("192.0.2.0/24", 64497, 24)
+            },
+            Roa {
+                rir: Some(Rir::ARIN),
+                not_after: past,
+                ..roa("198.51.100.0/24", 64496, 24)
+            },
+        ]);
+        let covered: IpNet = "192.0.2.0/24".parse().unwrap();
+        let alone: IpNet = "198.51.100.0/24".parse().unwrap();
+
+        let expect = [
+            (
+                ExpiredRoaPolicy::Ignore,
+                RpkiValidation::Valid,
+                RpkiValidation::Valid,
+            ),
+            (
+                ExpiredRoaPolicy::Unknown,
+                RpkiValidation::Unknown,
+                RpkiValidation::Unknown,
+            ),
+            (
+                ExpiredRoaPolicy::Covering,
+                RpkiValidation::Invalid,
+                RpkiValidation::Invalid,
+            ),
+            (
+                ExpiredRoaPolicy::Discard,
+                RpkiValidation::Invalid,
+                RpkiValidation::Unknown,
+            ),
+        ];
+        for (expired_roas, covered_state, alone_state) in expect {
+            let policy = RpkiValidationPolicy::default().with_expired_roas(expired_roas);
+            assert_eq!(
+                trie.validate_with_policy(&covered, 64496, &policy),
+                covered_state
+            );
+            assert_eq!(
+                trie.validate_with_policy(&alone, 64496, &policy),
+                alone_state
+            );
+        }
+
+        let policy = RpkiValidationPolicy::default().with_expired_roas(ExpiredRoaPolicy::Unknown);
+        assert_eq!(
+            trie.validate_with_policy(&covered, 64496, &policy),
+            trie.validate_check_expiry(&covered, 64496, None)
+        );
+        let before_expiry = policy.with_check_time(past.unwrap());
+        assert_eq!(
+            trie.validate_with_policy(&covered, 64496, &before_expiry),
+            RpkiValidation::Valid
+        );
+    }
+}
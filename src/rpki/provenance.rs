@@ -0,0 +1,346 @@
+//! Provenance of the data held by an [`RpkiTrie`] and source-aware reloading.
+//!
+//! Every loader records where its data came from: the real-time Cloudflare
+//! endpoint (with its HTTP validators), a historical source and date,
+//! explicit file URLs, or an RTR cache. [`RpkiTrie::reload`] and
+//! [`RpkiTrie::reload_with_diff`] use this record to fetch fresh data from
+//! the same origin instead of guessing from the date alone.
+
+use std::net::SocketAddr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{HistoricalRpkiSource, RpkiDiff, RpkiTrie};
+use crate::{BgpkitCommonsError, Result};
+
+/// Format of explicitly given RPKI data files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RpkiFileFormat {
+    /// rpki-client JSON (e.g. RIPE NCC `output.json.xz`)
+    RpkiClientJson,
+    /// RPKIviews `.tgz` archive containing rpki-client JSON
+    RpkiViewsArchive,
+    /// RPKISPOOL `.tar.zst` archive containing CCR files
+    RpkiSpoolsArchive,
+    /// Routinator `json` output
+    RoutinatorJson,
+    /// Routinator `jsonext` output, with the validity of each VRP's source objects
+    RoutinatorExtendedJson,
+    /// Routinator `csv` output
+    RoutinatorCsv,
+    /// Fort JSON output
+    FortJson,
+    /// Fort CSV output
+    FortCsv,
+    /// The `vrps` JSON consumed by StayRTR/GoRTR, e.g. produced by OctoRPKI
+    VrpsJson,
+    /// Plain `ASN,prefix,maxlen[,ta[,not_before,not_after]]` CSV, with an optional header line
+    VrpCsv,
+}
+
+impl From<HistoricalRpkiSource> for RpkiFileFormat {
+    fn from(source: HistoricalRpkiSource) -> Self {
+        match source {
+            HistoricalRpkiSource::Ripe => RpkiFileFormat::RpkiClientJson,
+            HistoricalRpkiSource::RpkiViews(_) => RpkiFileFormat::RpkiViewsArchive,
+            HistoricalRpkiSource::RpkiSpools(_) => RpkiFileFormat::RpkiSpoolsArchive,
+            HistoricalRpkiSource::Local(archive) => archive.layout.file_format(),
+        }
+    }
+}
+
+/// Origin of the data in an [`RpkiTrie`].
+#[derive(Debug, Clone, Default)]
+pub enum RpkiSource {
+    /// Built in memory; there is no origin to reload from
+    #[default]
+    Manual,
+    /// Cloudflare RPKI portal (real-time)
+    Cloudflare,
+    /// A historical source for the trie's date
+    Historical(HistoricalRpkiSource),
+    /// Explicit file URLs in a given format
+    Files(RpkiFileFormat),
+    /// An RPKI-to-Router cache
+    Rtr(Vec<SocketAddr>),
+}
+
+impl std::fmt::Display for RpkiSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RpkiSource::Manual => write!(f, "manual"),
+            RpkiSource::Cloudflare => write!(f, "Cloudflare"),
+            RpkiSource::Historical(source) => write!(f, "{}", source),
+            RpkiSource::Files(format) => write!(f, "files ({:?})", format),
+            RpkiSource::Rtr(addrs) => match addrs.first() {
+                Some(addr) => write!(f, "RTR ({})", addr),
+                None => write!(f, "RTR"),
+            },
+        }
+    }
+}
+
+/// Where the data in an [`RpkiTrie`] came from.
+#[derive(Debug, Clone, Default)]
+pub struct RpkiProvenance {
+    /// The data source
+    pub source: RpkiSource,
+    /// URLs of the files the data was loaded from
+    pub urls: Vec<String>,
+    /// `ETag` returned by the source, used for conditional reloads
+    pub etag: Option<String>,
+    /// `Last-Modified` returned by the source, used for conditional reloads
+    pub last_modified: Option<String>,
+    /// The point in time requested from a source with several states per
+    /// day (RPKISPOOL); `None` loads the day's default state
+    pub time: Option<DateTime<Utc>>,
+    /// The vantage point requested together with `time` (RPKISPOOL); `None`
+    /// uses the vantage point of the first CCR in the archive
+    pub vantage_point: Option<String>,
+}
+
+impl RpkiProvenance {
+    pub(crate) fn new(source: RpkiSource, urls: Vec<String>) -> Self {
+        Self {
+            source,
+            urls,
+            etag: None,
+            last_modified: None,
+            time: None,
+            vantage_point: None,
+        }
+    }
+}
+
+impl RpkiTrie {
+    /// Where the data in this trie came from.
+    pub fn provenance(&self) -> &RpkiProvenance {
+        &self.provenance
+    }
+
+    /// Load RPKI data for a date from a historical source.
+    pub fn from_historical(source: &HistoricalRpkiSource, date: chrono::NaiveDate) -> Result<Self> {
+        match source {
+            HistoricalRpkiSource::Ripe => RpkiTrie::from_ripe_historical(date),
+            HistoricalRpkiSource::RpkiViews(collector) => {
+                RpkiTrie::from_rpkiviews(*collector, date)
+            }
+            HistoricalRpkiSource::RpkiSpools(collector) => {
+                RpkiTrie::from_rpkispools(*collector, date)
+            }
+            HistoricalRpkiSource::Local(archive) => RpkiTrie::from_local_archive(archive, date),
+        }
+    }
+
+    /// Reload the RPKI data from the same origin it was loaded from and report what changed.
+    ///
+    /// - Cloudflare data is re-fetched with a conditional request; an
+    ///   unchanged upstream returns an empty diff without downloading.
+    /// - Historical data is re-fetched from the same source and collector for
+    ///   the trie's date; RPKISPOOL data loaded for a point in time reloads the
+    ///   state closest to that same time.
+    /// - File-based data re-fetches the same URLs.
+    /// - RTR data performs a new full synchronization with the same cache.
+    ///
+    /// Tries built in memory have no origin and return an error.
+    pub fn reload_with_diff(&mut self) -> Result<RpkiDiff> {
+        match self.fetch_from_source()? {
+            Some(fresh) => {
+                let diff = self.diff(&fresh);
+                self.replace_with(fresh);
+                Ok(diff)
+            }
+            None => Ok(RpkiDiff::default()),
+        }
+    }
+
+    /// Reload the RPKI data from the same origin it was loaded from.
+    ///
+    /// See [`RpkiTrie::reload_with_diff`] for how each source is reloaded.
+    pub fn reload(&mut self) -> Result<()> {
+        if let Some(fresh) = self.fetch_from_source()? {
+            self.replace_with(fresh);
+        }
+        Ok(())
+    }
+
+    fn replace_with(&mut self, fresh: RpkiTrie) {
+        self.slurm = fresh.slurm;
+        self.slurm_report = fresh.slurm_report;
+        self.trie = fresh.trie;
+        self.aspas = fresh.aspas;
+        self.router_keys = fresh.router_keys;
+        self.provenance = fresh.provenance;
+        self.index = fresh.index;
+    }
+
+    /// Fetch fresh data from the recorded origin and re-apply the SLURM files
+    /// applied to the current data. `Ok(None)` means the source reported no change.
+    fn fetch_from_source(&self) -> Result<Option<RpkiTrie>> {
+        let provenance = &self.provenance;
+        let mut trie = match (&provenance.source, provenance.time) {
+            (RpkiSource::Historical(HistoricalRpkiSource::RpkiSpools(collector)), Some(time)) => {
+                RpkiTrie::from_rpkispools_at(*collector, time, provenance.vantage_point.as_deref())?
+            }
+            (RpkiSource::Files(RpkiFileFormat::RpkiSpoolsArchive), Some(time)) => {
+                let mut trie = RpkiTrie::new(self.date);
+                for url in &provenance.urls {
+                    trie.merge(RpkiTrie::from_rpkispools_url_at(
+                        url,
+                        time,
+                        provenance.vantage_point.as_deref(),
+                    )?);
+                }
+                trie.provenance = provenance.clone();
+                trie
+            }
+            (RpkiSource::Manual, _) => {
+                return Err(BgpkitCommonsError::data_source_error(
+                    "RPKI",
+                    "trie was built in memory and has no source to reload from",
+                ));
+            }
+            (RpkiSource::Cloudflare, _) => {
+                match RpkiTrie::from_cloudflare_conditional(
+                    provenance.etag.as_deref(),
+                    provenance.last_modified.as_deref(),
+                )? {
+                    Some(load) => load.trie,
+                    None => return Ok(None),
+                }
+            }
+            (RpkiSource::Historical(source), _) => {
+                let Some(date) = self.date else {
+                    return Err(BgpkitCommonsError::data_source_error(
+                        source.to_string(),
+                        "historical trie has no date to reload",
+                    ));
+                };
+                RpkiTrie::from_historical(source, date)?
+            }
+            (RpkiSource::Files(format), _) => {
+                RpkiTrie::from_files(&provenance.urls, *format, self.date)?
+            }
+            (RpkiSource::Rtr(addrs), _) => RpkiTrie::from_rtr(&addrs[..])?,
+        };
+        for slurm in &self.slurm {
+            trie.apply_slurm(slurm)?;
+        }
+        Ok(Some(trie))
+    }
+
+    /// Load RPKI data from file URLs or local paths in the given format.
+    pub fn from_files(
+        urls: &[String],
+        format: RpkiFileFormat,
+        date: Option<chrono::NaiveDate>,
+    ) -> Result<Self> {
+        match format {
+            RpkiFileFormat::RpkiClientJson => RpkiTrie::from_ripe_files(urls, date),
+            RpkiFileFormat::RpkiViewsArchive => RpkiTrie::from_rpkiviews_files(urls, date),
+            RpkiFileFormat::RpkiSpoolsArchive => {
+                let mut trie = RpkiTrie::new(date);
+                for url in urls {
+                    trie.merge(RpkiTrie::from_rpkispools_url(url, date)?);
+                }
+                trie.provenance = RpkiProvenance::new(RpkiSource::Files(format), urls.to_vec());
+                Ok(trie)
+            }
+            _ => RpkiTrie::from_validator_output_files(urls, format, date),
+        }
+    }
+
+    /// Merge the ROAs, ASPAs and router keys of another trie into this one.
+    ///
+    /// ASPAs already present for a customer ASN are kept.
+    fn merge(&mut self, other: RpkiTrie) {
+        for (_, roas) in other.trie.iter() {
+            self.insert_roas(roas.clone());
+        }
+        for aspa in other.aspas {
+            if self.lookup_aspa(aspa.customer_asn).is_none() {
+                self.insert_aspa(aspa);
+            }
+        }
+        for key in other.router_keys {
+            self.insert_router_key(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::Roa;
+    use std::io::Write;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_manual_trie_cannot_reload() {
+        let mut trie = RpkiTrie::new(None);
+        assert!(matches!(trie.provenance().source, RpkiSource::Manual));
+        assert!(trie.reload().is_err());
+    }
+
+    #[test]
+    fn test_file_reload_reports_changes() {
+        // Serve two different versions of the same rpki-client JSON file
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/output.json", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            for asn in [64496, 64497] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buf = [0u8; 4096];
+                let _ = std::io::Read::read(&mut stream, &mut buf).unwrap();
+                let body = format!(
+                    r#"{{"roas":[{{"prefix":"192.0.2.0/24","maxLength":24,"asn":{},"ta":"apnic","expires":0}}]}}"#,
+                    asn
+                );
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+
+        let mut trie = RpkiTrie::from_files(
+            std::slice::from_ref(&url),
+            RpkiFileFormat::RpkiClientJson,
+            None,
+        )
+        .unwrap();
+        assert!(matches!(
+            trie.provenance().source,
+            RpkiSource::Files(RpkiFileFormat::RpkiClientJson)
+        ));
+        assert_eq!(trie.provenance().urls, vec![url]);
+
+        let diff = trie.reload_with_diff().unwrap();
+        server.join().unwrap();
+        let added: Vec<&Roa> = diff.roas_added.iter().collect();
+        assert_eq!(added.len(), 1);
+        assert_eq!(added[0].asn, 64497);
+        assert_eq!(diff.roas_removed.len(), 1);
+        assert_eq!(diff.roas_removed[0].asn, 64496);
+        assert_eq!(
+            trie.lookup_by_prefix(&"192.0.2.0/24".parse().unwrap())[0].asn,
+            64497
+        );
+    }
+
+    #[test]
+    fn test_rtr_provenance() {
+        let server = crate::rpki::RtrServer::new(&RpkiTrie::new(None));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || server.serve(listener));
+
+        let mut trie = RpkiTrie::from_rtr(addr).unwrap();
+        assert!(matches!(&trie.provenance().source, RpkiSource::Rtr(addrs) if addrs == &[addr]));
+        assert!(trie.reload_with_diff().unwrap().is_empty());
+    }
+}
@@ -9,7 +9,9 @@
 
 use crate::Result;
 use crate::rpki::rpki_client::RpkiClientData;
-use crate::rpki::{Rir, RpkiFile, RpkiTrie};
+use crate::rpki::{
+    HistoricalRpkiSource, Rir, RpkiFile, RpkiFileFormat, RpkiProvenance, RpkiSource, RpkiTrie,
+};
 use chrono::{Datelike, NaiveDate, Utc};
 use tracing::info;
 
@@ -58,6 +60,7 @@ impl RpkiTrie {
     /// using the output.json.xz format which contains ROAs and ASPAs.
     pub fn from_ripe_historical(date: NaiveDate) -> Result<Self> {
         let mut trie = RpkiTrie::new(Some(date));
+        let mut urls = vec![];
 
         for rir in [
             Rir::AFRINIC,
@@ -77,8 +80,11 @@ impl RpkiTrie {
 
             let data = RpkiClientData::from_url(&url)?;
             trie.merge_rpki_client_data(data);
+            urls.push(url);
         }
 
+        trie.provenance =
+            RpkiProvenance::new(RpkiSource::Historical(HistoricalRpkiSource::Ripe), urls);
         Ok(trie)
     }
 
@@ -92,6 +98,10 @@ impl RpkiTrie {
             trie.merge_rpki_client_data(data);
         }
 
+        trie.provenance = RpkiProvenance::new(
+            RpkiSource::Files(RpkiFileFormat::RpkiClientJson),
+            urls.to_vec(),
+        );
         Ok(trie)
     }
 }
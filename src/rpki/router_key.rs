@@ -0,0 +1,224 @@
+//! BGPsec router keys.
+//!
+//! Router keys bind a BGPsec router's public key to an AS number
+//! ([RFC 8635]). Validators publish them next to ROAs and ASPAs; the
+//! rpki-client JSON output lists them under `bgpsec_keys` with the Subject
+//! Key Identifier (SKI) in hex and the DER-encoded SubjectPublicKeyInfo in
+//! base64.
+//!
+//! [RFC 8635]: https://www.rfc-editor.org/rfc/rfc8635
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use super::RpkiTrie;
+
+/// A validated BGPsec router key.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouterKey {
+    /// The AS number the key is certified for
+    pub asn: u32,
+    /// Subject Key Identifier, as uppercase hex without separators
+    pub ski: String,
+    /// DER-encoded SubjectPublicKeyInfo, base64-encoded
+    pub pubkey: String,
+    /// Trust anchor the key was validated under (e.g. "ripencc", "arin")
+    pub ta: Option<String>,
+    /// When the router key certificate expires
+    pub expires: Option<NaiveDateTime>,
+}
+
+impl RouterKey {
+    /// Create a router key from the raw SKI and SubjectPublicKeyInfo bytes.
+    pub fn from_bytes(asn: u32, ski: &[u8], spki: &[u8]) -> Self {
+        Self {
+            asn,
+            ski: ski.iter().map(|b| format!("{:02X}", b)).collect(),
+            pubkey: encode_base64(spki),
+            ta: None,
+            expires: None,
+        }
+    }
+
+    /// The Subject Key Identifier as bytes, or `None` if it is not 20 bytes of valid hex.
+    pub fn ski_bytes(&self) -> Option<[u8; 20]> {
+        let ski = normalize_ski(&self.ski);
+        // checking for ASCII hex first also keeps the slicing below on char boundaries
+        if !is_hex_ski(&ski) {
+            return None;
+        }
+        let mut bytes = [0u8; 20];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&ski[2 * i..2 * i + 2], 16).ok()?;
+        }
+        Some(bytes)
+    }
+
+    /// The DER-encoded SubjectPublicKeyInfo, or `None` if the base64 is malformed.
+    pub fn pubkey_bytes(&self) -> Option<Vec<u8>> {
+        decode_base64(&self.pubkey)
+    }
+
+    /// Whether this is the same key for the same AS, ignoring TA and expiry.
+    fn same_key(&self, other: &RouterKey) -> bool {
+        self.asn == other.asn
+            && normalize_ski(&self.ski) == normalize_ski(&other.ski)
+            && self.pubkey == other.pubkey
+    }
+}
+
+impl RpkiTrie {
+    /// Insert a router key. Returns false if the same (ASN, SKI, public key) is already present.
+    pub fn insert_router_key(&mut self, key: RouterKey) -> bool {
+        if self.router_keys.iter().any(|k| k.same_key(&key)) {
+            return false;
+        }
+        self.router_keys.push(key);
+        true
+    }
+
+    /// Remove the router key identified by (ASN, SKI, public key). Returns true if it was present.
+    pub fn remove_router_key(&mut self, key: &RouterKey) -> bool {
+        let before = self.router_keys.len();
+        self.router_keys.retain(|k| !k.same_key(key));
+        self.router_keys.len() != before
+    }
+
+    /// Look up all router keys certified for an AS number.
+    pub fn lookup_router_keys_by_asn(&self, asn: u32) -> Vec<RouterKey> {
+        self.router_keys
+            .iter()
+            .filter(|k| k.asn == asn)
+            .cloned()
+            .collect()
+    }
+
+    /// Look up all router keys with a given Subject Key Identifier.
+    ///
+    /// The SKI is matched case-insensitively and may contain `:` separators.
+    /// One key may be certified for several ASes, so more than one entry can match.
+    pub fn lookup_router_keys_by_ski(&self, ski: &str) -> Vec<RouterKey> {
+        let ski = normalize_ski(ski);
+        self.router_keys
+            .iter()
+            .filter(|k| normalize_ski(&k.ski) == ski)
+            .cloned()
+            .collect()
+    }
+}
+
+pub(crate) fn normalize_ski(ski: &str) -> String {
+    ski.chars()
+        .filter(|c| *c != ':')
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Whether a normalized SKI is 40 hex digits, i.e. a 20-byte SHA-1 hash.
+pub(crate) fn is_hex_ski(ski: &str) -> bool {
+    ski.len() == 40 && ski.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+pub(crate) fn encode_base64(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - 6 * i)) as usize & 0x3f] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode standard or URL-safe base64, with or without padding.
+pub(crate) fn decode_base64(data: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len() / 4 * 3);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in data.bytes().filter(|c| !c.is_ascii_whitespace()) {
+        if c == b'=' {
+            break;
+        }
+        let value = match c {
+            b'-' => 62,
+            b'_' => 63,
+            _ => BASE64_ALPHABET.iter().position(|&a| a == c)? as u32,
+        };
+        buffer = (buffer << 6) | value;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_roundtrip() {
+        for data in [&b""[..], b"f", b"fo", b"foo", b"foob", b"fooba", b"foobar"] {
+            let encoded = encode_base64(data);
+            assert_eq!(decode_base64(&encoded).unwrap(), data);
+        }
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert!(decode_base64("not*base64").is_none());
+        // URL-safe alphabet without padding, as used by SLURM
+        assert_eq!(decode_base64("-_8").unwrap(), vec![0xfb, 0xff]);
+    }
+
+    #[test]
+    fn test_router_key_lookups() {
+        let key = RouterKey::from_bytes(64496, &[0xab; 20], &[0x30, 0x59, 0x30, 0x13]);
+        assert_eq!(key.ski, "AB".repeat(20));
+        assert_eq!(key.ski_bytes(), Some([0xab; 20]));
+        assert_eq!(key.pubkey_bytes().unwrap(), vec![0x30, 0x59, 0x30, 0x13]);
+
+        let mut trie = RpkiTrie::new(None);
+        assert!(trie.insert_router_key(key.clone()));
+        assert!(!trie.insert_router_key(key.clone()));
+        // the same key certified for a second AS
+        assert!(trie.insert_router_key(RouterKey {
+            asn: 64497,
+            ..key.clone()
+        }));
+
+        assert_eq!(trie.lookup_router_keys_by_asn(64496), vec![key.clone()]);
+        let colon_ski = vec!["ab"; 20].join(":");
+        assert_eq!(trie.lookup_router_keys_by_ski(&colon_ski).len(), 2);
+        assert!(trie.lookup_router_keys_by_asn(64498).is_empty());
+
+        assert!(trie.remove_router_key(&key));
+        assert!(!trie.remove_router_key(&key));
+        assert_eq!(trie.router_keys.len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_ski() {
+        let key = |ski: String| RouterKey {
+            ski,
+            ..RouterKey::from_bytes(64496, &[0xab; 20], &[0x30])
+        };
+        // 40 bytes, but a two-byte character straddles a hex pair
+        assert_eq!(key(format!("{}é", "A".repeat(38))).ski_bytes(), None);
+        assert_eq!(key(format!("+{}", "A".repeat(39))).ski_bytes(), None);
+        assert_eq!(key("AB".repeat(19)).ski_bytes(), None);
+    }
+}
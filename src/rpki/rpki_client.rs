@@ -12,7 +12,7 @@ use serde::{Deserialize, Deserializer, Serialize};
 
 /// Custom deserializer for ASN that handles both numeric and string formats.
 /// RIPE uses "AS12345" format, while Cloudflare uses numeric 12345.
-fn deserialize_asn<'de, D>(deserializer: D) -> Result<u32, D::Error>
+pub(crate) fn deserialize_asn<'de, D>(deserializer: D) -> Result<u32, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -100,7 +100,7 @@ where
 
 /// Custom deserializer for provider list that handles both string array and number array.
 /// RIPE uses ["AS123", "AS456"] format, Cloudflare uses [123, 456].
-fn deserialize_providers<'de, D>(deserializer: D) -> Result<Vec<u32>, D::Error>
+pub(crate) fn deserialize_providers<'de, D>(deserializer: D) -> Result<Vec<u32>, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -650,4 +650,35 @@ mod tests {
             Some("2024-06-01T04:22:39Z".to_string())
         );
     }
+
+    #[test]
+    fn test_merge_bgpsec_keys() {
+        let json = r#"{
+            "bgpsec_keys": [
+                {
+                    "asn": 64496,
+                    "ski": "510f485d29a29db7b515f9c478f8ed1ce9b4cc87",
+                    "pubkey": "MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAE",
+                    "ta": "ripencc",
+                    "expires": 1704067200
+                }
+            ]
+        }"#;
+        let data: RpkiClientData = serde_json::from_str(json).unwrap();
+        let trie = crate::rpki::RpkiTrie::from_rpki_client_data(data, None).unwrap();
+        let keys = trie.lookup_router_keys_by_asn(64496);
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].ski, "510F485D29A29DB7B515F9C478F8ED1CE9B4CC87");
+        assert_eq!(keys[0].ta.as_deref(), Some("ripencc"));
+        assert_eq!(
+            keys[0].expires,
+            chrono::DateTime::from_timestamp(1704067200, 0).map(|dt| dt.naive_utc())
+        );
+        assert!(keys[0].ski_bytes().is_some());
+        assert_eq!(
+            trie.lookup_router_keys_by_ski("510F485D29A29DB7B515F9C478F8ED1CE9B4CC87")
+                .len(),
+            1
+        );
+    }
 }
@@ -6,7 +6,9 @@
 //!
 //! Each RPKISPOOL archive contains CCR files from multiple vantage points,
 //! with multiple snapshots per day. CCR files encode VRPs (Validated ROA Payloads)
-//! and VAPs (Validated ASPA Payloads) in DER-encoded ASN.1.
+//! and VAPs (Validated ASPA Payloads) in DER-encoded ASN.1, together with the
+//! manifests, trust anchor keys and BGPsec router keys the validator accepted,
+//! which [`RpkiSpoolsData`] exposes for repository health checks.
 //!
 //! RPKISPOOL data is available from three mirrors:
 //! - <https://josephine.sobornost.net/rpkidata/rpkispools/> (Netherlands)
@@ -17,12 +19,16 @@
 //! [draft-ietf-sidrops-rpki-ccr]: https://datatracker.ietf.org/doc/draft-ietf-sidrops-rpki-ccr/
 
 use crate::Result;
-use crate::rpki::{Aspa, Roa, RpkiCollector, RpkiFile, RpkiTrie};
-use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use crate::rpki::{
+    Aspa, HistoricalRpkiSource, Roa, RouterKey, RpkiCollector, RpkiDiff, RpkiFile, RpkiFileFormat,
+    RpkiProvenance, RpkiSource, RpkiTrie,
+};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, TimeDelta, Utc};
 use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
 use std::io::Read;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ops::ControlFlow;
 use std::str::FromStr;
 use tracing::info;
 
@@ -86,11 +92,89 @@ impl FromStr for RpkiSpoolsCollector {
 }
 
 /// Parsed data from an RPKISPOOL CCR file.
+#[derive(Clone, Debug, Default)]
 pub struct RpkiSpoolsData {
+    /// When the validator produced the CCR
+    pub produced_at: Option<NaiveDateTime>,
     /// Validated ROA Payloads
     pub roas: Vec<Roa>,
     /// Validated ASPA Payloads
     pub aspas: Vec<Aspa>,
+    /// Manifests the validator accepted; `None` if the CCR has no manifest state
+    pub manifests: Option<CcrManifestState>,
+    /// Trust anchor keys in use; `None` if the CCR has no trust anchor state
+    pub trust_anchors: Option<CcrTrustAnchorState>,
+    /// Validated BGPsec router keys
+    pub router_keys: Vec<RouterKey>,
+}
+
+/// ManifestState of a CCR: the current manifest of every publication point.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CcrManifestState {
+    /// One entry per manifest
+    pub manifests: Vec<CcrManifest>,
+    /// The most recent `this_update` of all manifests
+    pub most_recent_update: Option<NaiveDateTime>,
+}
+
+/// A validated manifest (ManifestInstance) of a CCR.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CcrManifest {
+    /// SHA-256 of the manifest object, as uppercase hex
+    pub hash: String,
+    /// Size of the manifest object in bytes
+    pub size: u64,
+    /// Authority Key Identifier of the issuing CA, as uppercase hex
+    pub aki: String,
+    /// Manifest number, as uppercase hex (it may be up to 160 bits long)
+    pub manifest_number: String,
+    /// The manifest's thisUpdate
+    pub this_update: NaiveDateTime,
+    /// Where the manifest is published (rsync and RRDP URIs)
+    pub locations: Vec<String>,
+    /// Subject Key Identifiers of the CA certificates listed on the manifest, as uppercase hex
+    pub subordinates: Vec<String>,
+}
+
+/// TrustAnchorState of a CCR: the trust anchor keys the validator used.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CcrTrustAnchorState {
+    /// Subject Key Identifiers of the trust anchor certificates, as uppercase hex
+    pub skis: Vec<String>,
+}
+
+impl RpkiSpoolsData {
+    /// Manifests whose thisUpdate is more than `max_age` before the CCR was produced.
+    ///
+    /// Returns nothing if the CCR has no manifest state or production time.
+    pub fn stale_manifests(&self, max_age: TimeDelta) -> Vec<&CcrManifest> {
+        let (Some(manifests), Some(produced_at)) = (&self.manifests, self.produced_at) else {
+            return vec![];
+        };
+        manifests
+            .manifests
+            .iter()
+            .filter(|m| produced_at - m.this_update > max_age)
+            .collect()
+    }
+
+    /// The trust anchor SKIs in `expected` that are absent from the trust anchor state.
+    ///
+    /// SKIs are compared case-insensitively, ignoring `:` separators. If the
+    /// CCR has no trust anchor state, all of `expected` is missing.
+    pub fn missing_trust_anchors<'a>(&self, expected: &[&'a str]) -> Vec<&'a str> {
+        let normalize = |ski: &str| ski.replace(':', "").to_ascii_uppercase();
+        let present: Vec<String> = self
+            .trust_anchors
+            .iter()
+            .flat_map(|tas| tas.skis.iter().map(|ski| normalize(ski)))
+            .collect();
+        expected
+            .iter()
+            .filter(|ski| !present.contains(&normalize(ski)))
+            .copied()
+            .collect()
+    }
 }
 
 /// Build the RPKISPOOL changelog archive URL for a given date.
@@ -98,9 +182,13 @@ pub struct RpkiSpoolsData {
 /// The RPKISPOOL archive contains CCR snapshots throughout the day,
 /// which is much more efficient to parse than the initstate archive.
 pub fn rpkispool_url(collector: RpkiSpoolsCollector, date: NaiveDate) -> String {
+    format!("{}/{}", collector.base_url(), rpkispool_path(date))
+}
+
+/// Path of the RPKISPOOL archive of a date, relative to the collector's base URL.
+pub(crate) fn rpkispool_path(date: NaiveDate) -> String {
     format!(
-        "{}/{:04}/{:02}/{:02}/{:04}{:02}{:02}-rpkispool.tar.zst",
-        collector.base_url(),
+        "{:04}/{:02}/{:02}/{:04}{:02}{:02}-rpkispool.tar.zst",
         date.year(),
         date.month(),
         date.day(),
@@ -180,7 +268,7 @@ pub fn parse_ccr(data: &[u8]) -> Result<RpkiSpoolsData> {
     })
 }
 
-/// Parse the outer ContentInfo-like wrapper and extract VRPs/VAPs.
+/// Parse the outer ContentInfo-like wrapper and all CCR states.
 fn parse_ccr_content<S: bcder::decode::Source>(
     cons: &mut bcder::decode::Constructed<S>,
 ) -> std::result::Result<RpkiSpoolsData, bcder::decode::DecodeError<S::Error>> {
@@ -193,6 +281,9 @@ fn parse_ccr_content<S: bcder::decode::Source>(
         // [0] EXPLICIT - the CCR content
         cons.take_constructed_if(Tag::CTX_0, |cons| {
             cons.take_sequence(|cons| {
+                // version [0] INTEGER DEFAULT 0 - omitted in DER when 0
+                cons.take_opt_constructed_if(Tag::CTX_0, |cons| cons.take_u8())?;
+
                 // hashAlg: DigestAlgorithmIdentifier (SEQUENCE { OID })
                 cons.take_sequence(|cons| {
                     let _hash_oid = Oid::take_from(cons)?;
@@ -201,46 +292,236 @@ fn parse_ccr_content<S: bcder::decode::Source>(
                     Ok(())
                 })?;
 
-                // producedAt: GeneralizedTime - skip
-                cons.take_value(|_tag, content| {
-                    content.as_primitive()?.skip_all()?;
-                    Ok(())
-                })?;
+                // producedAt: GeneralizedTime
+                let produced_at = take_generalized_time(cons)?;
 
-                let mut roas = Vec::new();
-                let mut aspas = Vec::new();
+                // [1] ManifestState OPTIONAL
+                let manifests =
+                    cons.take_opt_constructed_if(Tag::CTX_1, |cons| parse_manifest_state(cons))?;
 
-                // [1] ManifestState OPTIONAL - skip
-                cons.take_opt_constructed_if(Tag::CTX_1, |cons| {
-                    // Skip all manifest content
-                    cons.capture_all()?;
-                    Ok(())
-                })?;
+                // [2] ROAPayloadState OPTIONAL
+                let roas = cons
+                    .take_opt_constructed_if(Tag::CTX_2, |cons| parse_roa_payload_state(cons))?
+                    .unwrap_or_default();
+
+                // [3] ASPAPayloadState OPTIONAL
+                let aspas = cons
+                    .take_opt_constructed_if(Tag::CTX_3, |cons| parse_aspa_payload_state(cons))?
+                    .unwrap_or_default();
 
-                // [2] ROAPayloadState OPTIONAL - parse
-                if let Some(roa_data) =
-                    cons.take_opt_constructed_if(Tag::CTX_2, |cons| parse_roa_payload_state(cons))?
-                {
-                    roas = roa_data;
-                }
-
-                // [3] ASPAPayloadState OPTIONAL - parse
-                if let Some(aspa_data) =
-                    cons.take_opt_constructed_if(Tag::CTX_3, |cons| parse_aspa_payload_state(cons))?
-                {
-                    aspas = aspa_data;
-                }
-
-                // Skip remaining optional fields ([4] TAS, [5] RKS, ...)
-                // by consuming all remaining content
+                // [4] TrustAnchorState OPTIONAL
+                let trust_anchors = cons
+                    .take_opt_constructed_if(Tag::CTX_4, |cons| parse_trust_anchor_state(cons))?;
+
+                // [5] RouterKeyState OPTIONAL
+                let router_keys = cons
+                    .take_opt_constructed_if(Tag::CTX_5, |cons| parse_router_key_state(cons))?
+                    .unwrap_or_default();
+
+                // Skip extensions added by later revisions of the format
                 cons.capture_all()?;
 
-                Ok(RpkiSpoolsData { roas, aspas })
+                Ok(RpkiSpoolsData {
+                    produced_at: Some(produced_at),
+                    roas,
+                    aspas,
+                    manifests,
+                    trust_anchors,
+                    router_keys,
+                })
             })
         })
     })
 }
 
+/// Take a GeneralizedTime (`YYYYMMDDHHMMSS[.fff]Z`).
+fn take_generalized_time<S: bcder::decode::Source>(
+    cons: &mut bcder::decode::Constructed<S>,
+) -> std::result::Result<NaiveDateTime, bcder::decode::DecodeError<S::Error>> {
+    let bytes = cons.take_primitive_if(bcder::Tag::GENERALIZED_TIME, |prim| prim.take_all())?;
+    std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(|s| NaiveDateTime::parse_from_str(s, "%Y%m%d%H%M%S%.fZ").ok())
+        .ok_or_else(|| cons.content_err("invalid GeneralizedTime"))
+}
+
+/// Take an OCTET STRING as uppercase hex.
+fn take_hex_octets<S: bcder::decode::Source>(
+    cons: &mut bcder::decode::Constructed<S>,
+) -> std::result::Result<String, bcder::decode::DecodeError<S::Error>> {
+    let octets = bcder::OctetString::take_from(cons)?;
+    Ok(to_hex(octets.to_bytes().as_ref()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// Parse ManifestState:
+/// ```text
+/// ManifestState ::= SEQUENCE {
+///   mis               SEQUENCE OF ManifestInstance,
+///   mostRecentUpdate  GeneralizedTime,
+///   hash              Digest }
+/// ```
+fn parse_manifest_state<S: bcder::decode::Source>(
+    cons: &mut bcder::decode::Constructed<S>,
+) -> std::result::Result<CcrManifestState, bcder::decode::DecodeError<S::Error>> {
+    cons.take_sequence(|cons| {
+        let manifests = cons.take_sequence(|cons| {
+            let mut manifests = Vec::new();
+            while let Some(manifest) =
+                cons.take_opt_sequence(|cons| parse_manifest_instance(cons))?
+            {
+                manifests.push(manifest);
+            }
+            Ok(manifests)
+        })?;
+        let most_recent_update = take_generalized_time(cons)?;
+
+        // hash: Digest (OCTET STRING) - skip
+        cons.capture_all()?;
+
+        Ok(CcrManifestState {
+            manifests,
+            most_recent_update: Some(most_recent_update),
+        })
+    })
+}
+
+/// Parse ManifestInstance:
+/// ```text
+/// ManifestInstance ::= SEQUENCE {
+///   hash            Digest,
+///   size            INTEGER (1000..MAX),
+///   aki             KeyIdentifier,
+///   manifestNumber  CRLNumber,
+///   thisUpdate      GeneralizedTime,
+///   locations       SEQUENCE (SIZE(1..MAX)) OF AccessDescription,
+///   subordinates    SEQUENCE (SIZE(1..MAX)) OF SubjectKeyIdentifier OPTIONAL }
+/// ```
+fn parse_manifest_instance<S: bcder::decode::Source>(
+    cons: &mut bcder::decode::Constructed<S>,
+) -> std::result::Result<CcrManifest, bcder::decode::DecodeError<S::Error>> {
+    let hash = take_hex_octets(cons)?;
+    let size = cons.take_u64()?;
+    let aki = take_hex_octets(cons)?;
+    let manifest_number = bcder::Unsigned::take_from(cons)?;
+    let this_update = take_generalized_time(cons)?;
+
+    // AccessDescription ::= SEQUENCE { accessMethod OID, accessLocation GeneralName }
+    // where the location is a uniformResourceIdentifier [6] IA5String
+    let locations = cons.take_sequence(|cons| {
+        let mut locations = Vec::new();
+        while let Some(location) = cons.take_opt_sequence(|cons| {
+            let _method = bcder::Oid::take_from(cons)?;
+            cons.take_value(|_tag, content| {
+                let uri = content.as_primitive()?.take_all()?;
+                Ok(String::from_utf8_lossy(&uri).to_string())
+            })
+        })? {
+            locations.push(location);
+        }
+        Ok(locations)
+    })?;
+
+    let subordinates = cons
+        .take_opt_sequence(|cons| {
+            let mut skis = Vec::new();
+            while let Some(ski) = bcder::OctetString::take_opt_from(cons)? {
+                skis.push(to_hex(ski.to_bytes().as_ref()));
+            }
+            Ok(skis)
+        })?
+        .unwrap_or_default();
+
+    Ok(CcrManifest {
+        hash,
+        size,
+        aki,
+        manifest_number: to_hex(manifest_number.as_slice()),
+        this_update,
+        locations,
+        subordinates,
+    })
+}
+
+/// Parse TrustAnchorState:
+/// ```text
+/// TrustAnchorState ::= SEQUENCE {
+///   skis  SEQUENCE (SIZE(1..MAX)) OF SubjectKeyIdentifier,
+///   hash  Digest }
+/// ```
+fn parse_trust_anchor_state<S: bcder::decode::Source>(
+    cons: &mut bcder::decode::Constructed<S>,
+) -> std::result::Result<CcrTrustAnchorState, bcder::decode::DecodeError<S::Error>> {
+    cons.take_sequence(|cons| {
+        let skis = cons.take_sequence(|cons| {
+            let mut skis = Vec::new();
+            while let Some(ski) = bcder::OctetString::take_opt_from(cons)? {
+                skis.push(to_hex(ski.to_bytes().as_ref()));
+            }
+            Ok(skis)
+        })?;
+
+        // hash: Digest (OCTET STRING) - skip
+        cons.capture_all()?;
+
+        Ok(CcrTrustAnchorState { skis })
+    })
+}
+
+/// Parse RouterKeyState:
+/// ```text
+/// RouterKeyState ::= SEQUENCE {
+///   rksets  SEQUENCE OF RouterKeySet,
+///   hash    Digest }
+///
+/// RouterKeySet ::= SEQUENCE {
+///   asID        ASID,
+///   routerKeys  SEQUENCE (SIZE(1..MAX)) OF RouterKey }
+///
+/// RouterKey ::= SEQUENCE {
+///   ski   SubjectKeyIdentifier,
+///   spki  SubjectPublicKeyInfo }
+/// ```
+fn parse_router_key_state<S: bcder::decode::Source>(
+    cons: &mut bcder::decode::Constructed<S>,
+) -> std::result::Result<Vec<RouterKey>, bcder::decode::DecodeError<S::Error>> {
+    cons.take_sequence(|cons| {
+        let keys = cons.take_sequence(|cons| {
+            let mut all_keys = Vec::new();
+            while let Some(keys) = cons.take_opt_sequence(|cons| {
+                let asn = cons.take_u32()?;
+                cons.take_sequence(|cons| {
+                    let mut keys = Vec::new();
+                    while let Some(key) = cons.take_opt_sequence(|cons| {
+                        let ski = bcder::OctetString::take_from(cons)?;
+                        // keep the complete DER encoding of the SubjectPublicKeyInfo
+                        let spki = cons.capture_one()?;
+                        Ok(RouterKey::from_bytes(
+                            asn,
+                            ski.to_bytes().as_ref(),
+                            spki.as_slice(),
+                        ))
+                    })? {
+                        keys.push(key);
+                    }
+                    Ok(keys)
+                })
+            })? {
+                all_keys.extend(keys);
+            }
+            Ok(all_keys)
+        })?;
+
+        // hash: Digest (OCTET STRING) - skip
+        cons.capture_all()?;
+
+        Ok(keys)
+    })
+}
+
 /// Parse ROAPayloadState:
 /// ```text
 /// ROAPayloadState ::= SEQUENCE {
@@ -435,22 +716,28 @@ fn parse_aspa_payload_state<S: bcder::decode::Source>(
 // Streaming tar.zst and extracting CCR files
 // ============================================================================
 
-/// Parse an RPKISPOOL archive from a URL, extracting the first CCR file's VRPs and VAPs.
-///
-/// This streams the `.tar.zst` archive and parses the first CCR file found,
-/// which contains all validated ROA and ASPA payloads from one vantage point snapshot.
-pub fn parse_rpkispools_archive(url: &str) -> Result<RpkiSpoolsData> {
+/// Open an RPKISPOOL archive URL or local path.
+fn open_archive(url: &str) -> Result<Box<dyn Read + Send>> {
     info!("streaming RPKISPOOL archive: {}", url);
-
-    let reader = oneio::OneIo::new()
+    oneio::OneIo::new()
         .and_then(|client| client.get_reader_raw(url))
         .map_err(|e| {
             crate::BgpkitCommonsError::data_source_error(
                 "RPKISPOOL",
                 format!("Failed to fetch {}: {}", url, e),
             )
-        })?;
+        })
+}
 
+/// Stream the CCR files of a `.tar.zst` archive in archive order.
+///
+/// `f` receives the path and content of each CCR file and returns
+/// [`ControlFlow::Break`] to stop early.
+fn for_each_ccr<R, F>(reader: R, mut f: F) -> Result<()>
+where
+    R: Read,
+    F: FnMut(&str, Vec<u8>) -> Result<ControlFlow<()>>,
+{
     // Decompress zstd stream
     let decoder = zstd::Decoder::new(reader).map_err(|e| {
         crate::BgpkitCommonsError::data_source_error(
@@ -479,9 +766,7 @@ pub fn parse_rpkispools_archive(url: &str) -> Result<RpkiSpoolsData> {
             Err(_) => continue,
         };
 
-        // Find the first .ccr file
         if path.ends_with(".ccr") {
-            info!("parsing CCR file: {} ({} bytes)", path, entry.size());
             let mut ccr_data = Vec::with_capacity(entry.size() as usize);
             entry.read_to_end(&mut ccr_data).map_err(|e| {
                 crate::BgpkitCommonsError::data_source_error(
@@ -489,15 +774,197 @@ pub fn parse_rpkispools_archive(url: &str) -> Result<RpkiSpoolsData> {
                     format!("Failed to read CCR entry {}: {}", path, e),
                 )
             })?;
-
-            return parse_ccr(&ccr_data);
+            if f(&path, ccr_data)?.is_break() {
+                break;
+            }
         }
     }
+    Ok(())
+}
 
-    Err(crate::BgpkitCommonsError::data_source_error(
+fn no_ccr_error(url: &str) -> crate::BgpkitCommonsError {
+    crate::BgpkitCommonsError::data_source_error(
         "RPKISPOOL",
         format!("No CCR file found in archive: {}", url),
-    ))
+    )
+}
+
+/// Parse an RPKISPOOL archive from a URL, extracting the first CCR file.
+///
+/// This streams the `.tar.zst` archive and parses the first CCR file found,
+/// which contains all validated payloads and the manifest, trust anchor and
+/// router key states from one vantage point snapshot.
+pub fn parse_rpkispools_archive(url: &str) -> Result<RpkiSpoolsData> {
+    let mut data = None;
+    for_each_ccr(open_archive(url)?, |path, ccr_data| {
+        info!("parsing CCR file: {} ({} bytes)", path, ccr_data.len());
+        data = Some(parse_ccr(&ccr_data)?);
+        Ok(ControlFlow::Break(()))
+    })?;
+    data.ok_or_else(|| no_ccr_error(url))
+}
+
+// ============================================================================
+// Intra-day states
+// ============================================================================
+
+/// A CCR state inside an RPKISPOOL archive.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpkiSpoolsState {
+    /// Path of the CCR file inside the archive
+    pub path: String,
+    /// The vantage point that produced the state: the directory of `path`
+    pub vantage_point: String,
+    /// When the validator produced the state
+    pub produced_at: DateTime<Utc>,
+}
+
+impl RpkiSpoolsState {
+    fn new(path: &str, produced_at: NaiveDateTime) -> Self {
+        Self {
+            path: path.to_string(),
+            vantage_point: path
+                .rsplit_once('/')
+                .map(|(dir, _)| dir.to_string())
+                .unwrap_or_default(),
+            produced_at: produced_at.and_utc(),
+        }
+    }
+}
+
+/// The change between two consecutive states of one vantage point.
+#[derive(Clone, Debug)]
+pub struct RpkiSpoolsDelta {
+    /// The earlier state
+    pub from: RpkiSpoolsState,
+    /// The later state
+    pub to: RpkiSpoolsState,
+    /// ROAs and ASPAs added and removed between the two states
+    pub diff: RpkiDiff,
+}
+
+/// Read only the producedAt time of a CCR.
+fn parse_ccr_produced_at(data: &[u8]) -> Result<NaiveDateTime> {
+    use bcder::decode::SliceSource;
+    use bcder::{Mode, Oid, Tag};
+
+    Mode::Der
+        .decode(SliceSource::new(data), |cons| {
+            cons.take_sequence(|cons| {
+                let _oid = Oid::take_from(cons)?;
+                cons.take_constructed_if(Tag::CTX_0, |cons| {
+                    cons.take_sequence(|cons| {
+                        cons.take_opt_constructed_if(Tag::CTX_0, |cons| cons.take_u8())?;
+                        cons.take_sequence(|cons| cons.skip_all())?;
+                        let produced_at = take_generalized_time(cons)?;
+                        cons.skip_all()?;
+                        Ok(produced_at)
+                    })
+                })
+            })
+        })
+        .map_err(|e| {
+            crate::BgpkitCommonsError::data_source_error(
+                "RPKISPOOL",
+                format!("Failed to parse CCR: {}", e),
+            )
+        })
+}
+
+/// List the CCR states in an RPKISPOOL archive, sorted by production time.
+///
+/// Only the CCR headers are parsed, but the whole archive is streamed.
+pub fn list_rpkispools_states(url: &str) -> Result<Vec<RpkiSpoolsState>> {
+    list_states(open_archive(url)?)
+}
+
+fn list_states<R: Read>(reader: R) -> Result<Vec<RpkiSpoolsState>> {
+    let mut states = vec![];
+    for_each_ccr(reader, |path, ccr_data| {
+        states.push(RpkiSpoolsState::new(
+            path,
+            parse_ccr_produced_at(&ccr_data)?,
+        ));
+        Ok(ControlFlow::Continue(()))
+    })?;
+    states.sort_by(|a, b| (a.produced_at, &a.path).cmp(&(b.produced_at, &b.path)));
+    Ok(states)
+}
+
+/// The state of one vantage point closest to `time` and its parsed data; the
+/// earlier state wins ties. `None` uses the vantage point of the first CCR.
+fn closest_state<R: Read>(
+    reader: R,
+    time: DateTime<Utc>,
+    vantage_point: Option<&str>,
+) -> Result<Option<(RpkiSpoolsState, RpkiSpoolsData)>> {
+    let mut vantage_point = vantage_point.map(str::to_string);
+    let mut best: Option<(TimeDelta, RpkiSpoolsState, Vec<u8>)> = None;
+    for_each_ccr(reader, |path, ccr_data| {
+        let state = RpkiSpoolsState::new(path, parse_ccr_produced_at(&ccr_data)?);
+        let vantage_point = vantage_point.get_or_insert_with(|| state.vantage_point.clone());
+        if state.vantage_point != *vantage_point {
+            return Ok(ControlFlow::Continue(()));
+        }
+        let distance = (state.produced_at - time).abs();
+        let closer = match &best {
+            None => true,
+            Some((d, s, _)) => (distance, state.produced_at) < (*d, s.produced_at),
+        };
+        if closer {
+            best = Some((distance, state, ccr_data));
+        }
+        Ok(ControlFlow::Continue(()))
+    })?;
+    best.map(|(_, state, ccr_data)| Ok((state, parse_ccr(&ccr_data)?)))
+        .transpose()
+}
+
+/// Compute the deltas between consecutive states of one vantage point, in production order.
+fn state_deltas<R: Read>(reader: R, vantage_point: Option<&str>) -> Result<Vec<RpkiSpoolsDelta>> {
+    let mut vantage_point = vantage_point.map(str::to_string);
+    let mut ccrs: Vec<(RpkiSpoolsState, Vec<u8>)> = vec![];
+    for_each_ccr(reader, |path, ccr_data| {
+        let state = RpkiSpoolsState::new(path, parse_ccr_produced_at(&ccr_data)?);
+        let vantage_point = vantage_point.get_or_insert_with(|| state.vantage_point.clone());
+        if state.vantage_point == *vantage_point {
+            ccrs.push((state, ccr_data));
+        }
+        Ok(ControlFlow::Continue(()))
+    })?;
+    // the same order as `list_states`
+    ccrs.sort_by(|(a, _), (b, _)| (a.produced_at, &a.path).cmp(&(b.produced_at, &b.path)));
+
+    let mut previous: Option<(RpkiSpoolsState, RpkiTrie)> = None;
+    let mut deltas = vec![];
+    for (state, ccr_data) in ccrs {
+        let data = parse_ccr(&ccr_data)?;
+        let trie = RpkiTrie::from_rpkispools_data(data, Some(state.produced_at.date_naive()));
+        if let Some((from, prev_trie)) = previous.take() {
+            deltas.push(RpkiSpoolsDelta {
+                diff: prev_trie.diff(&trie),
+                from,
+                to: state.clone(),
+            });
+        }
+        previous = Some((state, trie));
+    }
+    Ok(deltas)
+}
+
+/// The changes between consecutive CCR states of one vantage point in an RPKISPOOL archive.
+///
+/// States are compared in order of production time, as listed by
+/// [`list_rpkispools_states`]. `vantage_point` selects the vantage point
+/// (see [`RpkiSpoolsState::vantage_point`]); `None` uses the vantage point of
+/// the first CCR in the archive. A CCR without a readable production time is
+/// an error. The raw CCRs of the vantage point are buffered for sorting, but
+/// at most two parsed states are held in memory at a time.
+pub fn list_rpkispools_deltas(
+    url: &str,
+    vantage_point: Option<&str>,
+) -> Result<Vec<RpkiSpoolsDelta>> {
+    state_deltas(open_archive(url)?, vantage_point)
 }
 
 // ============================================================================
@@ -515,24 +982,78 @@ impl RpkiTrie {
             "loading RPKISPOOL data from {} for date {}",
             collector, date
         );
-        Self::from_rpkispools_url(&url, Some(date))
+        let mut trie = Self::from_rpkispools_url(&url, Some(date))?;
+        trie.provenance.source =
+            RpkiSource::Historical(HistoricalRpkiSource::RpkiSpools(collector));
+        Ok(trie)
     }
 
     /// Load RPKI data from a specific RPKISPOOL archive URL.
     pub fn from_rpkispools_url(url: &str, date: Option<NaiveDate>) -> Result<Self> {
         let data = parse_rpkispools_archive(url)?;
+        let mut trie = Self::from_rpkispools_data(data, date);
+        trie.provenance = RpkiProvenance::new(
+            RpkiSource::Files(RpkiFileFormat::RpkiSpoolsArchive),
+            vec![url.to_string()],
+        );
+        Ok(trie)
+    }
+
+    /// Load the RPKISPOOL state closest to `time`.
+    ///
+    /// Streams the archive of the day of `time` and builds the trie from the
+    /// CCR of `vantage_point` (see [`RpkiSpoolsState::vantage_point`]) whose
+    /// production time is closest to it. `None` uses the vantage point of the
+    /// first CCR in the archive, so that states of different validators are
+    /// not mixed across calls.
+    pub fn from_rpkispools_at(
+        collector: RpkiSpoolsCollector,
+        time: DateTime<Utc>,
+        vantage_point: Option<&str>,
+    ) -> Result<Self> {
+        let url = rpkispool_url(collector, time.date_naive());
+        let mut trie = Self::from_rpkispools_url_at(&url, time, vantage_point)?;
+        trie.provenance.source =
+            RpkiSource::Historical(HistoricalRpkiSource::RpkiSpools(collector));
+        Ok(trie)
+    }
+
+    /// Load the state closest to `time` from a specific RPKISPOOL archive URL.
+    ///
+    /// See [`RpkiTrie::from_rpkispools_at`] for how `vantage_point` is used.
+    pub fn from_rpkispools_url_at(
+        url: &str,
+        time: DateTime<Utc>,
+        vantage_point: Option<&str>,
+    ) -> Result<Self> {
+        let (state, data) = closest_state(open_archive(url)?, time, vantage_point)?
+            .ok_or_else(|| no_ccr_error(url))?;
+        info!(
+            "using CCR {} produced at {} for {}",
+            state.path, state.produced_at, time
+        );
+        let mut trie = Self::from_rpkispools_data(data, Some(state.produced_at.date_naive()));
+        trie.provenance = RpkiProvenance::new(
+            RpkiSource::Files(RpkiFileFormat::RpkiSpoolsArchive),
+            vec![url.to_string()],
+        );
+        trie.provenance.time = Some(time);
+        trie.provenance.vantage_point = vantage_point.map(str::to_string);
+        Ok(trie)
+    }
+
+    fn from_rpkispools_data(data: RpkiSpoolsData, date: Option<NaiveDate>) -> Self {
         let mut trie = RpkiTrie::new(date);
         trie.insert_roas(data.roas);
         for aspa in data.aspas {
-            if !trie
-                .aspas
-                .iter()
-                .any(|a| a.customer_asn == aspa.customer_asn)
-            {
-                trie.aspas.push(aspa);
+            if trie.lookup_aspa(aspa.customer_asn).is_none() {
+                trie.insert_aspa(aspa);
             }
         }
-        Ok(trie)
+        for key in data.router_keys {
+            trie.insert_router_key(key);
+        }
+        trie
     }
 }
 
@@ -581,6 +1102,342 @@ mod tests {
         );
     }
 
+    fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        match content.len() {
+            len @ 0..=127 => out.push(len as u8),
+            len @ 128..=255 => out.extend([0x81, len as u8]),
+            len => out.extend([0x82, (len >> 8) as u8, len as u8]),
+        }
+        out.extend_from_slice(content);
+        out
+    }
+
+    fn seq(parts: &[Vec<u8>]) -> Vec<u8> {
+        tlv(0x30, &parts.concat())
+    }
+
+    fn manifest_instance(this_update: &str, subordinates: bool) -> Vec<u8> {
+        let access = seq(&[
+            // id-ad-rpkiManifest
+            tlv(0x06, &[0x2B, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x0A]),
+            tlv(0x86, b"rsync://rpki.example.net/repo/ca.mft"),
+        ]);
+        let mut fields = vec![
+            tlv(0x04, &[0x11; 32]),
+            tlv(0x02, &[0x05, 0xDC]),
+            tlv(0x04, &[0x22; 20]),
+            tlv(0x02, &[0x05]),
+            tlv(0x18, this_update.as_bytes()),
+            seq(&[access]),
+        ];
+        if subordinates {
+            fields.push(seq(&[tlv(0x04, &[0x33; 20])]));
+        }
+        seq(&fields)
+    }
+
+    /// A CCR with every state present.
+    fn sample_ccr() -> Vec<u8> {
+        ccr_with("20260320120000Z", 64496)
+    }
+
+    /// A CCR produced at `produced_at` whose single ROA has origin `roa_asn` (32768..=65535).
+    fn ccr_with(produced_at: &str, roa_asn: u32) -> Vec<u8> {
+        let digest = tlv(0x04, &[0; 32]);
+        let manifests = seq(&[
+            seq(&[
+                manifest_instance("20260320110000Z", true),
+                manifest_instance("20260310000000Z", false),
+            ]),
+            tlv(0x18, b"20260320110000Z"),
+            digest.clone(),
+        ]);
+        let roa_address = seq(&[tlv(0x03, &[0x00, 0xC0, 0x00, 0x02]), tlv(0x02, &[24])]);
+        let roas = seq(&[
+            seq(&[seq(&[
+                tlv(0x02, &[0x00, (roa_asn >> 8) as u8, roa_asn as u8]),
+                seq(&[seq(&[tlv(0x04, &[0x00, 0x01]), seq(&[roa_address])])]),
+            ])]),
+            digest.clone(),
+        ]);
+        let aspas = seq(&[
+            seq(&[seq(&[
+                tlv(0x02, &[0x00, 0xFB, 0xF0]),
+                seq(&[tlv(0x02, &[0x00, 0xFB, 0xFE])]),
+            ])]),
+            digest.clone(),
+        ]);
+        let trust_anchors = seq(&[seq(&[tlv(0x04, &[0x44; 20])]), digest.clone()]);
+        let spki = seq(&[
+            // id-ecPublicKey
+            seq(&[tlv(0x06, &[0x2A, 0x86, 0x48, 0xCE, 0x3D, 0x02, 0x01])]),
+            tlv(0x03, &[0x00, 0x04]),
+        ]);
+        let router_keys = seq(&[
+            seq(&[seq(&[
+                tlv(0x02, &[0x00, 0xFB, 0xF0]),
+                seq(&[seq(&[tlv(0x04, &[0xAB; 20]), spki])]),
+            ])]),
+            digest,
+        ]);
+
+        let content = seq(&[
+            // SHA-256
+            seq(&[tlv(
+                0x06,
+                &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01],
+            )]),
+            tlv(0x18, produced_at.as_bytes()),
+            tlv(0xA1, &manifests),
+            tlv(0xA2, &roas),
+            tlv(0xA3, &aspas),
+            tlv(0xA4, &trust_anchors),
+            tlv(0xA5, &router_keys),
+        ]);
+        seq(&[
+            tlv(
+                0x06,
+                &[
+                    0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x10, 0x01, 0x36,
+                ],
+            ),
+            tlv(0xA0, &content),
+        ])
+    }
+
+    #[test]
+    fn test_parse_ccr_states() {
+        let data = parse_ccr(&sample_ccr()).unwrap();
+        let time = |s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(data.produced_at, Some(time("2026-03-20 12:00:00")));
+
+        assert_eq!(data.roas.len(), 1);
+        assert_eq!(
+            data.roas[0].prefix,
+            "192.0.2.0/24".parse::<IpNet>().unwrap()
+        );
+        assert_eq!(data.roas[0].asn, 64496);
+        assert_eq!(data.aspas[0].providers, vec![64510]);
+
+        let manifests = data.manifests.as_ref().unwrap();
+        assert_eq!(manifests.manifests.len(), 2);
+        assert_eq!(
+            manifests.most_recent_update,
+            Some(time("2026-03-20 11:00:00"))
+        );
+        let manifest = &manifests.manifests[0];
+        assert_eq!(manifest.size, 1500);
+        assert_eq!(manifest.aki, "22".repeat(20));
+        assert_eq!(manifest.manifest_number, "05");
+        assert_eq!(manifest.this_update, time("2026-03-20 11:00:00"));
+        assert_eq!(
+            manifest.locations,
+            vec!["rsync://rpki.example.net/repo/ca.mft"]
+        );
+        assert_eq!(manifest.subordinates, vec!["33".repeat(20)]);
+        assert!(manifests.manifests[1].subordinates.is_empty());
+
+        let stale = data.stale_manifests(TimeDelta::days(1));
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].this_update, time("2026-03-10 00:00:00"));
+
+        let ta = "44".repeat(20);
+        assert_eq!(data.trust_anchors.as_ref().unwrap().skis, vec![ta.clone()]);
+        let other_ta = "55".repeat(20);
+        assert_eq!(
+            data.missing_trust_anchors(&[&ta.to_lowercase(), &other_ta]),
+            vec![other_ta.as_str()]
+        );
+
+        assert_eq!(data.router_keys.len(), 1);
+        let key = &data.router_keys[0];
+        assert_eq!(key.asn, 64496);
+        assert_eq!(key.ski, "AB".repeat(20));
+        assert_eq!(key.pubkey_bytes().unwrap()[0], 0x30);
+    }
+
+    #[test]
+    fn test_parse_ccr_without_optional_states() {
+        let content = seq(&[
+            seq(&[tlv(
+                0x06,
+                &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01],
+            )]),
+            tlv(0x18, b"20260320120000.5Z"),
+        ]);
+        let minimal = seq(&[
+            tlv(
+                0x06,
+                &[
+                    0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x10, 0x01, 0x36,
+                ],
+            ),
+            tlv(0xA0, &content),
+        ]);
+        let data = parse_ccr(&minimal).unwrap();
+        assert!(data.produced_at.is_some());
+        assert!(data.roas.is_empty());
+        assert!(data.manifests.is_none());
+        assert!(data.trust_anchors.is_none());
+        assert!(data.stale_manifests(TimeDelta::zero()).is_empty());
+        assert!(data.router_keys.is_empty());
+    }
+
+    fn archive(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, data.as_slice())
+                .unwrap();
+        }
+        zstd::encode_all(builder.into_inner().unwrap().as_slice(), 0).unwrap()
+    }
+
+    fn intraday_archive() -> Vec<u8> {
+        archive(&[
+            ("vp1/120000.ccr", ccr_with("20260320120000Z", 64496)),
+            ("README", b"not a CCR".to_vec()),
+            ("vp2/120300.ccr", ccr_with("20260320120300Z", 64499)),
+            ("vp1/120500.ccr", ccr_with("20260320120500Z", 64497)),
+        ])
+    }
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().to_utc()
+    }
+
+    #[test]
+    fn test_list_states() {
+        let states = list_states(intraday_archive().as_slice()).unwrap();
+        let times: Vec<_> = states.iter().map(|s| s.produced_at).collect();
+        assert_eq!(
+            times,
+            vec![
+                at("2026-03-20T12:00:00Z"),
+                at("2026-03-20T12:03:00Z"),
+                at("2026-03-20T12:05:00Z")
+            ]
+        );
+        assert_eq!(states[1].path, "vp2/120300.ccr");
+        assert_eq!(states[1].vantage_point, "vp2");
+    }
+
+    #[test]
+    fn test_closest_state() {
+        let spool = intraday_archive();
+        let (state, data) =
+            closest_state(spool.as_slice(), at("2026-03-20T12:02:00Z"), Some("vp2"))
+                .unwrap()
+                .unwrap();
+        assert_eq!(state.path, "vp2/120300.ccr");
+        assert_eq!(data.roas[0].asn, 64499);
+
+        // the vantage point of the first CCR by default, even when another is closer
+        let (state, _) = closest_state(spool.as_slice(), at("2026-03-20T12:03:00Z"), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.path, "vp1/120500.ccr");
+
+        // equally close: the earlier state wins
+        let (state, _) = closest_state(spool.as_slice(), at("2026-03-20T12:02:30Z"), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.path, "vp1/120000.ccr");
+
+        let (state, _) = closest_state(spool.as_slice(), at("2026-03-21T00:00:00Z"), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.path, "vp1/120500.ccr");
+
+        assert!(
+            closest_state(spool.as_slice(), at("2026-03-20T12:00:00Z"), Some("vp3"))
+                .unwrap()
+                .is_none()
+        );
+        let empty = archive(&[]);
+        assert!(
+            closest_state(empty.as_slice(), at("2026-03-20T12:00:00Z"), None)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_state_deltas() {
+        let archive = intraday_archive();
+        let deltas = state_deltas(archive.as_slice(), None).unwrap();
+        assert_eq!(deltas.len(), 1);
+        let delta = &deltas[0];
+        assert_eq!(delta.from.path, "vp1/120000.ccr");
+        assert_eq!(delta.to.path, "vp1/120500.ccr");
+        assert_eq!(delta.diff.roas_added.len(), 1);
+        assert_eq!(delta.diff.roas_added[0].asn, 64497);
+        assert_eq!(delta.diff.roas_removed[0].asn, 64496);
+        assert!(delta.diff.aspas_added.is_empty());
+
+        assert!(
+            state_deltas(archive.as_slice(), Some("vp2"))
+                .unwrap()
+                .is_empty()
+        );
+
+        // states are diffed in production order, not archive order
+        let archive = self::archive(&[
+            ("vp1/c.ccr", ccr_with("20260320121000Z", 64498)),
+            ("vp1/a.ccr", ccr_with("20260320120000Z", 64496)),
+            ("vp1/b.ccr", ccr_with("20260320120500Z", 64497)),
+        ]);
+        let deltas = state_deltas(archive.as_slice(), None).unwrap();
+        let steps: Vec<(&str, &str)> = deltas
+            .iter()
+            .map(|d| (d.from.path.as_str(), d.to.path.as_str()))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![("vp1/a.ccr", "vp1/b.ccr"), ("vp1/b.ccr", "vp1/c.ccr")]
+        );
+        assert_eq!(deltas[0].diff.roas_added[0].asn, 64497);
+
+        // a CCR without a production time is reported
+        let broken = self::archive(&[("vp1/a.ccr", b"not a CCR".to_vec())]);
+        assert!(state_deltas(broken.as_slice(), None).is_err());
+    }
+
+    #[test]
+    fn test_reload_state_at() {
+        let path = std::env::temp_dir().join(format!(
+            "bgpkit-commons-rpkispool-{}.tar.zst",
+            std::process::id()
+        ));
+        std::fs::write(&path, intraday_archive()).unwrap();
+        let url = path.to_str().unwrap();
+
+        let time = at("2026-03-20T12:05:00Z");
+        let mut trie = RpkiTrie::from_rpkispools_url_at(url, time, None).unwrap();
+        assert_eq!(trie.provenance().time, Some(time));
+        assert_eq!(trie.lookup_roas_by_asn(64497).len(), 1);
+
+        // reloading keeps the requested state instead of the first CCR of the day
+        let diff = trie.reload_with_diff().unwrap();
+        assert!(diff.roas_added.is_empty() && diff.roas_removed.is_empty());
+        assert_eq!(trie.lookup_roas_by_asn(64497).len(), 1);
+        assert!(trie.lookup_roas_by_asn(64496).is_empty());
+        assert_eq!(trie.provenance().time, Some(time));
+
+        // and the requested vantage point
+        let mut trie = RpkiTrie::from_rpkispools_url_at(url, time, Some("vp2")).unwrap();
+        assert_eq!(trie.lookup_roas_by_asn(64499).len(), 1);
+        trie.reload().unwrap();
+        assert_eq!(trie.lookup_roas_by_asn(64499).len(), 1);
+        assert_eq!(trie.provenance().vantage_point.as_deref(), Some("vp2"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
     #[test]
     #[ignore] // Requires network access
     fn test_from_rpkispools() {
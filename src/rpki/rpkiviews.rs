@@ -12,7 +12,10 @@
 
 use crate::Result;
 use crate::rpki::rpki_client::RpkiClientData;
-use crate::rpki::{RpkiCollector, RpkiFile, RpkiTrie};
+use crate::rpki::{
+    HistoricalRpkiSource, RpkiCollector, RpkiFile, RpkiFileFormat, RpkiProvenance, RpkiSource,
+    RpkiTrie,
+};
 use chrono::{DateTime, Datelike, NaiveDate};
 use serde::{Deserialize, Serialize};
 use std::io::{BufRead, Read, Write};
@@ -547,13 +550,20 @@ impl RpkiTrie {
             collector, first_file.timestamp
         );
 
-        Self::from_rpkiviews_file(&first_file.url, Some(date))
+        let mut trie = Self::from_rpkiviews_file(&first_file.url, Some(date))?;
+        trie.provenance.source = RpkiSource::Historical(HistoricalRpkiSource::RpkiViews(collector));
+        Ok(trie)
     }
 
     /// Load RPKI data from a specific RPKIviews .tgz file URL.
     pub fn from_rpkiviews_file(url: &str, date: Option<NaiveDate>) -> Result<Self> {
         let data = stream_tgz_and_extract_json(url)?;
-        Self::from_rpki_client_data(data, date)
+        let mut trie = Self::from_rpki_client_data(data, date)?;
+        trie.provenance = RpkiProvenance::new(
+            RpkiSource::Files(RpkiFileFormat::RpkiViewsArchive),
+            vec![url.to_string()],
+        );
+        Ok(trie)
     }
 
     /// Load RPKI data from multiple RPKIviews file URLs.
@@ -567,6 +577,10 @@ impl RpkiTrie {
             trie.merge_rpki_client_data(data);
         }
 
+        trie.provenance = RpkiProvenance::new(
+            RpkiSource::Files(RpkiFileFormat::RpkiViewsArchive),
+            urls.to_vec(),
+        );
         Ok(trie)
     }
 }
@@ -0,0 +1,700 @@
+//! RTR client that keeps an [`RpkiTrie`] in sync with a validator cache.
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use tracing::{info, warn};
+
+use super::pdu::{RTR_MAX_VERSION, RtrErrorCode, RtrPdu, RtrTiming};
+use crate::errors::data_sources;
+use crate::rpki::{Aspa, Roa, RouterKey, RpkiProvenance, RpkiSource, RpkiTrie};
+use crate::{BgpkitCommonsError, Result};
+
+/// Summary of one completed synchronization with the cache.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RtrSyncSummary {
+    /// Session ID reported by the cache
+    pub session_id: u16,
+    /// Serial number of the data now held by the client
+    pub serial: u32,
+    /// Whether this was a full reload (Reset Query) rather than an incremental update
+    pub reset: bool,
+    /// Number of ROAs announced by the cache
+    pub roas_announced: usize,
+    /// Number of ROAs withdrawn by the cache
+    pub roas_withdrawn: usize,
+    /// Number of ASPAs announced by the cache
+    pub aspas_announced: usize,
+    /// Number of ASPAs withdrawn by the cache
+    pub aspas_withdrawn: usize,
+    /// Number of BGPsec router keys announced by the cache
+    pub router_keys_announced: usize,
+    /// Number of BGPsec router keys withdrawn by the cache
+    pub router_keys_withdrawn: usize,
+}
+
+/// A change received from the cache, applied once End of Data arrives.
+enum RtrChange {
+    Roa(bool, Roa),
+    Aspa(bool, Aspa),
+    RouterKey(bool, RouterKey),
+}
+
+/// Outcome of a query/response exchange.
+enum SyncOutcome {
+    Done(RtrSyncSummary),
+    /// The cache does not support our version and offered a lower one
+    Downgrade(u8),
+}
+
+/// RPKI-to-Router client (RFC 6810, RFC 8210 and draft-ietf-sidrops-8210bis).
+///
+/// The client talks to a validator cache over any `Read + Write` stream and
+/// maintains an [`RpkiTrie`] with the cache's VRPs, router keys and ASPAs. Updates are
+/// collected until the cache sends End of Data and then applied at once, so
+/// [`RtrClient::trie`] never exposes a half-applied delta.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use bgpkit_commons::rpki::RtrClient;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// // Connect to a local validator cache and fetch the full data set
+/// let mut client = RtrClient::connect("127.0.0.1:3323")?;
+/// println!("serial {:?}", client.serial());
+///
+/// // Apply incremental updates whenever the cache sends a Serial Notify
+/// while let Some(summary) = client.update_on_notify()? {
+///     println!("serial {} (+{} -{} ROAs)", summary.serial, summary.roas_announced, summary.roas_withdrawn);
+///     let _ = client.trie().validate(&"192.0.2.0/24".parse()?, 64496);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct RtrClient<S: Read + Write> {
+    stream: S,
+    version: u8,
+    session_id: Option<u16>,
+    serial: Option<u32>,
+    timing: RtrTiming,
+    trie: RpkiTrie,
+}
+
+impl RtrClient<TcpStream> {
+    /// Connect to a cache over TCP and perform the initial full synchronization.
+    ///
+    /// Starts with protocol version 2 and reconnects with a lower version if
+    /// the cache reports that it does not support it. A cache that has no
+    /// data yet is reported as [`BgpkitCommonsError::DataNotAvailable`].
+    pub fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        let addrs: Vec<SocketAddr> = addr.to_socket_addrs()?.collect();
+        let mut version = RTR_MAX_VERSION;
+        loop {
+            let stream = TcpStream::connect(&addrs[..])?;
+            let mut client = RtrClient::with_version(stream, version);
+            match client.sync(true)? {
+                SyncOutcome::Done(summary) => {
+                    info!(
+                        "RTR session {} established at version {}, serial {}",
+                        summary.session_id, client.version, summary.serial
+                    );
+                    return Ok(client);
+                }
+                SyncOutcome::Downgrade(lower) => {
+                    info!(
+                        "RTR cache does not support version {}, retrying with {}",
+                        version, lower
+                    );
+                    version = lower;
+                }
+            }
+        }
+    }
+}
+
+impl<S: Read + Write> RtrClient<S> {
+    /// Create a client on an already-connected stream using protocol version 2.
+    ///
+    /// No data is exchanged until [`RtrClient::reset`] or [`RtrClient::refresh`] is called.
+    pub fn new(stream: S) -> Self {
+        Self::with_version(stream, RTR_MAX_VERSION)
+    }
+
+    /// Create a client on an already-connected stream using a specific protocol version.
+    pub fn with_version(stream: S, version: u8) -> Self {
+        Self {
+            stream,
+            version: version.min(RTR_MAX_VERSION),
+            session_id: None,
+            serial: None,
+            timing: RtrTiming::default(),
+            trie: RpkiTrie::new(None),
+        }
+    }
+
+    /// The negotiated protocol version.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Session ID of the cache, once the first synchronization has completed.
+    pub fn session_id(&self) -> Option<u16> {
+        self.session_id
+    }
+
+    /// Serial number of the data currently held, once the first synchronization has completed.
+    pub fn serial(&self) -> Option<u32> {
+        self.serial
+    }
+
+    /// Refresh, retry and expire intervals announced by the cache.
+    pub fn timing(&self) -> RtrTiming {
+        self.timing
+    }
+
+    /// The RPKI data received from the cache.
+    pub fn trie(&self) -> &RpkiTrie {
+        &self.trie
+    }
+
+    /// Consume the client and return the RPKI data received from the cache.
+    pub fn into_trie(self) -> RpkiTrie {
+        self.trie
+    }
+
+    /// Replace all data with a full copy from the cache (Reset Query).
+    pub fn reset(&mut self) -> Result<RtrSyncSummary> {
+        match self.sync(true)? {
+            SyncOutcome::Done(summary) => Ok(summary),
+            SyncOutcome::Downgrade(lower) => Err(BgpkitCommonsError::data_source_error(
+                data_sources::RTR,
+                format!(
+                    "cache does not support version {}; reconnect with version {}",
+                    self.version, lower
+                ),
+            )),
+        }
+    }
+
+    /// Fetch changes since the current serial (Serial Query).
+    ///
+    /// Falls back to a full [`RtrClient::reset`] if no data has been loaded
+    /// yet or the cache cannot provide an incremental update.
+    pub fn refresh(&mut self) -> Result<RtrSyncSummary> {
+        if self.serial.is_none() {
+            return self.reset();
+        }
+        match self.sync(false)? {
+            SyncOutcome::Done(summary) => Ok(summary),
+            SyncOutcome::Downgrade(lower) => Err(BgpkitCommonsError::data_source_error(
+                data_sources::RTR,
+                format!("cache downgraded mid-session to version {}", lower),
+            )),
+        }
+    }
+
+    /// Block until the cache sends a Serial Notify and return its serial.
+    ///
+    /// Returns `Ok(None)` if the cache closes the connection.
+    pub fn wait_for_notify(&mut self) -> Result<Option<u32>> {
+        loop {
+            match RtrPdu::read_from(&mut self.stream)? {
+                None => return Ok(None),
+                Some((_, RtrPdu::SerialNotify { serial, .. })) => return Ok(Some(serial)),
+                Some((_, RtrPdu::ErrorReport { code, text, .. })) => {
+                    return Err(error_report(code, &text));
+                }
+                Some((_, pdu)) => warn!("ignoring unexpected RTR PDU while idle: {:?}", pdu),
+            }
+        }
+    }
+
+    /// Wait for the next Serial Notify and apply the announced changes.
+    ///
+    /// Returns `Ok(None)` if the cache closes the connection. Notifications for
+    /// a serial the client already holds are skipped.
+    pub fn update_on_notify(&mut self) -> Result<Option<RtrSyncSummary>> {
+        loop {
+            let Some(serial) = self.wait_for_notify()? else {
+                return Ok(None);
+            };
+            if Some(serial) != self.serial {
+                return self.refresh().map(Some);
+            }
+        }
+    }
+
+    fn send(&mut self, pdu: RtrPdu) -> Result<()> {
+        pdu.write_to(&mut self.stream, self.version)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    /// Send a query and read the response up to End of Data.
+    ///
+    /// A Serial Query answered with Cache Reset is followed by a Reset Query.
+    /// No Data Available is returned as [`BgpkitCommonsError::DataNotAvailable`]
+    /// with the retry interval, so the caller can try again later.
+    fn sync(&mut self, reset: bool) -> Result<SyncOutcome> {
+        let query = match (reset, self.session_id, self.serial) {
+            (false, Some(session_id), Some(serial)) => RtrPdu::SerialQuery { session_id, serial },
+            _ => RtrPdu::ResetQuery,
+        };
+        let reset = matches!(query, RtrPdu::ResetQuery);
+        self.send(query)?;
+
+        let mut changes: Vec<RtrChange> = Vec::new();
+        let mut response_session = None;
+        loop {
+            let Some((version, pdu)) = RtrPdu::read_from(&mut self.stream)? else {
+                return Err(BgpkitCommonsError::data_source_error(
+                    data_sources::RTR,
+                    "connection closed before End of Data",
+                ));
+            };
+
+            if let RtrPdu::ErrorReport { code, text, .. } = &pdu {
+                if *code == RtrErrorCode::UnsupportedProtocolVersion
+                    && self.session_id.is_none()
+                    && version < self.version
+                {
+                    return Ok(SyncOutcome::Downgrade(version));
+                }
+                if *code == RtrErrorCode::NoDataAvailable {
+                    return Err(BgpkitCommonsError::data_not_available(
+                        data_sources::RTR,
+                        Duration::from_secs(self.timing.retry.into()),
+                    ));
+                }
+                return Err(error_report(*code, text));
+            }
+
+            if version != self.version {
+                // A cache that only speaks an older version may answer directly in it
+                if self.session_id.is_none() && response_session.is_none() && version < self.version
+                {
+                    self.version = version;
+                } else {
+                    return Err(BgpkitCommonsError::data_source_error(
+                        data_sources::RTR,
+                        format!(
+                            "unexpected protocol version {} (session uses {})",
+                            version, self.version
+                        ),
+                    ));
+                }
+            }
+
+            match pdu {
+                RtrPdu::SerialNotify { .. } => continue,
+                RtrPdu::CacheReset if !reset => {
+                    info!("RTR cache sent Cache Reset, performing full reload");
+                    return self.sync(true);
+                }
+                RtrPdu::CacheResponse { session_id } => {
+                    if !reset && Some(session_id) != self.session_id {
+                        return Err(BgpkitCommonsError::data_source_error(
+                            data_sources::RTR,
+                            format!("cache session changed to {}", session_id),
+                        ));
+                    }
+                    response_session = Some(session_id);
+                }
+                RtrPdu::Prefix {
+                    announce,
+                    prefix,
+                    max_length,
+                    asn,
+                } => changes.push(RtrChange::Roa(
+                    announce,
+                    Roa {
+                        prefix,
+                        asn,
+                        max_length,
+                        rir: None,
+                        not_before: None,
+                        not_after: None,
+                    },
+                )),
+                RtrPdu::Aspa {
+                    announce,
+                    customer_asn,
+                    providers,
+                } => changes.push(RtrChange::Aspa(
+                    announce,
+                    Aspa {
+                        customer_asn,
+                        providers,
+                        expires: None,
+                    },
+                )),
+                RtrPdu::RouterKey {
+                    announce,
+                    ski,
+                    asn,
+                    spki,
+                } => changes.push(RtrChange::RouterKey(
+                    announce,
+                    RouterKey::from_bytes(asn, &ski, &spki),
+                )),
+                RtrPdu::EndOfData {
+                    session_id,
+                    serial,
+                    timing,
+                } => {
+                    if response_session != Some(session_id) {
+                        return Err(BgpkitCommonsError::data_source_error(
+                            data_sources::RTR,
+                            "End of Data without matching Cache Response",
+                        ));
+                    }
+                    let summary = self.apply(changes, reset, session_id, serial);
+                    if let Some(timing) = timing {
+                        self.timing = timing;
+                    }
+                    return Ok(SyncOutcome::Done(summary));
+                }
+                other => {
+                    return Err(BgpkitCommonsError::data_source_error(
+                        data_sources::RTR,
+                        format!("unexpected PDU from cache: {:?}", other),
+                    ));
+                }
+            }
+        }
+    }
+
+    fn apply(
+        &mut self,
+        changes: Vec<RtrChange>,
+        reset: bool,
+        session_id: u16,
+        serial: u32,
+    ) -> RtrSyncSummary {
+        if reset {
+            self.trie = RpkiTrie::new(None);
+        }
+        let mut summary = RtrSyncSummary {
+            session_id,
+            serial,
+            reset,
+            ..Default::default()
+        };
+        for change in changes {
+            match change {
+                RtrChange::Roa(true, roa) => {
+                    self.trie.insert_roa(roa);
+                    summary.roas_announced += 1;
+                }
+                RtrChange::Roa(false, roa) => {
+                    if self.trie.remove_roa(&roa.prefix, roa.asn, roa.max_length) {
+                        summary.roas_withdrawn += 1;
+                    } else {
+                        warn!(
+                            "RTR cache withdrew unknown ROA {} AS{}",
+                            roa.prefix, roa.asn
+                        );
+                    }
+                }
+                RtrChange::Aspa(true, aspa) => {
+                    self.trie.insert_aspa(aspa);
+                    summary.aspas_announced += 1;
+                }
+                RtrChange::Aspa(false, aspa) => {
+                    if self.trie.remove_aspa(aspa.customer_asn).is_some() {
+                        summary.aspas_withdrawn += 1;
+                    } else {
+                        warn!("RTR cache withdrew unknown ASPA AS{}", aspa.customer_asn);
+                    }
+                }
+                RtrChange::RouterKey(true, key) => {
+                    self.trie.insert_router_key(key);
+                    summary.router_keys_announced += 1;
+                }
+                RtrChange::RouterKey(false, key) => {
+                    if self.trie.remove_router_key(&key) {
+                        summary.router_keys_withdrawn += 1;
+                    } else {
+                        warn!(
+                            "RTR cache withdrew unknown router key {} AS{}",
+                            key.ski, key.asn
+                        );
+                    }
+                }
+            }
+        }
+        self.session_id = Some(session_id);
+        self.serial = Some(serial);
+        summary
+    }
+}
+
+fn error_report(code: RtrErrorCode, text: &str) -> BgpkitCommonsError {
+    BgpkitCommonsError::data_source_error(
+        data_sources::RTR,
+        format!("cache reported error: {} ({})", code, text),
+    )
+}
+
+impl RpkiTrie {
+    /// Load the full VRP and ASPA set from an RTR cache over TCP.
+    ///
+    /// For a long-lived connection that follows incremental updates, use
+    /// [`RtrClient`] directly.
+    pub fn from_rtr(addr: impl ToSocketAddrs) -> Result<Self> {
+        let addrs: Vec<SocketAddr> = addr.to_socket_addrs()?.collect();
+        let mut trie = RtrClient::connect(&addrs[..])?.into_trie();
+        trie.provenance = RpkiProvenance::new(RpkiSource::Rtr(addrs), vec![]);
+        Ok(trie)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::RpkiValidation;
+    use ipnet::IpNet;
+    use std::net::TcpListener;
+
+    fn prefix(announce: bool, prefix: &str, max_length: u8, asn: u32) -> RtrPdu {
+        RtrPdu::Prefix {
+            announce,
+            prefix: prefix.parse().unwrap(),
+            max_length,
+            asn,
+        }
+    }
+
+    fn end_of_data(session_id: u16, serial: u32) -> RtrPdu {
+        RtrPdu::EndOfData {
+            session_id,
+            serial,
+            timing: Some(RtrTiming {
+                refresh: 60,
+                retry: 30,
+                expire: 600,
+            }),
+        }
+    }
+
+    /// Spawn a scripted mock cache. For each accepted connection, the script
+    /// receives the stream and answers queries in order.
+    fn mock_cache<F>(script: F) -> (SocketAddr, std::thread::JoinHandle<()>)
+    where
+        F: FnOnce(&TcpListener) + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = std::thread::spawn(move || script(&listener));
+        (addr, handle)
+    }
+
+    fn expect_query(stream: &mut TcpStream) -> (u8, RtrPdu) {
+        RtrPdu::read_from(stream).unwrap().unwrap()
+    }
+
+    fn send_all(stream: &mut TcpStream, version: u8, pdus: &[RtrPdu]) {
+        for pdu in pdus {
+            pdu.write_to(stream, version).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_reset_and_incremental_update() {
+        let (addr, cache) = mock_cache(|listener| {
+            let (mut stream, _) = listener.accept().unwrap();
+            assert_eq!(expect_query(&mut stream), (2, RtrPdu::ResetQuery));
+            send_all(
+                &mut stream,
+                2,
+                &[
+                    RtrPdu::CacheResponse { session_id: 9 },
+                    prefix(true, "192.0.2.0/24", 24, 64496),
+                    prefix(true, "198.51.100.0/22", 24, 64497),
+                    prefix(true, "2001:db8::/32", 48, 64498),
+                    RtrPdu::Aspa {
+                        announce: true,
+                        customer_asn: 64496,
+                        providers: vec![64500],
+                    },
+                    end_of_data(9, 1),
+                ],
+            );
+
+            send_all(
+                &mut stream,
+                2,
+                &[RtrPdu::SerialNotify {
+                    session_id: 9,
+                    serial: 2,
+                }],
+            );
+            assert_eq!(
+                expect_query(&mut stream),
+                (
+                    2,
+                    RtrPdu::SerialQuery {
+                        session_id: 9,
+                        serial: 1
+                    }
+                )
+            );
+            send_all(
+                &mut stream,
+                2,
+                &[
+                    RtrPdu::CacheResponse { session_id: 9 },
+                    prefix(false, "198.51.100.0/22", 24, 64497),
+                    prefix(true, "203.0.113.0/24", 24, 64499),
+                    RtrPdu::Aspa {
+                        announce: true,
+                        customer_asn: 64496,
+                        providers: vec![64500, 64501],
+                    },
+                    end_of_data(9, 2),
+                ],
+            );
+        });
+
+        let mut client = RtrClient::connect(addr).unwrap();
+        assert_eq!(client.version(), 2);
+        assert_eq!(client.session_id(), Some(9));
+        assert_eq!(client.serial(), Some(1));
+        assert_eq!(client.timing().refresh, 60);
+
+        let trie = client.trie();
+        let p: IpNet = "198.51.100.0/24".parse().unwrap();
+        assert_eq!(trie.validate(&p, 64497), RpkiValidation::Valid);
+        assert_eq!(trie.aspas.len(), 1);
+
+        let summary = client.update_on_notify().unwrap().unwrap();
+        assert!(!summary.reset);
+        assert_eq!(summary.serial, 2);
+        assert_eq!(summary.roas_announced, 1);
+        assert_eq!(summary.roas_withdrawn, 1);
+        assert_eq!(summary.aspas_announced, 1);
+
+        let trie = client.trie();
+        assert_eq!(trie.validate(&p, 64497), RpkiValidation::Unknown);
+        let p: IpNet = "203.0.113.0/24".parse().unwrap();
+        assert_eq!(trie.validate(&p, 64499), RpkiValidation::Valid);
+        assert_eq!(trie.aspas[0].providers, vec![64500, 64501]);
+
+        cache.join().unwrap();
+        // Connection closed by the cache
+        assert!(client.update_on_notify().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_cache_reset_falls_back_to_full_reload() {
+        let (addr, cache) = mock_cache(|listener| {
+            let (mut stream, _) = listener.accept().unwrap();
+            expect_query(&mut stream);
+            send_all(
+                &mut stream,
+                2,
+                &[
+                    RtrPdu::CacheResponse { session_id: 1 },
+                    prefix(true, "192.0.2.0/24", 24, 64496),
+                    end_of_data(1, 10),
+                ],
+            );
+            assert!(matches!(
+                expect_query(&mut stream).1,
+                RtrPdu::SerialQuery { serial: 10, .. }
+            ));
+            send_all(&mut stream, 2, &[RtrPdu::CacheReset]);
+            assert_eq!(expect_query(&mut stream).1, RtrPdu::ResetQuery);
+            send_all(
+                &mut stream,
+                2,
+                &[
+                    RtrPdu::CacheResponse { session_id: 2 },
+                    prefix(true, "203.0.113.0/24", 24, 64499),
+                    end_of_data(2, 1),
+                ],
+            );
+        });
+
+        let mut client = RtrClient::connect(addr).unwrap();
+        let summary = client.refresh().unwrap();
+        assert!(summary.reset);
+        assert_eq!(client.session_id(), Some(2));
+        assert_eq!(client.serial(), Some(1));
+        let p: IpNet = "192.0.2.0/24".parse().unwrap();
+        assert_eq!(client.trie().validate(&p, 64496), RpkiValidation::Unknown);
+        cache.join().unwrap();
+    }
+
+    #[test]
+    fn test_version_downgrade() {
+        let (addr, cache) = mock_cache(|listener| {
+            // First connection: reject version 2
+            let (mut stream, _) = listener.accept().unwrap();
+            let (version, query) = expect_query(&mut stream);
+            assert_eq!(version, 2);
+            send_all(
+                &mut stream,
+                0,
+                &[RtrPdu::ErrorReport {
+                    code: RtrErrorCode::UnsupportedProtocolVersion,
+                    pdu: query.encode(version),
+                    text: String::new(),
+                }],
+            );
+            drop(stream);
+
+            // Second connection: speak version 0
+            let (mut stream, _) = listener.accept().unwrap();
+            assert_eq!(expect_query(&mut stream), (0, RtrPdu::ResetQuery));
+            send_all(
+                &mut stream,
+                0,
+                &[
+                    RtrPdu::CacheResponse { session_id: 3 },
+                    prefix(true, "192.0.2.0/24", 24, 64496),
+                    RtrPdu::EndOfData {
+                        session_id: 3,
+                        serial: 5,
+                        timing: None,
+                    },
+                ],
+            );
+        });
+
+        let client = RtrClient::connect(addr).unwrap();
+        assert_eq!(client.version(), 0);
+        assert_eq!(client.timing(), RtrTiming::default());
+        let trie = client.into_trie();
+        let p: IpNet = "192.0.2.0/24".parse().unwrap();
+        assert_eq!(trie.validate(&p, 64496), RpkiValidation::Valid);
+        cache.join().unwrap();
+    }
+
+    #[test]
+    fn test_no_data_available() {
+        let (addr, cache) = mock_cache(|listener| {
+            let (mut stream, _) = listener.accept().unwrap();
+            let (version, query) = expect_query(&mut stream);
+            send_all(
+                &mut stream,
+                version,
+                &[RtrPdu::ErrorReport {
+                    code: RtrErrorCode::NoDataAvailable,
+                    pdu: query.encode(version),
+                    text: "still validating".to_string(),
+                }],
+            );
+        });
+
+        let error = RpkiTrie::from_rtr(addr).err().unwrap();
+        assert!(matches!(
+            error,
+            BgpkitCommonsError::DataNotAvailable { retry_after, .. }
+                if retry_after == Duration::from_secs(600)
+        ));
+        cache.join().unwrap();
+    }
+}
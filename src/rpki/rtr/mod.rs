@@ -0,0 +1,26 @@
+//! RPKI-to-Router (RTR) protocol support.
+//!
+//! RTR ([RFC 6810], [RFC 8210], [draft-ietf-sidrops-8210bis]) is the protocol
+//! routers use to fetch validated RPKI payloads from a local validator cache
+//! such as rpki-client/StayRTR, Routinator or Fort.
+//!
+//! - [`RtrClient`] connects to a cache and builds an [`RpkiTrie`](crate::rpki::RpkiTrie)
+//!   from the cache's VRPs and ASPAs, following incremental updates announced by
+//!   Serial Notify PDUs. Analytics built on the resulting trie see exactly the
+//!   same data set as the routers fed by that cache.
+//! - [`RtrServer`] is the other side: it serves any loaded trie to routers,
+//!   computing incremental deltas between reloads. A historical RPKIviews or
+//!   RPKISPOOL snapshot can be replayed to a lab router this way.
+//! - [`RtrPdu`] encodes and decodes the individual protocol data units.
+//!
+//! [RFC 6810]: https://www.rfc-editor.org/rfc/rfc6810
+//! [RFC 8210]: https://www.rfc-editor.org/rfc/rfc8210
+//! [draft-ietf-sidrops-8210bis]: https://datatracker.ietf.org/doc/draft-ietf-sidrops-8210bis/
+
+mod client;
+mod pdu;
+mod server;
+
+pub use client::{RtrClient, RtrSyncSummary};
+pub use pdu::{RTR_MAX_VERSION, RtrErrorCode, RtrPdu, RtrTiming};
+pub use server::RtrServer;
@@ -0,0 +1,622 @@
+//! RPKI-to-Router protocol data units.
+//!
+//! Encoding and decoding of the PDUs defined in [RFC 6810] (version 0),
+//! [RFC 8210] (version 1) and [draft-ietf-sidrops-8210bis] (version 2, which
+//! adds the ASPA PDU).
+//!
+//! Every PDU starts with the same 8-byte header:
+//! ```text
+//! 0          8          16         24        31
+//! +----------+----------+----------+----------+
+//! | Version  | PDU Type | Session ID / Flags  |
+//! +----------+----------+----------+----------+
+//! |                  Length                   |
+//! +----------+----------+----------+----------+
+//! ```
+//!
+//! [RFC 6810]: https://www.rfc-editor.org/rfc/rfc6810
+//! [RFC 8210]: https://www.rfc-editor.org/rfc/rfc8210
+//! [draft-ietf-sidrops-8210bis]: https://datatracker.ietf.org/doc/draft-ietf-sidrops-8210bis/
+
+use crate::{BgpkitCommonsError, Result};
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Largest PDU accepted from a peer. Bounds allocations for Error Report and ASPA PDUs.
+const MAX_PDU_LENGTH: u32 = 1 << 20;
+
+const HEADER_LENGTH: usize = 8;
+
+const PDU_SERIAL_NOTIFY: u8 = 0;
+const PDU_SERIAL_QUERY: u8 = 1;
+const PDU_RESET_QUERY: u8 = 2;
+const PDU_CACHE_RESPONSE: u8 = 3;
+const PDU_IPV4_PREFIX: u8 = 4;
+const PDU_IPV6_PREFIX: u8 = 6;
+const PDU_END_OF_DATA: u8 = 7;
+const PDU_CACHE_RESET: u8 = 8;
+const PDU_ROUTER_KEY: u8 = 9;
+const PDU_ERROR_REPORT: u8 = 10;
+const PDU_ASPA: u8 = 11;
+
+/// Flag bit marking an announcement (set) or a withdrawal (clear).
+const FLAG_ANNOUNCE: u8 = 0x01;
+
+/// Highest RTR protocol version supported.
+pub const RTR_MAX_VERSION: u8 = 2;
+
+/// Timing parameters carried in End of Data PDUs (version 1 and later), in seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RtrTiming {
+    /// How long a router should wait before polling the cache again
+    pub refresh: u32,
+    /// How long a router should wait before retrying a failed poll
+    pub retry: u32,
+    /// How long a router may keep using data without a successful refresh
+    pub expire: u32,
+}
+
+impl Default for RtrTiming {
+    /// The defaults recommended by RFC 8210 section 6.
+    fn default() -> Self {
+        Self {
+            refresh: 3600,
+            retry: 600,
+            expire: 7200,
+        }
+    }
+}
+
+/// Error codes used in Error Report PDUs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RtrErrorCode {
+    CorruptData,
+    InternalError,
+    NoDataAvailable,
+    InvalidRequest,
+    UnsupportedProtocolVersion,
+    UnsupportedPduType,
+    WithdrawalOfUnknownRecord,
+    DuplicateAnnouncement,
+    UnexpectedProtocolVersion,
+    AspaProviderListError,
+    /// A code not known to this implementation
+    Other(u16),
+}
+
+impl From<u16> for RtrErrorCode {
+    fn from(code: u16) -> Self {
+        match code {
+            0 => RtrErrorCode::CorruptData,
+            1 => RtrErrorCode::InternalError,
+            2 => RtrErrorCode::NoDataAvailable,
+            3 => RtrErrorCode::InvalidRequest,
+            4 => RtrErrorCode::UnsupportedProtocolVersion,
+            5 => RtrErrorCode::UnsupportedPduType,
+            6 => RtrErrorCode::WithdrawalOfUnknownRecord,
+            7 => RtrErrorCode::DuplicateAnnouncement,
+            8 => RtrErrorCode::UnexpectedProtocolVersion,
+            9 => RtrErrorCode::AspaProviderListError,
+            other => RtrErrorCode::Other(other),
+        }
+    }
+}
+
+impl From<RtrErrorCode> for u16 {
+    fn from(code: RtrErrorCode) -> Self {
+        match code {
+            RtrErrorCode::CorruptData => 0,
+            RtrErrorCode::InternalError => 1,
+            RtrErrorCode::NoDataAvailable => 2,
+            RtrErrorCode::InvalidRequest => 3,
+            RtrErrorCode::UnsupportedProtocolVersion => 4,
+            RtrErrorCode::UnsupportedPduType => 5,
+            RtrErrorCode::WithdrawalOfUnknownRecord => 6,
+            RtrErrorCode::DuplicateAnnouncement => 7,
+            RtrErrorCode::UnexpectedProtocolVersion => 8,
+            RtrErrorCode::AspaProviderListError => 9,
+            RtrErrorCode::Other(other) => other,
+        }
+    }
+}
+
+impl std::fmt::Display for RtrErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RtrErrorCode::CorruptData => write!(f, "corrupt data"),
+            RtrErrorCode::InternalError => write!(f, "internal error"),
+            RtrErrorCode::NoDataAvailable => write!(f, "no data available"),
+            RtrErrorCode::InvalidRequest => write!(f, "invalid request"),
+            RtrErrorCode::UnsupportedProtocolVersion => write!(f, "unsupported protocol version"),
+            RtrErrorCode::UnsupportedPduType => write!(f, "unsupported PDU type"),
+            RtrErrorCode::WithdrawalOfUnknownRecord => write!(f, "withdrawal of unknown record"),
+            RtrErrorCode::DuplicateAnnouncement => write!(f, "duplicate announcement"),
+            RtrErrorCode::UnexpectedProtocolVersion => write!(f, "unexpected protocol version"),
+            RtrErrorCode::AspaProviderListError => write!(f, "ASPA provider list error"),
+            RtrErrorCode::Other(code) => write!(f, "error code {}", code),
+        }
+    }
+}
+
+/// A decoded RTR protocol data unit.
+///
+/// The protocol version is not part of the enum; it is passed to
+/// [`RtrPdu::encode`] and returned by [`RtrPdu::read_from`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RtrPdu {
+    /// Cache tells the router that new data is available
+    SerialNotify { session_id: u16, serial: u32 },
+    /// Router asks for changes since `serial`
+    SerialQuery { session_id: u16, serial: u32 },
+    /// Router asks for the full data set
+    ResetQuery,
+    /// Cache starts answering a query
+    CacheResponse { session_id: u16 },
+    /// IPv4 or IPv6 prefix announcement or withdrawal
+    Prefix {
+        announce: bool,
+        prefix: IpNet,
+        max_length: u8,
+        asn: u32,
+    },
+    /// Cache finished answering a query. `timing` is present for version 1 and later.
+    EndOfData {
+        session_id: u16,
+        serial: u32,
+        timing: Option<RtrTiming>,
+    },
+    /// Cache cannot provide incremental data; the router must send a Reset Query
+    CacheReset,
+    /// BGPsec router key announcement or withdrawal (version 1 and later)
+    RouterKey {
+        announce: bool,
+        ski: [u8; 20],
+        asn: u32,
+        spki: Vec<u8>,
+    },
+    /// Error report, fatal unless the code is NoDataAvailable
+    ErrorReport {
+        code: RtrErrorCode,
+        pdu: Vec<u8>,
+        text: String,
+    },
+    /// ASPA announcement or withdrawal (version 2)
+    Aspa {
+        announce: bool,
+        customer_asn: u32,
+        providers: Vec<u32>,
+    },
+}
+
+impl RtrPdu {
+    /// Encode this PDU for the given protocol version.
+    pub fn encode(&self, version: u8) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(32);
+        match self {
+            RtrPdu::SerialNotify { session_id, serial } => {
+                put_header(&mut buf, version, PDU_SERIAL_NOTIFY, *session_id);
+                buf.extend_from_slice(&serial.to_be_bytes());
+            }
+            RtrPdu::SerialQuery { session_id, serial } => {
+                put_header(&mut buf, version, PDU_SERIAL_QUERY, *session_id);
+                buf.extend_from_slice(&serial.to_be_bytes());
+            }
+            RtrPdu::ResetQuery => put_header(&mut buf, version, PDU_RESET_QUERY, 0),
+            RtrPdu::CacheResponse { session_id } => {
+                put_header(&mut buf, version, PDU_CACHE_RESPONSE, *session_id)
+            }
+            RtrPdu::Prefix {
+                announce,
+                prefix,
+                max_length,
+                asn,
+            } => {
+                let pdu_type = match prefix {
+                    IpNet::V4(_) => PDU_IPV4_PREFIX,
+                    IpNet::V6(_) => PDU_IPV6_PREFIX,
+                };
+                put_header(&mut buf, version, pdu_type, 0);
+                buf.push(if *announce { FLAG_ANNOUNCE } else { 0 });
+                buf.push(prefix.prefix_len());
+                buf.push(*max_length);
+                buf.push(0);
+                match prefix {
+                    IpNet::V4(net) => buf.extend_from_slice(&net.network().octets()),
+                    IpNet::V6(net) => buf.extend_from_slice(&net.network().octets()),
+                }
+                buf.extend_from_slice(&asn.to_be_bytes());
+            }
+            RtrPdu::EndOfData {
+                session_id,
+                serial,
+                timing,
+            } => {
+                put_header(&mut buf, version, PDU_END_OF_DATA, *session_id);
+                buf.extend_from_slice(&serial.to_be_bytes());
+                if version >= 1 {
+                    let timing = timing.unwrap_or_default();
+                    buf.extend_from_slice(&timing.refresh.to_be_bytes());
+                    buf.extend_from_slice(&timing.retry.to_be_bytes());
+                    buf.extend_from_slice(&timing.expire.to_be_bytes());
+                }
+            }
+            RtrPdu::CacheReset => put_header(&mut buf, version, PDU_CACHE_RESET, 0),
+            RtrPdu::RouterKey {
+                announce,
+                ski,
+                asn,
+                spki,
+            } => {
+                let flags = if *announce { FLAG_ANNOUNCE } else { 0 };
+                put_header(&mut buf, version, PDU_ROUTER_KEY, (flags as u16) << 8);
+                buf.extend_from_slice(ski);
+                buf.extend_from_slice(&asn.to_be_bytes());
+                buf.extend_from_slice(spki);
+            }
+            RtrPdu::ErrorReport { code, pdu, text } => {
+                put_header(&mut buf, version, PDU_ERROR_REPORT, u16::from(*code));
+                buf.extend_from_slice(&(pdu.len() as u32).to_be_bytes());
+                buf.extend_from_slice(pdu);
+                buf.extend_from_slice(&(text.len() as u32).to_be_bytes());
+                buf.extend_from_slice(text.as_bytes());
+            }
+            RtrPdu::Aspa {
+                announce,
+                customer_asn,
+                providers,
+            } => {
+                let flags = if *announce { FLAG_ANNOUNCE } else { 0 };
+                put_header(&mut buf, version, PDU_ASPA, (flags as u16) << 8);
+                buf.extend_from_slice(&customer_asn.to_be_bytes());
+                if *announce {
+                    for provider in providers {
+                        buf.extend_from_slice(&provider.to_be_bytes());
+                    }
+                }
+            }
+        }
+        let length = buf.len() as u32;
+        buf[4..8].copy_from_slice(&length.to_be_bytes());
+        buf
+    }
+
+    /// Write this PDU to a stream.
+    pub fn write_to<W: Write>(&self, writer: &mut W, version: u8) -> Result<()> {
+        writer.write_all(&self.encode(version))?;
+        Ok(())
+    }
+
+    /// Read one PDU from a stream, returning the protocol version it was sent with.
+    ///
+    /// Returns `Ok(None)` if the stream is closed cleanly before a new PDU starts.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Option<(u8, RtrPdu)>> {
+        let mut header = [0u8; HEADER_LENGTH];
+        let mut filled = 0;
+        while filled < HEADER_LENGTH {
+            match reader.read(&mut header[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into()),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let length = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        if (length as usize) < HEADER_LENGTH || length > MAX_PDU_LENGTH {
+            return Err(corrupt(format!("invalid PDU length {}", length)));
+        }
+        let mut body = vec![0u8; length as usize - HEADER_LENGTH];
+        reader.read_exact(&mut body)?;
+
+        let mut pdu = header.to_vec();
+        pdu.extend_from_slice(&body);
+        Self::decode(&pdu).map(Some)
+    }
+
+    /// Decode a complete PDU (header included), returning the protocol version and the PDU.
+    pub fn decode(data: &[u8]) -> Result<(u8, RtrPdu)> {
+        if data.len() < HEADER_LENGTH {
+            return Err(corrupt("PDU shorter than header"));
+        }
+        let version = data[0];
+        let pdu_type = data[1];
+        let session = u16::from_be_bytes([data[2], data[3]]);
+        let length = u32::from_be_bytes([data[4], data[5], data[6], data[7]]) as usize;
+        if length != data.len() {
+            return Err(corrupt(format!(
+                "PDU length field {} does not match {} bytes",
+                length,
+                data.len()
+            )));
+        }
+        let body = &data[HEADER_LENGTH..];
+        let expect_len = |expected: usize| -> Result<()> {
+            if length != expected {
+                return Err(corrupt(format!(
+                    "PDU type {} has length {}, expected {}",
+                    pdu_type, length, expected
+                )));
+            }
+            Ok(())
+        };
+
+        let pdu = match pdu_type {
+            PDU_SERIAL_NOTIFY => {
+                expect_len(12)?;
+                RtrPdu::SerialNotify {
+                    session_id: session,
+                    serial: be_u32(body, 0),
+                }
+            }
+            PDU_SERIAL_QUERY => {
+                expect_len(12)?;
+                RtrPdu::SerialQuery {
+                    session_id: session,
+                    serial: be_u32(body, 0),
+                }
+            }
+            PDU_RESET_QUERY => {
+                expect_len(8)?;
+                RtrPdu::ResetQuery
+            }
+            PDU_CACHE_RESPONSE => {
+                expect_len(8)?;
+                RtrPdu::CacheResponse {
+                    session_id: session,
+                }
+            }
+            PDU_IPV4_PREFIX => {
+                expect_len(20)?;
+                let prefix_len = body[1];
+                let addr = Ipv4Addr::new(body[4], body[5], body[6], body[7]);
+                let prefix = Ipv4Net::new(addr, prefix_len)
+                    .map_err(|_| corrupt(format!("invalid IPv4 prefix length {}", prefix_len)))?;
+                RtrPdu::Prefix {
+                    announce: body[0] & FLAG_ANNOUNCE != 0,
+                    prefix: IpNet::V4(prefix.trunc()),
+                    max_length: body[2],
+                    asn: be_u32(body, 8),
+                }
+            }
+            PDU_IPV6_PREFIX => {
+                expect_len(32)?;
+                let prefix_len = body[1];
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(&body[4..20]);
+                let prefix = Ipv6Net::new(Ipv6Addr::from(octets), prefix_len)
+                    .map_err(|_| corrupt(format!("invalid IPv6 prefix length {}", prefix_len)))?;
+                RtrPdu::Prefix {
+                    announce: body[0] & FLAG_ANNOUNCE != 0,
+                    prefix: IpNet::V6(prefix.trunc()),
+                    max_length: body[2],
+                    asn: be_u32(body, 20),
+                }
+            }
+            PDU_END_OF_DATA => {
+                if version == 0 {
+                    expect_len(12)?;
+                    RtrPdu::EndOfData {
+                        session_id: session,
+                        serial: be_u32(body, 0),
+                        timing: None,
+                    }
+                } else {
+                    expect_len(24)?;
+                    RtrPdu::EndOfData {
+                        session_id: session,
+                        serial: be_u32(body, 0),
+                        timing: Some(RtrTiming {
+                            refresh: be_u32(body, 4),
+                            retry: be_u32(body, 8),
+                            expire: be_u32(body, 12),
+                        }),
+                    }
+                }
+            }
+            PDU_CACHE_RESET => {
+                expect_len(8)?;
+                RtrPdu::CacheReset
+            }
+            PDU_ROUTER_KEY => {
+                if length < HEADER_LENGTH + 24 {
+                    return Err(corrupt("router key PDU too short"));
+                }
+                let mut ski = [0u8; 20];
+                ski.copy_from_slice(&body[0..20]);
+                RtrPdu::RouterKey {
+                    announce: data[2] & FLAG_ANNOUNCE != 0,
+                    ski,
+                    asn: be_u32(body, 20),
+                    spki: body[24..].to_vec(),
+                }
+            }
+            PDU_ERROR_REPORT => {
+                if body.len() < 4 {
+                    return Err(corrupt("error report PDU too short"));
+                }
+                let pdu_len = be_u32(body, 0) as usize;
+                if body.len() < 8 + pdu_len {
+                    return Err(corrupt("error report encapsulated PDU overflows"));
+                }
+                let pdu = body[4..4 + pdu_len].to_vec();
+                let text_len = be_u32(body, 4 + pdu_len) as usize;
+                let text_start = 8 + pdu_len;
+                if body.len() != text_start + text_len {
+                    return Err(corrupt("error report text length mismatch"));
+                }
+                RtrPdu::ErrorReport {
+                    code: RtrErrorCode::from(session),
+                    pdu,
+                    text: String::from_utf8_lossy(&body[text_start..]).to_string(),
+                }
+            }
+            PDU_ASPA => {
+                if length < HEADER_LENGTH + 4 || !(length - HEADER_LENGTH).is_multiple_of(4) {
+                    return Err(corrupt(format!("invalid ASPA PDU length {}", length)));
+                }
+                let providers = body[4..]
+                    .chunks_exact(4)
+                    .map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+                    .collect();
+                RtrPdu::Aspa {
+                    announce: data[2] & FLAG_ANNOUNCE != 0,
+                    customer_asn: be_u32(body, 0),
+                    providers,
+                }
+            }
+            other => {
+                return Err(BgpkitCommonsError::invalid_format(
+                    "RTR PDU",
+                    format!("type {}", other),
+                    "unsupported PDU type",
+                ));
+            }
+        };
+        Ok((version, pdu))
+    }
+}
+
+fn put_header(buf: &mut Vec<u8>, version: u8, pdu_type: u8, session: u16) {
+    buf.push(version);
+    buf.push(pdu_type);
+    buf.extend_from_slice(&session.to_be_bytes());
+    // length is patched in by `encode` once the body is written
+    buf.extend_from_slice(&[0, 0, 0, 0]);
+}
+
+fn be_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+fn corrupt(reason: impl Into<String>) -> BgpkitCommonsError {
+    BgpkitCommonsError::invalid_format("RTR PDU", "", reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(pdu: RtrPdu, version: u8) {
+        let bytes = pdu.encode(version);
+        let (decoded_version, decoded) = RtrPdu::decode(&bytes).unwrap();
+        assert_eq!(decoded_version, version);
+        assert_eq!(decoded, pdu);
+    }
+
+    #[test]
+    fn test_pdu_roundtrip() {
+        roundtrip(
+            RtrPdu::SerialNotify {
+                session_id: 7,
+                serial: 42,
+            },
+            1,
+        );
+        roundtrip(RtrPdu::ResetQuery, 2);
+        roundtrip(
+            RtrPdu::Prefix {
+                announce: true,
+                prefix: "192.0.2.0/24".parse().unwrap(),
+                max_length: 24,
+                asn: 64496,
+            },
+            0,
+        );
+        roundtrip(
+            RtrPdu::Prefix {
+                announce: false,
+                prefix: "2001:db8::/32".parse().unwrap(),
+                max_length: 48,
+                asn: 64497,
+            },
+            2,
+        );
+        roundtrip(
+            RtrPdu::EndOfData {
+                session_id: 7,
+                serial: 42,
+                timing: Some(RtrTiming::default()),
+            },
+            2,
+        );
+        roundtrip(
+            RtrPdu::EndOfData {
+                session_id: 7,
+                serial: 42,
+                timing: None,
+            },
+            0,
+        );
+        roundtrip(
+            RtrPdu::RouterKey {
+                announce: true,
+                ski: [1; 20],
+                asn: 64496,
+                spki: vec![0x30, 0x59, 0x30, 0x13],
+            },
+            1,
+        );
+        roundtrip(
+            RtrPdu::ErrorReport {
+                code: RtrErrorCode::NoDataAvailable,
+                pdu: RtrPdu::ResetQuery.encode(1),
+                text: "not ready".to_string(),
+            },
+            1,
+        );
+        roundtrip(
+            RtrPdu::Aspa {
+                announce: true,
+                customer_asn: 64496,
+                providers: vec![64500, 64501],
+            },
+            2,
+        );
+    }
+
+    #[test]
+    fn test_prefix_wire_format() {
+        let pdu = RtrPdu::Prefix {
+            announce: true,
+            prefix: "192.0.2.0/24".parse().unwrap(),
+            max_length: 24,
+            asn: 64496,
+        };
+        assert_eq!(
+            pdu.encode(1),
+            vec![
+                1, 4, 0, 0, 0, 0, 0, 20, // header
+                1, 24, 24, 0, // flags, prefix length, max length, zero
+                192, 0, 2, 0, // prefix
+                0, 0, 0xfb, 0xf0, // ASN 64496
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_rejects_bad_length() {
+        let mut bytes = RtrPdu::ResetQuery.encode(1);
+        bytes[7] = 12;
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+        assert!(RtrPdu::decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_read_from_clean_eof() {
+        let mut empty: &[u8] = &[];
+        assert!(RtrPdu::read_from(&mut empty).unwrap().is_none());
+
+        let bytes = RtrPdu::CacheReset.encode(2);
+        let mut reader: &[u8] = &bytes;
+        let (version, pdu) = RtrPdu::read_from(&mut reader).unwrap().unwrap();
+        assert_eq!(version, 2);
+        assert_eq!(pdu, RtrPdu::CacheReset);
+    }
+}
@@ -0,0 +1,743 @@
+//! RTR cache server that hands an [`RpkiTrie`] to routers.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::hash::{BuildHasher, Hasher};
+use std::io::Write;
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ipnet::IpNet;
+use tracing::{info, warn};
+
+use super::client::RtrSyncSummary;
+use super::pdu::{RTR_MAX_VERSION, RtrErrorCode, RtrPdu, RtrTiming};
+use crate::Result;
+use crate::rpki::RpkiTrie;
+
+/// Number of deltas kept for answering Serial Queries. Routers further behind get a Cache Reset.
+const MAX_DELTAS: usize = 64;
+
+/// How long a write to a router may block before the connection is given up.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A single payload as seen on the wire.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Payload {
+    Roa {
+        prefix: IpNet,
+        max_length: u8,
+        asn: u32,
+    },
+    RouterKey {
+        asn: u32,
+        ski: [u8; 20],
+        spki: Vec<u8>,
+    },
+    Aspa {
+        customer_asn: u32,
+        providers: Vec<u32>,
+    },
+}
+
+impl Payload {
+    fn to_pdu(&self, announce: bool) -> RtrPdu {
+        match self {
+            Payload::Roa {
+                prefix,
+                max_length,
+                asn,
+            } => RtrPdu::Prefix {
+                announce,
+                prefix: *prefix,
+                max_length: *max_length,
+                asn: *asn,
+            },
+            Payload::RouterKey { asn, ski, spki } => RtrPdu::RouterKey {
+                announce,
+                ski: *ski,
+                asn: *asn,
+                spki: spki.clone(),
+            },
+            Payload::Aspa {
+                customer_asn,
+                providers,
+            } => RtrPdu::Aspa {
+                announce,
+                customer_asn: *customer_asn,
+                providers: if announce { providers.clone() } else { vec![] },
+            },
+        }
+    }
+}
+
+/// Changes that moved the cache from serial `from` to the following serial.
+struct Delta {
+    from: u32,
+    changes: Vec<(bool, Payload)>,
+}
+
+/// The data set currently served, plus the recent history needed for Serial Queries.
+struct CacheData {
+    serial: u32,
+    payloads: BTreeSet<Payload>,
+    deltas: VecDeque<Delta>,
+}
+
+/// A connected router that receives Serial Notify PDUs.
+struct Session {
+    id: u64,
+    version: u8,
+    writer: Arc<Mutex<TcpStream>>,
+}
+
+struct ServerState {
+    session_id: u16,
+    timing: RtrTiming,
+    data: RwLock<CacheData>,
+    sessions: Mutex<Vec<Session>>,
+    next_connection: AtomicU64,
+}
+
+/// RPKI-to-Router cache server (RFC 6810, RFC 8210 and draft-ietf-sidrops-8210bis).
+///
+/// Serves the ROAs, router keys and ASPAs of an [`RpkiTrie`] to routers speaking protocol
+/// versions 0, 1 or 2. BGPsec router keys are sent to version 1 and 2
+/// sessions, ASPAs only to version 2 sessions.
+///
+/// Each call to [`RtrServer::update`] computes the difference to the data
+/// currently served, assigns it a new serial number and sends Serial Notify
+/// to all connected routers, which then fetch just the delta. Routers asking
+/// for a serial that is no longer in the history, or for a different session,
+/// receive a Cache Reset and reload the full data set.
+///
+/// `RtrServer` is cheap to clone; all clones serve the same data.
+///
+/// # Example
+///
+/// Replay a historical snapshot to a lab router:
+///
+/// ```rust,no_run
+/// use bgpkit_commons::rpki::{RpkiTrie, RtrServer};
+/// use chrono::NaiveDate;
+/// use std::net::TcpListener;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let date = NaiveDate::from_ymd_opt(2024, 1, 4).unwrap();
+/// let trie = RpkiTrie::from_ripe_historical(date)?;
+///
+/// let server = RtrServer::new(&trie);
+/// let listener = TcpListener::bind("0.0.0.0:3323")?;
+/// std::thread::spawn({
+///     let server = server.clone();
+///     move || server.serve(listener)
+/// });
+///
+/// // Step forward one day; connected routers are notified and fetch the delta
+/// let next = RpkiTrie::from_ripe_historical(date.succ_opt().unwrap())?;
+/// let summary = server.update(&next);
+/// println!("serial {}: +{} -{} ROAs", summary.serial, summary.roas_announced, summary.roas_withdrawn);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RtrServer {
+    state: Arc<ServerState>,
+}
+
+impl RtrServer {
+    /// Create a server for the given data with the default timing parameters.
+    ///
+    /// The session ID is random, so restarting the server forces routers to
+    /// reload instead of applying stale deltas.
+    pub fn new(trie: &RpkiTrie) -> Self {
+        Self::with_timing(trie, RtrTiming::default())
+    }
+
+    /// Create a server announcing specific refresh, retry and expire intervals.
+    pub fn with_timing(trie: &RpkiTrie, timing: RtrTiming) -> Self {
+        Self {
+            state: Arc::new(ServerState {
+                session_id: random_session_id(),
+                timing,
+                data: RwLock::new(CacheData {
+                    serial: 1,
+                    payloads: payloads_from_trie(trie),
+                    deltas: VecDeque::new(),
+                }),
+                sessions: Mutex::new(Vec::new()),
+                next_connection: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Session ID announced to routers.
+    pub fn session_id(&self) -> u16 {
+        self.state.session_id
+    }
+
+    /// Serial number of the data currently served.
+    pub fn serial(&self) -> u32 {
+        self.state.data.read().unwrap().serial
+    }
+
+    /// Replace the served data with the content of `trie`.
+    ///
+    /// If anything changed, the serial number is incremented and connected
+    /// routers receive a Serial Notify. The returned summary counts the
+    /// announcements and withdrawals in the new delta; an ASPA whose provider
+    /// set changed counts as one announcement.
+    pub fn update(&self, trie: &RpkiTrie) -> RtrSyncSummary {
+        let new_payloads = payloads_from_trie(trie);
+        let mut data = self.state.data.write().unwrap();
+
+        let mut changes: Vec<(bool, Payload)> = Vec::new();
+        changes.extend(
+            data.payloads
+                .difference(&new_payloads)
+                .map(|p| (false, p.clone())),
+        );
+        changes.extend(
+            new_payloads
+                .difference(&data.payloads)
+                .map(|p| (true, p.clone())),
+        );
+
+        let mut summary = RtrSyncSummary {
+            session_id: self.state.session_id,
+            serial: data.serial,
+            ..Default::default()
+        };
+        if changes.is_empty() {
+            return summary;
+        }
+        for (announce, payload) in net_changes(&changes, RTR_MAX_VERSION) {
+            match (announce, payload) {
+                (true, Payload::Roa { .. }) => summary.roas_announced += 1,
+                (false, Payload::Roa { .. }) => summary.roas_withdrawn += 1,
+                (true, Payload::Aspa { .. }) => summary.aspas_announced += 1,
+                (false, Payload::Aspa { .. }) => summary.aspas_withdrawn += 1,
+                (true, Payload::RouterKey { .. }) => summary.router_keys_announced += 1,
+                (false, Payload::RouterKey { .. }) => summary.router_keys_withdrawn += 1,
+            }
+        }
+
+        let from = data.serial;
+        data.deltas.push_back(Delta { from, changes });
+        if data.deltas.len() > MAX_DELTAS {
+            data.deltas.pop_front();
+        }
+        data.serial = from.wrapping_add(1);
+        data.payloads = new_payloads;
+        summary.serial = data.serial;
+        drop(data);
+
+        info!(
+            "RTR cache serial {}: +{} -{} ROAs, +{} -{} ASPAs",
+            summary.serial,
+            summary.roas_announced,
+            summary.roas_withdrawn,
+            summary.aspas_announced,
+            summary.aspas_withdrawn
+        );
+        self.notify(summary.serial);
+        summary
+    }
+
+    /// Accept routers on `listener` and serve each connection on its own thread.
+    ///
+    /// Blocks until accepting fails.
+    pub fn serve(&self, listener: TcpListener) -> Result<()> {
+        loop {
+            let (stream, peer) = listener.accept()?;
+            let server = self.clone();
+            std::thread::spawn(move || {
+                info!("RTR router connected from {}", peer);
+                if let Err(e) = server.handle_connection(stream) {
+                    warn!("RTR session with {} failed: {}", peer, e);
+                }
+            });
+        }
+    }
+
+    /// Serve a single router connection until it is closed.
+    ///
+    /// The protocol version is fixed by the first PDU the router sends.
+    /// A router that stops reading for longer than 30 seconds is disconnected.
+    pub fn handle_connection(&self, stream: TcpStream) -> Result<()> {
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        let mut reader = stream.try_clone()?;
+        let writer = Arc::new(Mutex::new(stream));
+        let connection = self.state.next_connection.fetch_add(1, Ordering::Relaxed);
+        let result = self.run_session(&mut reader, &writer, connection);
+        self.state
+            .sessions
+            .lock()
+            .unwrap()
+            .retain(|s| s.id != connection);
+        result
+    }
+
+    fn run_session(
+        &self,
+        reader: &mut TcpStream,
+        writer: &Arc<Mutex<TcpStream>>,
+        connection: u64,
+    ) -> Result<()> {
+        let mut session_version: Option<u8> = None;
+        loop {
+            let (version, pdu) = match RtrPdu::read_from(reader) {
+                Ok(Some(received)) => received,
+                Ok(None) => return Ok(()),
+                Err(e) => {
+                    let error = RtrPdu::ErrorReport {
+                        code: RtrErrorCode::CorruptData,
+                        pdu: vec![],
+                        text: e.to_string(),
+                    };
+                    let _ = send(writer, session_version.unwrap_or(0), &[error]);
+                    return Err(e);
+                }
+            };
+
+            match session_version {
+                None if version > RTR_MAX_VERSION => {
+                    let error = error_report(
+                        RtrErrorCode::UnsupportedProtocolVersion,
+                        &pdu,
+                        version,
+                        format!("highest supported version is {}", RTR_MAX_VERSION),
+                    );
+                    return send(writer, RTR_MAX_VERSION, &[error]);
+                }
+                None => {
+                    session_version = Some(version);
+                    self.state.sessions.lock().unwrap().push(Session {
+                        id: connection,
+                        version,
+                        writer: writer.clone(),
+                    });
+                }
+                Some(expected) if expected != version => {
+                    let error = error_report(
+                        RtrErrorCode::UnexpectedProtocolVersion,
+                        &pdu,
+                        version,
+                        format!("session uses version {}", expected),
+                    );
+                    return send(writer, expected, &[error]);
+                }
+                Some(_) => {}
+            }
+
+            match &pdu {
+                RtrPdu::ResetQuery => {
+                    let response = self.full_response(version);
+                    send(writer, version, &response)?;
+                }
+                RtrPdu::SerialQuery { session_id, serial } => {
+                    let response = self.delta_response(version, *session_id, *serial);
+                    send(writer, version, &response)?;
+                }
+                RtrPdu::ErrorReport { code, text, .. } => {
+                    warn!("RTR router reported error: {} ({})", code, text);
+                    return Ok(());
+                }
+                _ => {
+                    let error = error_report(
+                        RtrErrorCode::InvalidRequest,
+                        &pdu,
+                        version,
+                        "unexpected PDU from router".to_string(),
+                    );
+                    return send(writer, version, &[error]);
+                }
+            }
+        }
+    }
+
+    fn full_response(&self, version: u8) -> Vec<RtrPdu> {
+        let data = self.state.data.read().unwrap();
+        let mut response = vec![RtrPdu::CacheResponse {
+            session_id: self.state.session_id,
+        }];
+        response.extend(
+            data.payloads
+                .iter()
+                .filter(|p| supported(p, version))
+                .map(|p| p.to_pdu(true)),
+        );
+        response.push(self.end_of_data(data.serial));
+        response
+    }
+
+    fn delta_response(&self, version: u8, session_id: u16, serial: u32) -> Vec<RtrPdu> {
+        let data = self.state.data.read().unwrap();
+        if session_id != self.state.session_id {
+            return vec![RtrPdu::CacheReset];
+        }
+        let changes: Vec<(bool, Payload)> = if serial == data.serial {
+            vec![]
+        } else {
+            let Some(start) = data.deltas.iter().position(|d| d.from == serial) else {
+                return vec![RtrPdu::CacheReset];
+            };
+            data.deltas
+                .iter()
+                .skip(start)
+                .flat_map(|d| d.changes.iter().cloned())
+                .collect()
+        };
+
+        let mut response = vec![RtrPdu::CacheResponse {
+            session_id: self.state.session_id,
+        }];
+        response.extend(
+            net_changes(&changes, version)
+                .into_iter()
+                .map(|(announce, payload)| payload.to_pdu(announce)),
+        );
+        response.push(self.end_of_data(data.serial));
+        response
+    }
+
+    fn end_of_data(&self, serial: u32) -> RtrPdu {
+        RtrPdu::EndOfData {
+            session_id: self.state.session_id,
+            serial,
+            timing: Some(self.state.timing),
+        }
+    }
+
+    /// Send Serial Notify to every connected router.
+    ///
+    /// Each router is notified on its own thread, outside the sessions lock,
+    /// so a router that does not read blocks neither `update` nor new connections.
+    fn notify(&self, serial: u32) {
+        let sessions: Vec<(u8, Arc<Mutex<TcpStream>>)> = self
+            .state
+            .sessions
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|session| (session.version, session.writer.clone()))
+            .collect();
+        let notify = RtrPdu::SerialNotify {
+            session_id: self.state.session_id,
+            serial,
+        };
+        for (version, writer) in sessions {
+            let notify = notify.clone();
+            std::thread::spawn(move || {
+                if send(&writer, version, &[notify]).is_err() {
+                    // the session thread notices the closed connection and cleans up
+                    let _ = writer.lock().unwrap().shutdown(Shutdown::Both);
+                }
+            });
+        }
+    }
+}
+
+/// A random session ID (RFC 8210, section 5.1), seeded by the standard
+/// library's per-process random hash keys and the current time.
+fn random_session_id() -> u16 {
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    hasher.finish() as u16
+}
+
+/// Whether a payload can be sent to a session of the given version.
+fn supported(payload: &Payload, version: u8) -> bool {
+    match payload {
+        Payload::Roa { .. } => true,
+        Payload::RouterKey { .. } => version >= 1,
+        Payload::Aspa { .. } => version >= 2,
+    }
+}
+
+/// Collapse a sequence of changes into the net announcements and withdrawals.
+///
+/// A payload announced and later withdrawn (or the reverse) cancels out. An
+/// ASPA whose provider set changed is sent as a single announcement, which
+/// replaces the previous record for that customer; withdrawals are listed first.
+fn net_changes(changes: &[(bool, Payload)], version: u8) -> Vec<(bool, Payload)> {
+    // payload -> (present before the changes, present after the changes)
+    let mut states: BTreeMap<&Payload, (bool, bool)> = BTreeMap::new();
+    for (announce, payload) in changes {
+        if !supported(payload, version) {
+            continue;
+        }
+        states.entry(payload).or_insert((!announce, *announce)).1 = *announce;
+    }
+
+    let announced_customers: BTreeSet<u32> = states
+        .iter()
+        .filter_map(|(payload, (before, after))| match payload {
+            Payload::Aspa { customer_asn, .. } if *after && !before => Some(*customer_asn),
+            _ => None,
+        })
+        .collect();
+
+    let mut withdrawals = vec![];
+    let mut announcements = vec![];
+    for (payload, (before, after)) in states {
+        match (before, after) {
+            (false, true) => announcements.push((true, payload.clone())),
+            (true, false) => {
+                if let Payload::Aspa { customer_asn, .. } = payload {
+                    if announced_customers.contains(customer_asn) {
+                        continue;
+                    }
+                }
+                withdrawals.push((false, payload.clone()));
+            }
+            _ => {}
+        }
+    }
+    withdrawals.extend(announcements);
+    withdrawals
+}
+
+fn payloads_from_trie(trie: &RpkiTrie) -> BTreeSet<Payload> {
+    let mut payloads: BTreeSet<Payload> = trie
+        .trie
+        .iter()
+        .flat_map(|(_, roas)| roas.iter())
+        .map(|roa| Payload::Roa {
+            prefix: roa.prefix,
+            max_length: roa.max_length,
+            asn: roa.asn,
+        })
+        .collect();
+    payloads.extend(trie.router_keys.iter().filter_map(|key| {
+        Some(Payload::RouterKey {
+            asn: key.asn,
+            ski: key.ski_bytes()?,
+            spki: key.pubkey_bytes()?,
+        })
+    }));
+    payloads.extend(trie.aspas.iter().map(|aspa| {
+        let mut providers = aspa.providers.clone();
+        providers.sort_unstable();
+        providers.dedup();
+        Payload::Aspa {
+            customer_asn: aspa.customer_asn,
+            providers,
+        }
+    }));
+    payloads
+}
+
+fn error_report(code: RtrErrorCode, pdu: &RtrPdu, version: u8, text: String) -> RtrPdu {
+    RtrPdu::ErrorReport {
+        code,
+        pdu: pdu.encode(version),
+        text,
+    }
+}
+
+fn send(writer: &Mutex<TcpStream>, version: u8, pdus: &[RtrPdu]) -> Result<()> {
+    let buf: Vec<u8> = pdus.iter().flat_map(|pdu| pdu.encode(version)).collect();
+    let mut stream = writer.lock().unwrap();
+    stream.write_all(&buf)?;
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::{Aspa, Roa, RouterKey, RpkiValidation, RtrClient, roa};
+
+    fn trie(roas: &[Roa], aspas: &[(u32, &[u32])]) -> RpkiTrie {
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roas(roas.to_vec());
+        for (customer_asn, providers) in aspas {
+            trie.insert_aspa(Aspa {
+                customer_asn: *customer_asn,
+                providers: providers.to_vec(),
+                expires: None,
+            });
+        }
+        trie
+    }
+
+    fn start(server: &RtrServer) -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = server.clone();
+        std::thread::spawn(move || server.serve(listener));
+        addr
+    }
+
+    #[test]
+    fn test_serve_full_and_incremental() {
+        let server = RtrServer::new(&trie(
+            &[
+                roa("192.0.2.0/24", 64496, 24),
+                roa("198.51.100.0/22", 64497, 24),
+            ],
+            &[(64496, &[64500])],
+        ));
+        let addr = start(&server);
+
+        let mut client = RtrClient::connect(addr).unwrap();
+        assert_eq!(client.version(), 2);
+        assert_eq!(client.session_id(), Some(server.session_id()));
+        assert_eq!(client.serial(), Some(1));
+        let p: IpNet = "198.51.100.0/24".parse().unwrap();
+        assert_eq!(client.trie().validate(&p, 64497), RpkiValidation::Valid);
+        assert_eq!(client.trie().aspas.len(), 1);
+
+        let summary = server.update(&trie(
+            &[
+                roa("192.0.2.0/24", 64496, 24),
+                roa("203.0.113.0/24", 64499, 24),
+            ],
+            &[(64496, &[64501, 64500])],
+        ));
+        assert_eq!(summary.serial, 2);
+        assert_eq!(summary.roas_announced, 1);
+        assert_eq!(summary.roas_withdrawn, 1);
+        assert_eq!(summary.aspas_announced, 1);
+        assert_eq!(summary.aspas_withdrawn, 0);
+
+        let synced = client.update_on_notify().unwrap().unwrap();
+        assert!(!synced.reset);
+        assert_eq!(synced.serial, 2);
+        assert_eq!(synced.roas_announced, 1);
+        assert_eq!(synced.roas_withdrawn, 1);
+        assert_eq!(client.trie().validate(&p, 64497), RpkiValidation::Unknown);
+        let p: IpNet = "203.0.113.0/24".parse().unwrap();
+        assert_eq!(client.trie().validate(&p, 64499), RpkiValidation::Valid);
+        assert_eq!(client.trie().aspas[0].providers, vec![64500, 64501]);
+
+        // Reloading identical data does not bump the serial
+        let same = server.update(&trie(
+            &[
+                roa("192.0.2.0/24", 64496, 24),
+                roa("203.0.113.0/24", 64499, 24),
+            ],
+            &[(64496, &[64500, 64501])],
+        ));
+        assert_eq!(same.serial, 2);
+        assert_eq!(server.serial(), 2);
+    }
+
+    #[test]
+    fn test_older_versions_skip_newer_payloads() {
+        let mut data = trie(&[roa("192.0.2.0/24", 64496, 24)], &[(64496, &[64500])]);
+        data.insert_router_key(RouterKey::from_bytes(64496, &[7; 20], &[0x30, 0x59]));
+        let server = RtrServer::new(&data);
+        let addr = start(&server);
+
+        for version in [0, 1] {
+            let mut client = RtrClient::with_version(TcpStream::connect(addr).unwrap(), version);
+            client.reset().unwrap();
+            assert_eq!(client.version(), version);
+            assert!(client.trie().aspas.is_empty());
+            assert_eq!(client.trie().router_keys.len(), version as usize);
+            let p: IpNet = "192.0.2.0/24".parse().unwrap();
+            assert_eq!(client.trie().validate(&p, 64496), RpkiValidation::Valid);
+        }
+    }
+
+    #[test]
+    fn test_deltas_across_several_serials() {
+        let server = RtrServer::new(&trie(&[roa("192.0.2.0/24", 64496, 24)], &[]));
+        server.update(&trie(
+            &[
+                roa("192.0.2.0/24", 64496, 24),
+                roa("203.0.113.0/24", 64499, 24),
+            ],
+            &[],
+        ));
+        server.update(&trie(&[roa("203.0.113.0/24", 64499, 24)], &[]));
+        assert_eq!(server.serial(), 3);
+
+        // A router at serial 1 gets the net change: one withdrawal, one announcement
+        let response = server.delta_response(2, server.session_id(), 1);
+        assert_eq!(response.len(), 4);
+        assert!(matches!(
+            response[1],
+            RtrPdu::Prefix {
+                announce: false,
+                asn: 64496,
+                ..
+            }
+        ));
+        assert!(matches!(
+            response[2],
+            RtrPdu::Prefix {
+                announce: true,
+                asn: 64499,
+                ..
+            }
+        ));
+
+        // A router already up to date gets an empty delta
+        let response = server.delta_response(2, server.session_id(), 3);
+        assert_eq!(response.len(), 2);
+
+        // Unknown serial or session triggers Cache Reset
+        assert_eq!(
+            server.delta_response(2, server.session_id(), 99),
+            vec![RtrPdu::CacheReset]
+        );
+        assert_eq!(
+            server.delta_response(2, server.session_id().wrapping_add(1), 1),
+            vec![RtrPdu::CacheReset]
+        );
+    }
+
+    #[test]
+    fn test_net_changes_cancel_out() {
+        let a = Payload::Roa {
+            prefix: "192.0.2.0/24".parse().unwrap(),
+            max_length: 24,
+            asn: 64496,
+        };
+        let old = Payload::Aspa {
+            customer_asn: 64496,
+            providers: vec![64500],
+        };
+        let new = Payload::Aspa {
+            customer_asn: 64496,
+            providers: vec![64501],
+        };
+        let changes = vec![
+            (true, a.clone()),
+            (false, a.clone()),
+            (false, old.clone()),
+            (true, new.clone()),
+        ];
+        assert_eq!(net_changes(&changes, 2), vec![(true, new)]);
+        assert!(net_changes(&changes, 1).is_empty());
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let server = RtrServer::new(&RpkiTrie::new(None));
+        let addr = start(&server);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        RtrPdu::ResetQuery.write_to(&mut stream, 3).unwrap();
+        let (version, pdu) = RtrPdu::read_from(&mut stream).unwrap().unwrap();
+        assert_eq!(version, RTR_MAX_VERSION);
+        assert!(matches!(
+            pdu,
+            RtrPdu::ErrorReport {
+                code: RtrErrorCode::UnsupportedProtocolVersion,
+                ..
+            }
+        ));
+        // the server closes the connection after the error
+        assert!(RtrPdu::read_from(&mut stream).unwrap().is_none());
+    }
+}
@@ -0,0 +1,551 @@
+//! Simplified Local Internet Number Resource Management with the RPKI (SLURM).
+//!
+//! SLURM files ([RFC 8416]) let operators override validator output locally:
+//! filters suppress VRPs and BGPsec router keys, assertions add new ones.
+//! Validators such as rpki-client, Routinator and Fort apply them before
+//! handing data to routers, so applying the same file to an [`RpkiTrie`]
+//! makes [`RpkiTrie::validate`] agree with what the routers enforce.
+//!
+//! ```json
+//! {
+//!   "slurmVersion": 1,
+//!   "validationOutputFilters": {
+//!     "prefixFilters": [{ "prefix": "192.0.2.0/24", "comment": "All VRPs encompassed by prefix" }],
+//!     "bgpsecFilters": [{ "asn": 64496, "comment": "All keys for ASN" }]
+//!   },
+//!   "locallyAddedAssertions": {
+//!     "prefixAssertions": [{ "asn": 64496, "prefix": "198.51.100.0/24", "comment": "My other important route" }],
+//!     "bgpsecAssertions": []
+//!   }
+//! }
+//! ```
+//!
+//! [RFC 8416]: https://www.rfc-editor.org/rfc/rfc8416
+
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use tracing::info;
+
+use super::router_key::{decode_base64, normalize_ski};
+use super::validator_output::is_valid_max_length;
+use super::{Roa, RouterKey, RpkiTrie};
+use crate::{BgpkitCommonsError, Result};
+
+/// A parsed SLURM file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Slurm {
+    /// SLURM version, always 1
+    pub slurm_version: u32,
+    /// Filters removing VRPs and router keys from the validator output
+    pub validation_output_filters: SlurmFilters,
+    /// VRPs and router keys added locally
+    pub locally_added_assertions: SlurmAssertions,
+}
+
+/// The `validationOutputFilters` member of a SLURM file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlurmFilters {
+    #[serde(default)]
+    pub prefix_filters: Vec<SlurmPrefixFilter>,
+    #[serde(default)]
+    pub bgpsec_filters: Vec<SlurmBgpsecFilter>,
+}
+
+/// The `locallyAddedAssertions` member of a SLURM file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlurmAssertions {
+    #[serde(default)]
+    pub prefix_assertions: Vec<SlurmPrefixAssertion>,
+    #[serde(default)]
+    pub bgpsec_assertions: Vec<SlurmBgpsecAssertion>,
+}
+
+/// Removes all VRPs covered by `prefix` and/or originated by `asn`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlurmPrefixFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<IpNet>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asn: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// Removes all router keys for `asn` and/or with the given SKI.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlurmBgpsecFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asn: Option<u32>,
+    /// Subject Key Identifier, base64url-encoded without padding
+    #[serde(rename = "SKI", default, skip_serializing_if = "Option::is_none")]
+    pub ski: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// Adds a VRP.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlurmPrefixAssertion {
+    pub asn: u32,
+    pub prefix: IpNet,
+    /// Defaults to the prefix length when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_prefix_length: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// Adds a BGPsec router key.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlurmBgpsecAssertion {
+    pub asn: u32,
+    /// Subject Key Identifier, base64url-encoded without padding
+    #[serde(rename = "SKI")]
+    pub ski: String,
+    /// DER-encoded SubjectPublicKeyInfo, base64url-encoded without padding
+    pub router_public_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// Record of what applying SLURM changed in an [`RpkiTrie`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SlurmReport {
+    /// VRPs removed by prefix filters
+    pub roas_suppressed: Vec<Roa>,
+    /// VRPs added by prefix assertions
+    pub roas_added: Vec<Roa>,
+    /// Router keys removed by BGPsec filters
+    pub router_keys_suppressed: Vec<RouterKey>,
+    /// Router keys added by BGPsec assertions
+    pub router_keys_added: Vec<RouterKey>,
+}
+
+impl SlurmPrefixFilter {
+    fn matches(&self, roa: &Roa) -> bool {
+        if self.prefix.is_none() && self.asn.is_none() {
+            return false;
+        }
+        self.prefix.is_none_or(|p| p.contains(&roa.prefix)) && self.asn.is_none_or(|a| a == roa.asn)
+    }
+}
+
+impl SlurmBgpsecFilter {
+    fn matches(&self, key: &RouterKey) -> bool {
+        if self.asn.is_none() && self.ski.is_none() {
+            return false;
+        }
+        self.asn.is_none_or(|a| a == key.asn)
+            && self
+                .ski
+                .as_ref()
+                .is_none_or(|ski| ski_to_hex(ski).is_some_and(|hex| hex == normalize_ski(&key.ski)))
+    }
+}
+
+impl Slurm {
+    /// Parse a SLURM file from a JSON string.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let slurm: Slurm = serde_json::from_str(json)?;
+        slurm.validate()?;
+        Ok(slurm)
+    }
+
+    /// Load a SLURM file from a local path or URL.
+    pub fn from_path(path: &str) -> Result<Self> {
+        let mut json = String::new();
+        oneio::get_reader(path)?.read_to_string(&mut json)?;
+        Self::from_json(&json)
+    }
+
+    /// Check the version and that every assertion can be converted into a
+    /// VRP or router key.
+    pub fn validate(&self) -> Result<()> {
+        if self.slurm_version != 1 {
+            return Err(BgpkitCommonsError::invalid_format(
+                "SLURM",
+                self.slurm_version.to_string(),
+                "unsupported slurmVersion",
+            ));
+        }
+        self.asserted_roas()?;
+        self.asserted_router_keys()?;
+        Ok(())
+    }
+
+    fn asserted_roas(&self) -> Result<Vec<Roa>> {
+        self.locally_added_assertions
+            .prefix_assertions
+            .iter()
+            .map(|assertion| {
+                let max_length = assertion
+                    .max_prefix_length
+                    .unwrap_or(assertion.prefix.prefix_len());
+                if !is_valid_max_length(&assertion.prefix, max_length) {
+                    return Err(BgpkitCommonsError::invalid_format(
+                        "SLURM",
+                        assertion.prefix.to_string(),
+                        format!("invalid maxPrefixLength {}", max_length),
+                    ));
+                }
+                Ok(Roa {
+                    prefix: assertion.prefix.trunc(),
+                    asn: assertion.asn,
+                    max_length,
+                    rir: None,
+                    not_before: None,
+                    not_after: None,
+                })
+            })
+            .collect()
+    }
+
+    fn asserted_router_keys(&self) -> Result<Vec<RouterKey>> {
+        self.locally_added_assertions
+            .bgpsec_assertions
+            .iter()
+            .map(|assertion| {
+                let ski = decode_base64(&assertion.ski).filter(|ski| ski.len() == 20);
+                match (ski, decode_base64(&assertion.router_public_key)) {
+                    (Some(ski), Some(spki)) => {
+                        Ok(RouterKey::from_bytes(assertion.asn, &ski, &spki))
+                    }
+                    _ => Err(BgpkitCommonsError::invalid_format(
+                        "SLURM",
+                        assertion.ski.clone(),
+                        "invalid SKI or routerPublicKey encoding",
+                    )),
+                }
+            })
+            .collect()
+    }
+}
+
+impl RpkiTrie {
+    /// Apply a SLURM file to this trie.
+    ///
+    /// The file is kept with the trie together with the files applied
+    /// before it, and the whole set is applied as RFC 8416 section 4.2
+    /// requires: VRPs and router keys asserted by earlier files are
+    /// withdrawn, the filters of all files are applied to the validator
+    /// output, and the assertions of all files are added afterwards, so no
+    /// filter ever removes an assertion. Applying a file that is already
+    /// applied changes nothing. The files are re-applied by
+    /// [`RpkiTrie::reload`].
+    ///
+    /// Returns an error without changing the trie if an assertion is
+    /// invalid (see [`Slurm::validate`]). Otherwise returns what this call
+    /// changed; the accumulated record over all applied files is available
+    /// from [`RpkiTrie::slurm_report`].
+    pub fn apply_slurm(&mut self, slurm: &Slurm) -> Result<SlurmReport> {
+        slurm.validate()?;
+        if self.slurm.contains(slurm) {
+            return Ok(SlurmReport::default());
+        }
+        self.slurm.push(slurm.clone());
+
+        let previous_roas = std::mem::take(&mut self.slurm_report.roas_added);
+        for roa in &previous_roas {
+            self.remove_roa(&roa.prefix, roa.asn, roa.max_length);
+        }
+        let previous_keys = std::mem::take(&mut self.slurm_report.router_keys_added);
+        self.router_keys.retain(|key| !previous_keys.contains(key));
+
+        let mut report = SlurmReport::default();
+        let prefix_filters: Vec<&SlurmPrefixFilter> = self
+            .slurm
+            .iter()
+            .flat_map(|slurm| &slurm.validation_output_filters.prefix_filters)
+            .collect();
+        if !prefix_filters.is_empty() {
+            let suppressed: Vec<Roa> = self
+                .trie
+                .iter()
+                .flat_map(|(_, roas)| roas.iter())
+                .filter(|roa| prefix_filters.iter().any(|f| f.matches(roa)))
+                .cloned()
+                .collect();
+            for roa in &suppressed {
+                self.remove_roa(&roa.prefix, roa.asn, roa.max_length);
+            }
+            report.roas_suppressed = suppressed;
+        }
+
+        let bgpsec_filters: Vec<&SlurmBgpsecFilter> = self
+            .slurm
+            .iter()
+            .flat_map(|slurm| &slurm.validation_output_filters.bgpsec_filters)
+            .collect();
+        if !bgpsec_filters.is_empty() {
+            let (suppressed, kept) = std::mem::take(&mut self.router_keys)
+                .into_iter()
+                .partition(|key| bgpsec_filters.iter().any(|f| f.matches(key)));
+            self.router_keys = kept;
+            report.router_keys_suppressed = suppressed;
+        }
+
+        // Validated above, so converting the assertions cannot fail
+        let mut asserted_roas = vec![];
+        let mut asserted_keys = vec![];
+        for slurm in &self.slurm {
+            asserted_roas.extend(slurm.asserted_roas()?);
+            asserted_keys.extend(slurm.asserted_router_keys()?);
+        }
+        let mut added_roas = vec![];
+        for roa in asserted_roas {
+            let exists = self.trie.exact_match(roa.prefix).is_some_and(|roas| {
+                roas.iter()
+                    .any(|r| r.asn == roa.asn && r.max_length == roa.max_length)
+            });
+            if !exists {
+                self.insert_roa(roa.clone());
+                added_roas.push(roa);
+            }
+        }
+        let mut added_keys = vec![];
+        for key in asserted_keys {
+            if self.insert_router_key(key.clone()) {
+                added_keys.push(key);
+            }
+        }
+        report.roas_added = added_roas
+            .iter()
+            .filter(|roa| {
+                !previous_roas.iter().any(|r| {
+                    r.prefix == roa.prefix && r.asn == roa.asn && r.max_length == roa.max_length
+                })
+            })
+            .cloned()
+            .collect();
+        report.router_keys_added = added_keys
+            .iter()
+            .filter(|key| !previous_keys.contains(key))
+            .cloned()
+            .collect();
+
+        info!(
+            "applied SLURM: {} VRPs suppressed, {} VRPs added, {} router keys suppressed, {} router keys added",
+            report.roas_suppressed.len(),
+            report.roas_added.len(),
+            report.router_keys_suppressed.len(),
+            report.router_keys_added.len()
+        );
+        self.slurm_report
+            .roas_suppressed
+            .extend(report.roas_suppressed.clone());
+        self.slurm_report
+            .router_keys_suppressed
+            .extend(report.router_keys_suppressed.clone());
+        self.slurm_report.roas_added = added_roas;
+        self.slurm_report.router_keys_added = added_keys;
+        Ok(report)
+    }
+
+    /// SLURM files applied to this trie, in order.
+    pub fn slurm(&self) -> &[Slurm] {
+        &self.slurm
+    }
+
+    /// VRPs and router keys added or suppressed by all applied SLURM files.
+    pub fn slurm_report(&self) -> &SlurmReport {
+        &self.slurm_report
+    }
+}
+
+/// Convert a base64url SKI from SLURM to the hex form used by [`RouterKey`].
+fn ski_to_hex(ski: &str) -> Option<String> {
+    let bytes = decode_base64(ski)?;
+    if bytes.len() != 20 {
+        return None;
+    }
+    Some(bytes.iter().map(|b| format!("{:02X}", b)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::{RpkiValidation, roa};
+
+    const SLURM: &str = r#"{
+        "slurmVersion": 1,
+        "validationOutputFilters": {
+            "prefixFilters": [
+                { "prefix": "192.0.2.0/24", "comment": "All VRPs encompassed by prefix" },
+                { "asn": 64497, "comment": "All VRPs matching ASN" }
+            ],
+            "bgpsecFilters": [
+                { "asn": 64496, "comment": "All keys for ASN" }
+            ]
+        },
+        "locallyAddedAssertions": {
+            "prefixAssertions": [
+                { "asn": 64499, "prefix": "198.51.100.0/24", "comment": "My other important route" },
+                { "asn": 64499, "prefix": "2001:db8::/32", "maxPrefixLength": 48 }
+            ],
+            "bgpsecAssertions": [
+                { "asn": 64498, "SKI": "q6urq6urq6urq6urq6urq6urq6s", "routerPublicKey": "MFkwEw" }
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn test_apply_slurm() {
+        let slurm = Slurm::from_json(SLURM).unwrap();
+        assert_eq!(slurm.validation_output_filters.prefix_filters.len(), 2);
+
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roas(vec![
+            roa("192.0.2.0/24", 64496, 24),
+            roa("192.0.2.128/25", 64500, 25),
+            roa("203.0.113.0/24", 64497, 24),
+            roa("203.0.113.0/24", 64501, 24),
+        ]);
+        trie.insert_router_key(RouterKey::from_bytes(64496, &[1; 20], &[0x30]));
+        trie.insert_router_key(RouterKey::from_bytes(64500, &[2; 20], &[0x30]));
+
+        let report = trie.apply_slurm(&slurm).unwrap();
+        assert_eq!(report.roas_suppressed.len(), 3);
+        assert_eq!(report.roas_added.len(), 2);
+        assert_eq!(report.router_keys_suppressed.len(), 1);
+        assert_eq!(report.router_keys_suppressed[0].asn, 64496);
+        assert_eq!(report.router_keys_added.len(), 1);
+        assert_eq!(report.router_keys_added[0].ski, "AB".repeat(20));
+
+        let p = "192.0.2.0/24".parse().unwrap();
+        assert_eq!(trie.validate(&p, 64496), RpkiValidation::Unknown);
+        let p = "203.0.113.0/24".parse().unwrap();
+        assert_eq!(trie.validate(&p, 64497), RpkiValidation::Invalid);
+        assert_eq!(trie.validate(&p, 64501), RpkiValidation::Valid);
+        let p = "198.51.100.0/24".parse().unwrap();
+        assert_eq!(trie.validate(&p, 64499), RpkiValidation::Valid);
+        let p = "2001:db8:1::/48".parse().unwrap();
+        assert_eq!(trie.validate(&p, 64499), RpkiValidation::Valid);
+
+        assert_eq!(trie.slurm().len(), 1);
+        assert_eq!(trie.slurm_report().roas_added.len(), 2);
+
+        // Applying again suppresses nothing new and adds nothing twice
+        let report = trie.apply_slurm(&slurm).unwrap();
+        assert!(report.roas_added.is_empty());
+        assert!(report.router_keys_added.is_empty());
+        assert_eq!(trie.slurm().len(), 1);
+        assert_eq!(trie.slurm_report().roas_added.len(), 2);
+    }
+
+    #[test]
+    fn test_apply_multiple_slurm_files() {
+        let first = Slurm::from_json(
+            r#"{
+            "slurmVersion": 1,
+            "validationOutputFilters": {
+                "prefixFilters": [{ "prefix": "203.0.113.0/24" }]
+            },
+            "locallyAddedAssertions": {
+                "prefixAssertions": [{ "asn": 64499, "prefix": "198.51.100.0/24" }]
+            }
+        }"#,
+        )
+        .unwrap();
+        let second = Slurm::from_json(
+            r#"{
+            "slurmVersion": 1,
+            "validationOutputFilters": {
+                "prefixFilters": [{ "prefix": "198.51.100.0/22" }]
+            },
+            "locallyAddedAssertions": {
+                "prefixAssertions": [{ "asn": 64500, "prefix": "203.0.113.0/24" }]
+            }
+        }"#,
+        )
+        .unwrap();
+
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roas(vec![
+            roa("198.51.101.0/24", 64496, 24),
+            roa("203.0.113.0/24", 64497, 24),
+        ]);
+        trie.apply_slurm(&first).unwrap();
+        let report = trie.apply_slurm(&second).unwrap();
+        assert_eq!(report.roas_suppressed.len(), 1);
+        assert_eq!(report.roas_added.len(), 1);
+        assert_eq!(report.roas_added[0].asn, 64500);
+
+        // Filters of either file never remove an assertion of the other
+        let p = "198.51.100.0/24".parse().unwrap();
+        assert_eq!(trie.validate(&p, 64499), RpkiValidation::Valid);
+        let p = "198.51.101.0/24".parse().unwrap();
+        assert_eq!(trie.validate(&p, 64496), RpkiValidation::Unknown);
+        let p = "203.0.113.0/24".parse().unwrap();
+        assert_eq!(trie.validate(&p, 64497), RpkiValidation::Invalid);
+        assert_eq!(trie.validate(&p, 64500), RpkiValidation::Valid);
+
+        assert_eq!(trie.slurm().len(), 2);
+        assert_eq!(trie.slurm_report().roas_added.len(), 2);
+        assert_eq!(trie.slurm_report().roas_suppressed.len(), 2);
+    }
+
+    #[test]
+    fn test_apply_invalid_slurm() {
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roa(roa("192.0.2.0/24", 64496, 24));
+
+        let mut slurm = Slurm {
+            slurm_version: 1,
+            ..Default::default()
+        };
+        slurm
+            .validation_output_filters
+            .prefix_filters
+            .push(SlurmPrefixFilter {
+                prefix: None,
+                asn: Some(64496),
+                comment: None,
+            });
+        slurm
+            .locally_added_assertions
+            .prefix_assertions
+            .push(SlurmPrefixAssertion {
+                asn: 64499,
+                prefix: "198.51.100.0/24".parse().unwrap(),
+                max_prefix_length: Some(33),
+                comment: None,
+            });
+        assert!(trie.apply_slurm(&slurm).is_err());
+
+        slurm.locally_added_assertions.prefix_assertions.clear();
+        slurm
+            .locally_added_assertions
+            .bgpsec_assertions
+            .push(SlurmBgpsecAssertion {
+                asn: 64498,
+                ski: "q6urq6urq6urq6urq6urq6urq6s".to_string(),
+                router_public_key: "not base64!".to_string(),
+                comment: None,
+            });
+        assert!(trie.apply_slurm(&slurm).is_err());
+
+        // Nothing was applied
+        assert!(trie.slurm().is_empty());
+        let p = "192.0.2.0/24".parse().unwrap();
+        assert_eq!(trie.validate(&p, 64496), RpkiValidation::Valid);
+    }
+
+    #[test]
+    fn test_invalid_slurm() {
+        assert!(Slurm::from_json(r#"{"slurmVersion": 2, "validationOutputFilters": {}, "locallyAddedAssertions": {}}"#).is_err());
+        let bad_max_length = r#"{
+            "slurmVersion": 1,
+            "validationOutputFilters": {},
+            "locallyAddedAssertions": {
+                "prefixAssertions": [{ "asn": 64496, "prefix": "192.0.2.0/24", "maxPrefixLength": 16 }]
+            }
+        }"#;
+        assert!(Slurm::from_json(bad_max_length).is_err());
+    }
+}
@@ -0,0 +1,275 @@
+//! Route validation across a range of historical snapshots.
+//!
+//! [`validation_timeline`] evaluates a batch of routes against one archived
+//! snapshot per day. Each day loads a single snapshot file (the earliest of
+//! the day for RPKIviews and RPKISPOOL, the per-RIR files for RIPE NCC),
+//! checks every route against it and drops it before moving to the next
+//! day, so memory use stays at one snapshot regardless of the range.
+
+use chrono::NaiveDate;
+use ipnet::IpNet;
+use tracing::{info, warn};
+
+use super::{
+    AspaDirection, AspaValidation, HistoricalRpkiSource, RpkiTrie, RpkiValidation, list_ripe_files,
+    list_rpkispools_files, list_rpkiviews_files,
+};
+use crate::{BgpkitCommonsError, Result};
+
+/// A route to follow through a [`validation_timeline`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RpkiTimelineRoute {
+    /// The announced prefix
+    pub prefix: IpNet,
+    /// The origin ASN
+    pub origin: u32,
+    /// AS_PATH (origin last) and direction to verify against ASPA records, if any
+    pub as_path: Option<(Vec<u32>, AspaDirection)>,
+}
+
+impl RpkiTimelineRoute {
+    /// A route checked with route origin validation only.
+    pub fn new(prefix: IpNet, origin: u32) -> Self {
+        Self {
+            prefix,
+            origin,
+            as_path: None,
+        }
+    }
+
+    /// Also verify the AS_PATH of the route against ASPA records.
+    pub fn with_as_path(mut self, as_path: Vec<u32>, direction: AspaDirection) -> Self {
+        self.as_path = Some((as_path, direction));
+        self
+    }
+}
+
+/// Validation status of a route in one snapshot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RpkiRouteStatus {
+    /// Route origin validation state
+    pub rov: RpkiValidation,
+    /// ASPA verification state; `None` if the route has no AS_PATH
+    pub aspa: Option<AspaValidation>,
+}
+
+/// The snapshot evaluated for one day of a timeline.
+#[derive(Clone, Debug)]
+pub struct RpkiTimelineDay {
+    /// The day
+    pub date: NaiveDate,
+    /// URLs of the snapshot files that were loaded
+    pub urls: Vec<String>,
+    /// One status per route, in query order; `None` if no snapshot could be loaded
+    pub statuses: Option<Vec<RpkiRouteStatus>>,
+    /// Why no snapshot could be loaded
+    pub error: Option<String>,
+}
+
+/// Validation status of a batch of routes on each day of a date range.
+#[derive(Clone, Debug)]
+pub struct RpkiTimeline {
+    /// The routes, in query order
+    pub routes: Vec<RpkiTimelineRoute>,
+    /// One entry per day, in date order
+    pub days: Vec<RpkiTimelineDay>,
+}
+
+impl RpkiTimeline {
+    /// The status of the route at `index` on each day with a snapshot.
+    pub fn route_history(&self, index: usize) -> Vec<(NaiveDate, &RpkiRouteStatus)> {
+        self.days
+            .iter()
+            .filter_map(|day| Some((day.date, day.statuses.as_ref()?.get(index)?)))
+            .collect()
+    }
+
+    /// Days on which the status of the route at `index` differs from the
+    /// previous day with a snapshot, with the previous and the new status.
+    ///
+    /// Answers "when did this route become invalid".
+    pub fn route_changes(
+        &self,
+        index: usize,
+    ) -> Vec<(NaiveDate, &RpkiRouteStatus, &RpkiRouteStatus)> {
+        self.route_history(index)
+            .windows(2)
+            .filter(|w| w[0].1 != w[1].1)
+            .map(|w| (w[1].0, w[0].1, w[1].1))
+            .collect()
+    }
+}
+
+impl RpkiTrie {
+    /// The status of a route in this trie.
+    pub(super) fn route_status(&self, route: &RpkiTimelineRoute) -> RpkiRouteStatus {
+        RpkiRouteStatus {
+            rov: self.validate(&route.prefix, route.origin),
+            aspa: route
+                .as_path
+                .as_ref()
+                .map(|(path, direction)| self.verify_aspa_path(path, *direction).state),
+        }
+    }
+}
+
+/// Validate routes against the archived snapshot of each day from `start` to `end` (inclusive).
+///
+/// Days without a published snapshot, or whose snapshot fails to load, are
+/// kept in the timeline with `statuses: None` and the reason in `error`.
+pub fn validation_timeline(
+    source: HistoricalRpkiSource,
+    start: NaiveDate,
+    end: NaiveDate,
+    routes: &[RpkiTimelineRoute],
+) -> Result<RpkiTimeline> {
+    timeline_with_loader(start, end, routes, |date| load_day(&source, date))
+}
+
+/// Load the snapshot of one day, returning the loaded URLs and the trie.
+fn load_day(source: &HistoricalRpkiSource, date: NaiveDate) -> Result<(Vec<String>, RpkiTrie)> {
+    let files = match source {
+        HistoricalRpkiSource::Ripe => list_ripe_files(date)?,
+        HistoricalRpkiSource::RpkiViews(collector) => list_rpkiviews_files(*collector, date)?,
+        HistoricalRpkiSource::RpkiSpools(collector) => list_rpkispools_files(*collector, date)?,
+        HistoricalRpkiSource::Local(archive) => archive.list_files(date)?,
+    };
+    let Some(first) = files.first() else {
+        return Err(BgpkitCommonsError::data_source_error(
+            source.to_string(),
+            format!("no snapshot published for {}", date),
+        ));
+    };
+    match source {
+        HistoricalRpkiSource::Ripe => {
+            let urls: Vec<String> = files.iter().map(|f| f.url.clone()).collect();
+            let trie = RpkiTrie::from_ripe_files(&urls, Some(date))?;
+            Ok((urls, trie))
+        }
+        HistoricalRpkiSource::RpkiViews(_) => {
+            let trie = RpkiTrie::from_rpkiviews_file(&first.url, Some(date))?;
+            Ok((vec![first.url.clone()], trie))
+        }
+        HistoricalRpkiSource::RpkiSpools(_) => {
+            let trie = RpkiTrie::from_rpkispools_url(&first.url, Some(date))?;
+            Ok((vec![first.url.clone()], trie))
+        }
+        HistoricalRpkiSource::Local(archive) => {
+            let trie = RpkiTrie::from_local_archive(archive, date)?;
+            Ok((trie.provenance().urls.clone(), trie))
+        }
+    }
+}
+
+fn timeline_with_loader<F>(
+    start: NaiveDate,
+    end: NaiveDate,
+    routes: &[RpkiTimelineRoute],
+    mut load: F,
+) -> Result<RpkiTimeline>
+where
+    F: FnMut(NaiveDate) -> Result<(Vec<String>, RpkiTrie)>,
+{
+    if end < start {
+        return Err(BgpkitCommonsError::invalid_format(
+            "date range",
+            format!("{}..{}", start, end),
+            "end date is before start date",
+        ));
+    }
+
+    let mut days = vec![];
+    for date in start.iter_days().take_while(|d| *d <= end) {
+        info!(
+            "validating {} routes against the {} snapshot",
+            routes.len(),
+            date
+        );
+        let day = match load(date) {
+            Ok((urls, trie)) => RpkiTimelineDay {
+                date,
+                urls,
+                statuses: Some(routes.iter().map(|r| trie.route_status(r)).collect()),
+                error: None,
+            },
+            Err(e) => {
+                warn!("no RPKI snapshot for {}: {}", date, e);
+                RpkiTimelineDay {
+                    date,
+                    urls: vec![],
+                    statuses: None,
+                    error: Some(e.to_string()),
+                }
+            }
+        };
+        days.push(day);
+    }
+
+    Ok(RpkiTimeline {
+        routes: routes.to_vec(),
+        days,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::{Aspa, Roa};
+
+    fn trie_for_day(day: u32) -> RpkiTrie {
+        let mut trie = RpkiTrie::new(None);
+        // the ROA moves to another origin on day 3, the ASPA appears on day 2
+        trie.insert_roa(Roa {
+            prefix: "192.0.2.0/24".parse().unwrap(),
+            asn: if day < 3 { 64496 } else { 64497 },
+            max_length: 24,
+            rir: None,
+            not_before: None,
+            not_after: None,
+        });
+        if day >= 2 {
+            trie.insert_aspa(Aspa {
+                customer_asn: 64496,
+                providers: vec![64510],
+                expires: None,
+            });
+        }
+        trie
+    }
+
+    #[test]
+    fn test_timeline() {
+        let start = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2025, 1, 5).unwrap();
+        let routes = vec![
+            RpkiTimelineRoute::new("192.0.2.0/24".parse().unwrap(), 64496)
+                .with_as_path(vec![64510, 64496], AspaDirection::Upstream),
+        ];
+
+        let timeline = timeline_with_loader(start, end, &routes, |date| {
+            use chrono::Datelike;
+            match date.day() {
+                4 => Err(BgpkitCommonsError::data_source_error("test", "missing")),
+                day => Ok((vec![format!("day{}", day)], trie_for_day(day))),
+            }
+        })
+        .unwrap();
+
+        assert_eq!(timeline.days.len(), 5);
+        assert!(timeline.days[3].statuses.is_none());
+        assert!(timeline.days[3].error.is_some());
+        assert_eq!(timeline.route_history(0).len(), 4);
+
+        let changes = timeline.route_changes(0);
+        assert_eq!(changes.len(), 2);
+        // day 2: the ASPA makes the path verifiable
+        assert_eq!(changes[0].0, NaiveDate::from_ymd_opt(2025, 1, 2).unwrap());
+        assert_eq!(changes[0].1.aspa, Some(AspaValidation::Unknown));
+        assert_eq!(changes[0].2.aspa, Some(AspaValidation::Valid));
+        // day 3: the route becomes ROV invalid
+        assert_eq!(changes[1].0, NaiveDate::from_ymd_opt(2025, 1, 3).unwrap());
+        assert_eq!(changes[1].2.rov, RpkiValidation::Invalid);
+
+        assert!(timeline_with_loader(end, start, &routes, |_| unreachable!()).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! Route origin validation with an explanation of the outcome.
+//!
+//! [`RpkiTrie::validate_detailed`] and [`RpkiTrie::validate_detailed_check_expiry`]
+//! compute the same state as [`RpkiTrie::validate`] and
+//! [`RpkiTrie::validate_check_expiry`], and additionally return the covering
+//! ROAs, the ROA that made the route valid, and why an invalid route is invalid.
+
+use chrono::{NaiveDateTime, Utc};
+use ipnet::IpNet;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use super::policy::validate_origin;
+use super::{Roa, RpkiTrie, RpkiValidation, RpkiValidationPolicy};
+
+/// Why a route is RPKI invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RpkiInvalidReason {
+    /// A ROA for the origin ASN covers the prefix, but the prefix is longer than its max-length
+    MaxLengthExceeded,
+    /// All covering ROAs are AS0 ROAs (RFC 6483 section 4): the prefix should not be routed
+    As0,
+    /// The covering ROAs authorize other origin ASNs
+    OriginMismatch,
+}
+
+impl Display for RpkiInvalidReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RpkiInvalidReason::MaxLengthExceeded => write!(f, "max-length exceeded"),
+            RpkiInvalidReason::As0 => write!(f, "AS0 ROA"),
+            RpkiInvalidReason::OriginMismatch => write!(f, "origin mismatch"),
+        }
+    }
+}
+
+/// Detailed result of validating a (prefix, origin ASN) pair.
+#[derive(Clone, Debug)]
+pub struct RpkiValidationDetail {
+    /// The validated prefix
+    pub prefix: IpNet,
+    /// The validated origin ASN
+    pub asn: u32,
+    /// The validation state, identical to what `validate`/`validate_check_expiry` return
+    pub state: RpkiValidation,
+    /// All ROAs whose prefix covers the validated prefix, regardless of max-length
+    pub covering_roas: Vec<Roa>,
+    /// The ROA that made the route valid
+    pub matched_roa: Option<Roa>,
+    /// Why the route is invalid; `None` unless `state` is `Invalid`
+    pub reason: Option<RpkiInvalidReason>,
+    /// ROAs that would have matched but were outside their validity period at the check time
+    pub expiry_skipped_roas: Vec<Roa>,
+}
+
+impl RpkiTrie {
+    /// Validate a prefix-ASN pair and explain the result.
+    ///
+    /// The state matches [`RpkiTrie::validate`]; ROA validity periods are not checked.
+    pub fn validate_detailed(&self, prefix: &IpNet, asn: u32) -> RpkiValidationDetail {
+        self.validate_detailed_inner(prefix, asn, None)
+    }
+
+    /// Validate a prefix-ASN pair with ROA expiry checking and explain the result.
+    ///
+    /// The state matches [`RpkiTrie::validate_check_expiry`]. ROAs that would
+    /// have authorized the route but are outside their validity period at
+    /// `check_time` (default: now) are listed in
+    /// [`RpkiValidationDetail::expiry_skipped_roas`].
+    pub fn validate_detailed_check_expiry(
+        &self,
+        prefix: &IpNet,
+        asn: u32,
+        check_time: Option<NaiveDateTime>,
+    ) -> RpkiValidationDetail {
+        let check_time = check_time.unwrap_or_else(|| Utc::now().naive_utc());
+        self.validate_detailed_inner(prefix, asn, Some(check_time))
+    }
+
+    /// The validation state alone, without collecting ROAs.
+    ///
+    /// Same result as `validate_detailed_inner(..).state`, used by the plain
+    /// and bulk validation calls.
+    pub(crate) fn validation_state(
+        &self,
+        prefix: &IpNet,
+        asn: u32,
+        check_time: Option<NaiveDateTime>,
+    ) -> RpkiValidation {
+        self.validate_with_policy(prefix, asn, &RpkiValidationPolicy::check_expiry(check_time))
+    }
+
+    fn validate_detailed_inner(
+        &self,
+        prefix: &IpNet,
+        asn: u32,
+        check_time: Option<NaiveDateTime>,
+    ) -> RpkiValidationDetail {
+        let mut detail = RpkiValidationDetail {
+            prefix: *prefix,
+            asn,
+            state: RpkiValidation::Unknown,
+            covering_roas: self.lookup_covering_roas(prefix),
+            matched_roa: None,
+            reason: None,
+            expiry_skipped_roas: vec![],
+        };
+        let mut expiry_skipped_roas = vec![];
+        let (state, matched_roa) = validate_origin(
+            &detail.covering_roas,
+            prefix,
+            asn,
+            &RpkiValidationPolicy::check_expiry(check_time),
+            |roa| expiry_skipped_roas.push(roa.clone()),
+        );
+        detail.matched_roa = matched_roa.cloned();
+        detail.expiry_skipped_roas = expiry_skipped_roas;
+        detail.state = state;
+        if detail.state != RpkiValidation::Invalid {
+            return detail;
+        }
+
+        detail.reason = Some(if detail.covering_roas.iter().any(|roa| roa.asn == asn) {
+            RpkiInvalidReason::MaxLengthExceeded
+        } else if detail.covering_roas.iter().all(|roa| roa.asn == 0) {
+            RpkiInvalidReason::As0
+        } else {
+            RpkiInvalidReason::OriginMismatch
+        });
+        detail
+    }
+}
+
+pub(super) fn within_validity(roa: &Roa, time: NaiveDateTime) -> bool {
+    roa.not_before.is_none_or(|not_before| time >= not_before)
+        && roa.not_after.is_none_or(|not_after| time <= not_after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::roa;
+    use chrono::DateTime;
+
+    #[test]
+    fn test_validate_detailed_reasons() {
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roas(vec![
+            roa("192.0.2.0/24", 64496, 24),
+            roa("198.51.100.0/22", 64497, 22),
+            roa("203.0.113.0/24", 0, 24),
+        ]);
+
+        let p: IpNet = "192.0.2.0/24".parse().unwrap();
+        let detail = trie.validate_detailed(&p, 64496);
+        assert_eq!(detail.state, RpkiValidation::Valid);
+        assert_eq!(detail.matched_roa.unwrap().asn, 64496);
+        assert!(detail.reason.is_none());
+
+        let detail = trie.validate_detailed(&p, 64499);
+        assert_eq!(detail.state, RpkiValidation::Invalid);
+        assert_eq!(detail.reason, Some(RpkiInvalidReason::OriginMismatch));
+        assert_eq!(detail.covering_roas.len(), 1);
+
+        let p: IpNet = "198.51.100.0/24".parse().unwrap();
+        let detail = trie.validate_detailed(&p, 64497);
+        assert_eq!(detail.state, RpkiValidation::Invalid);
+        assert_eq!(detail.reason, Some(RpkiInvalidReason::MaxLengthExceeded));
+
+        let p: IpNet = "203.0.113.0/24".parse().unwrap();
+        let detail = trie.validate_detailed(&p, 64496);
+        assert_eq!(detail.reason, Some(RpkiInvalidReason::As0));
+
+        let p: IpNet = "10.0.0.0/8".parse().unwrap();
+        let detail = trie.validate_detailed(&p, 64496);
+        assert_eq!(detail.state, RpkiValidation::Unknown);
+        assert!(detail.covering_roas.is_empty());
+    }
+
+    #[test]
+    fn test_validate_detailed_check_expiry() {
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roas(vec![
+            Roa {
+                not_after: DateTime::from_timestamp(1_000, 0).map(|dt| dt.naive_utc()),
+                ..roa("192.0.2.0/24", 64496, 24)
+            },
+            roa("192.0.2.0/23", 64497, 24),
+        ]);
+        let p: IpNet = "192.0.2.0/24".parse().unwrap();
+        let later = DateTime::from_timestamp(2_000, 0).unwrap().naive_utc();
+
+        let detail = trie.validate_detailed_check_expiry(&p, 64496, Some(later));
+        assert_eq!(detail.state, RpkiValidation::Unknown);
+        assert_eq!(
+            detail.state,
+            trie.validate_check_expiry(&p, 64496, Some(later))
+        );
+        assert_eq!(detail.expiry_skipped_roas.len(), 1);
+        assert_eq!(detail.covering_roas.len(), 2);
+        assert!(detail.reason.is_none());
+
+        let earlier = DateTime::from_timestamp(500, 0).unwrap().naive_utc();
+        let detail = trie.validate_detailed_check_expiry(&p, 64496, Some(earlier));
+        assert_eq!(detail.state, RpkiValidation::Valid);
+        assert!(detail.expiry_skipped_roas.is_empty());
+    }
+}
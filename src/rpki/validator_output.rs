@@ -0,0 +1,547 @@
+//! Parsers for the VRP exports of other relying party software.
+//!
+//! Besides rpki-client JSON, validators commonly export their VRPs as:
+//! - Routinator `json` and `csv` (`routinator vrps -f json|csv`)
+//! - Routinator extended JSON (`-f jsonext`), which lists the source objects
+//!   of each VRP with their validity periods
+//! - Fort JSON and CSV (`--output.roa`, `--output.format`)
+//! - The `vrps` JSON served to StayRTR/GoRTR, e.g. by OctoRPKI
+//! - A plain `ASN,prefix,maxlen[,ta]` CSV
+//!
+//! The JSON exports share one shape (a `roas` array of `asn`, `prefix` and
+//! `maxLength`, with optional router keys and ASPAs) and are parsed by the
+//! same lenient reader. All CSV exports list ASN, prefix, max length and an
+//! optional trust anchor, with an optional header line; the CSV written by
+//! [`RpkiTrie::write_vrp_csv`] adds the validity period in two more columns.
+//!
+//! In both JSON and CSV, a VRP with an unparsable prefix or a max length
+//! outside the prefix length and the address length (32 or 128) is an
+//! [`BgpkitCommonsError::InvalidFormat`] error rather than being skipped, as
+//! is a JSON router key whose SKI is not 40 hex digits or whose public key is
+//! not valid base64. JSON ASPAs listed more than once for the same customer
+//! are merged into one record with the union of their providers.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::io::{BufRead, BufReader, Read};
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use ipnet::IpNet;
+use serde::Deserialize;
+use tracing::info;
+
+use super::provenance::{RpkiFileFormat, RpkiProvenance, RpkiSource};
+use super::router_key::{decode_base64, encode_base64, is_hex_ski, normalize_ski};
+use super::rpki_client::{RpkiClientData, deserialize_asn, deserialize_providers};
+use super::{Aspa, Rir, Roa, RouterKey, RpkiTrie};
+use crate::{BgpkitCommonsError, Result};
+
+#[derive(Debug, Deserialize)]
+struct ValidatorJson {
+    #[serde(default)]
+    roas: Vec<ValidatorJsonRoa>,
+    #[serde(
+        default,
+        alias = "routerKeys",
+        alias = "router-keys",
+        alias = "bgpsec_keys"
+    )]
+    router_keys: Vec<ValidatorJsonRouterKey>,
+    #[serde(default)]
+    aspas: Vec<ValidatorJsonAspa>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ValidatorJsonRoa {
+    prefix: String,
+    #[serde(rename = "maxLength", alias = "max_length", alias = "maxPrefixLength")]
+    max_length: Option<u8>,
+    #[serde(deserialize_with = "deserialize_asn")]
+    asn: u32,
+    /// Trust anchor (Routinator, vrps JSON)
+    #[serde(default)]
+    ta: Option<String>,
+    /// Expiry as a UNIX timestamp (vrps JSON)
+    #[serde(default)]
+    expires: Option<i64>,
+    /// Source objects (Routinator extended JSON)
+    #[serde(default)]
+    source: Vec<RoutinatorSource>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ValidatorJsonRouterKey {
+    #[serde(deserialize_with = "deserialize_asn")]
+    asn: u32,
+    #[serde(alias = "SKI")]
+    ski: String,
+    #[serde(alias = "routerPublicKey", alias = "spki")]
+    pubkey: String,
+    #[serde(default)]
+    ta: Option<String>,
+    #[serde(default)]
+    source: Vec<RoutinatorSource>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ValidatorJsonAspa {
+    #[serde(alias = "customer_asid", deserialize_with = "deserialize_asn")]
+    customer: u32,
+    #[serde(deserialize_with = "deserialize_providers")]
+    providers: Vec<u32>,
+    #[serde(default)]
+    source: Vec<RoutinatorSource>,
+}
+
+/// An object a Routinator extended JSON entry was derived from.
+#[derive(Debug, Deserialize)]
+struct RoutinatorSource {
+    #[serde(default)]
+    tal: Option<String>,
+    #[serde(default)]
+    validity: Option<RoutinatorValidity>,
+    /// Validity of the whole certificate chain, narrower than `validity`
+    #[serde(default, rename = "chainValidity")]
+    chain_validity: Option<RoutinatorValidity>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RoutinatorValidity {
+    #[serde(rename = "notBefore")]
+    not_before: String,
+    #[serde(rename = "notAfter")]
+    not_after: String,
+}
+
+/// Trust anchor and validity period of an entry, from its Routinator sources.
+///
+/// An entry derived from several objects is valid while any of them is, so
+/// the widest period is used. Local exceptions carry no validity.
+fn source_info(sources: &[RoutinatorSource]) -> SourceInfo {
+    let mut info = SourceInfo {
+        ta: sources.iter().find_map(|s| s.tal.clone()),
+        ..Default::default()
+    };
+    for validity in sources
+        .iter()
+        .filter_map(|s| s.chain_validity.as_ref().or(s.validity.as_ref()))
+    {
+        let (Some(not_before), Some(not_after)) = (
+            parse_rfc3339(&validity.not_before),
+            parse_rfc3339(&validity.not_after),
+        ) else {
+            continue;
+        };
+        info.not_before = Some(info.not_before.map_or(not_before, |t| t.min(not_before)));
+        info.not_after = Some(info.not_after.map_or(not_after, |t| t.max(not_after)));
+    }
+    info
+}
+
+#[derive(Debug, Default)]
+struct SourceInfo {
+    ta: Option<String>,
+    not_before: Option<NaiveDateTime>,
+    not_after: Option<NaiveDateTime>,
+}
+
+fn parse_rfc3339(value: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|dt| dt.naive_utc())
+}
+
+fn parse_asn(value: &str) -> Option<u32> {
+    let value = value.trim();
+    value
+        .strip_prefix("AS")
+        .or_else(|| value.strip_prefix("as"))
+        .unwrap_or(value)
+        .parse()
+        .ok()
+}
+
+/// Whether `max_length` lies between the prefix length and the address length.
+pub(super) fn is_valid_max_length(prefix: &IpNet, max_length: u8) -> bool {
+    (prefix.prefix_len()..=prefix.max_prefix_len()).contains(&max_length)
+}
+
+impl RpkiTrie {
+    /// Load RPKI data from a reader in the given format.
+    ///
+    /// The reader yields the decompressed file content. RPKIviews and
+    /// RPKISPOOL archives are only supported through
+    /// [`RpkiTrie::from_files`]. The provenance of the returned trie is
+    /// [`RpkiSource::Manual`], as there is no URL to reload from.
+    pub fn from_reader<R: Read>(
+        reader: R,
+        format: RpkiFileFormat,
+        date: Option<NaiveDate>,
+    ) -> Result<Self> {
+        let mut trie = RpkiTrie::new(date);
+        trie.merge_reader(reader, format)?;
+        Ok(trie)
+    }
+
+    /// Load validator output files of the given format from URLs or local paths.
+    pub(crate) fn from_validator_output_files(
+        urls: &[String],
+        format: RpkiFileFormat,
+        date: Option<NaiveDate>,
+    ) -> Result<Self> {
+        let mut trie = RpkiTrie::new(date);
+        for url in urls {
+            info!("loading {:?} VRPs from {}", format, url);
+            trie.merge_reader(oneio::get_reader(url)?, format)?;
+        }
+        trie.provenance = RpkiProvenance::new(RpkiSource::Files(format), urls.to_vec());
+        Ok(trie)
+    }
+
+    fn merge_reader<R: Read>(&mut self, reader: R, format: RpkiFileFormat) -> Result<()> {
+        match format {
+            RpkiFileFormat::RpkiClientJson => {
+                let data: RpkiClientData = serde_json::from_reader(reader)?;
+                self.merge_rpki_client_data(data);
+            }
+            RpkiFileFormat::RoutinatorJson
+            | RpkiFileFormat::RoutinatorExtendedJson
+            | RpkiFileFormat::FortJson
+            | RpkiFileFormat::VrpsJson => {
+                let data: ValidatorJson = serde_json::from_reader(reader)?;
+                self.merge_validator_json(data)?;
+            }
+            RpkiFileFormat::RoutinatorCsv | RpkiFileFormat::FortCsv | RpkiFileFormat::VrpCsv => {
+                self.merge_vrp_csv(reader)?;
+            }
+            RpkiFileFormat::RpkiViewsArchive | RpkiFileFormat::RpkiSpoolsArchive => {
+                return Err(BgpkitCommonsError::data_source_error(
+                    "RPKI",
+                    format!("{:?} can only be loaded from a URL", format),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn merge_validator_json(&mut self, data: ValidatorJson) -> Result<()> {
+        for entry in data.roas {
+            let Ok(prefix) = entry.prefix.parse::<IpNet>() else {
+                return Err(BgpkitCommonsError::invalid_format(
+                    "validator JSON",
+                    entry.prefix,
+                    "invalid prefix",
+                ));
+            };
+            let max_length = entry.max_length.unwrap_or(prefix.prefix_len());
+            if !is_valid_max_length(&prefix, max_length) {
+                return Err(BgpkitCommonsError::invalid_format(
+                    "validator JSON",
+                    entry.prefix,
+                    format!("invalid max length {}", max_length),
+                ));
+            }
+            let info = source_info(&entry.source);
+            let ta = entry.ta.or(info.ta);
+            let not_after = entry
+                .expires
+                .and_then(|ts| DateTime::from_timestamp(ts, 0))
+                .map(|dt| dt.naive_utc())
+                .or(info.not_after);
+            self.insert_roa(Roa {
+                prefix: prefix.trunc(),
+                asn: entry.asn,
+                max_length,
+                rir: ta.as_deref().and_then(|ta| Rir::from_str(ta).ok()),
+                not_before: info.not_before,
+                not_after,
+            });
+        }
+
+        // An ASPA split over several entries authorizes the union of their providers
+        let mut aspas: HashMap<u32, Aspa> = HashMap::new();
+        for entry in data.aspas {
+            let expires = source_info(&entry.source).not_after;
+            match aspas.entry(entry.customer) {
+                Entry::Occupied(mut existing) => {
+                    let aspa = existing.get_mut();
+                    aspa.providers.extend(entry.providers);
+                    aspa.providers.sort_unstable();
+                    aspa.providers.dedup();
+                    aspa.expires = aspa.expires.zip(expires).map(|(a, b)| a.max(b));
+                }
+                Entry::Vacant(vacant) => {
+                    vacant.insert(Aspa {
+                        customer_asn: entry.customer,
+                        providers: entry.providers,
+                        expires,
+                    });
+                }
+            }
+        }
+        for (customer, aspa) in aspas {
+            if self.lookup_aspa(customer).is_none() {
+                self.insert_aspa(aspa);
+            }
+        }
+
+        for entry in data.router_keys {
+            let ski = normalize_ski(&entry.ski);
+            if !is_hex_ski(&ski) {
+                return Err(BgpkitCommonsError::invalid_format(
+                    "validator JSON",
+                    entry.ski,
+                    "router key SKI is not 40 hex digits",
+                ));
+            }
+            // Routinator and Fort use URL-safe base64; store the standard alphabet
+            let Some(spki) = decode_base64(&entry.pubkey) else {
+                return Err(BgpkitCommonsError::invalid_format(
+                    "validator JSON",
+                    entry.pubkey,
+                    "invalid router public key encoding",
+                ));
+            };
+            let info = source_info(&entry.source);
+            self.insert_router_key(RouterKey {
+                asn: entry.asn,
+                ski,
+                pubkey: encode_base64(&spki),
+                ta: entry.ta.or(info.ta),
+                expires: info.not_after,
+            });
+        }
+        Ok(())
+    }
+
+    fn merge_vrp_csv<R: Read>(&mut self, reader: R) -> Result<()> {
+        let mut first_line = true;
+        for (index, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let is_first_line = std::mem::take(&mut first_line);
+            let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+            let Some(asn) = parse_asn(fields[0]) else {
+                if is_first_line {
+                    // header line
+                    continue;
+                }
+                return Err(BgpkitCommonsError::invalid_format(
+                    "VRP CSV",
+                    line,
+                    format!("line {}: invalid ASN", index + 1),
+                ));
+            };
+            let Some(prefix) = fields.get(1).and_then(|p| p.parse::<IpNet>().ok()) else {
+                return Err(BgpkitCommonsError::invalid_format(
+                    "VRP CSV",
+                    line,
+                    format!("line {}: invalid prefix", index + 1),
+                ));
+            };
+            let max_length = match fields.get(2).filter(|f| !f.is_empty()) {
+                None => prefix.prefix_len(),
+                Some(f) => f.parse::<u8>().map_err(|_| {
+                    BgpkitCommonsError::invalid_format(
+                        "VRP CSV",
+                        line,
+                        format!("line {}: invalid max length", index + 1),
+                    )
+                })?,
+            };
+            if !is_valid_max_length(&prefix, max_length) {
+                return Err(BgpkitCommonsError::invalid_format(
+                    "VRP CSV",
+                    line,
+                    format!("line {}: invalid max length", index + 1),
+                ));
+            }
+            self.insert_roa(Roa {
+                prefix: prefix.trunc(),
+                asn,
+                max_length,
+                rir: fields.get(3).and_then(|ta| Rir::from_str(ta).ok()),
+                not_before: fields.get(4).and_then(|t| parse_rfc3339(t)),
+                not_after: fields.get(5).and_then(|t| parse_rfc3339(t)),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::RpkiValidation;
+
+    fn load(input: &str, format: RpkiFileFormat) -> RpkiTrie {
+        RpkiTrie::from_reader(input.as_bytes(), format, None).unwrap()
+    }
+
+    #[test]
+    fn test_routinator_json() {
+        let trie = load(
+            r#"{
+              "metadata": {"generated": 1700000000, "generatedTime": "2023-11-14T22:13:20Z"},
+              "roas": [
+                {"asn": "AS13335", "prefix": "1.0.0.0/24", "maxLength": 24, "ta": "apnic"},
+                {"asn": "AS64496", "prefix": "2001:db8::/32", "maxLength": 48, "ta": "ripe"}
+              ],
+              "routerKeys": [
+                {"asn": "AS64496", "SKI": "abababababababababababababababababababab", "routerPublicKey": "MFkwEw", "ta": "ripe"}
+              ],
+              "aspas": [
+                {"customer": "AS64496", "providers": ["AS64510", "AS64511"], "ta": "ripe"}
+              ]
+            }"#,
+            RpkiFileFormat::RoutinatorJson,
+        );
+        let roas = trie.lookup_roas_by_asn(13335);
+        assert_eq!(roas.len(), 1);
+        assert_eq!(roas[0].rir, Some(Rir::APNIC));
+        let p: IpNet = "2001:db8:1::/48".parse().unwrap();
+        assert_eq!(trie.validate(&p, 64496), RpkiValidation::Valid);
+        assert_eq!(
+            trie.lookup_aspa(64496).unwrap().providers,
+            vec![64510, 64511]
+        );
+        let keys = trie.lookup_router_keys_by_asn(64496);
+        assert_eq!(keys[0].ski, "AB".repeat(20));
+        assert_eq!(keys[0].pubkey, "MFkwEw==");
+    }
+
+    #[test]
+    fn test_routinator_extended_json() {
+        let trie = load(
+            r#"{"roas": [{
+              "asn": "AS64496", "prefix": "192.0.2.0/24", "maxLength": 24,
+              "source": [
+                {"type": "roa", "uri": "rsync://example.net/a.roa", "tal": "arin",
+                 "validity": {"notBefore": "2024-01-01T00:00:00Z", "notAfter": "2025-01-01T00:00:00Z"},
+                 "chainValidity": {"notBefore": "2024-01-01T00:00:00Z", "notAfter": "2024-06-01T00:00:00Z"}},
+                {"type": "roa", "uri": "rsync://example.net/b.roa", "tal": "arin",
+                 "validity": {"notBefore": "2024-02-01T00:00:00Z", "notAfter": "2024-09-01T00:00:00Z"},
+                 "chainValidity": {"notBefore": "2024-02-01T00:00:00Z", "notAfter": "2024-09-01T00:00:00Z"}}
+              ]
+            }]}"#,
+            RpkiFileFormat::RoutinatorExtendedJson,
+        );
+        let roa = &trie.lookup_roas_by_asn(64496)[0];
+        assert_eq!(roa.rir, Some(Rir::ARIN));
+        assert_eq!(
+            roa.not_before,
+            parse_rfc3339("2024-01-01T00:00:00Z"),
+            "earliest start over all sources"
+        );
+        assert_eq!(
+            roa.not_after,
+            parse_rfc3339("2024-09-01T00:00:00Z"),
+            "latest chain expiry over all sources"
+        );
+    }
+
+    #[test]
+    fn test_vrps_json() {
+        // StayRTR/OctoRPKI vrps JSON, with numeric and string ASNs
+        let trie = load(
+            r#"{"metadata": {"counts": 2, "generated": 1700000000, "valid": 1700003600},
+                "roas": [
+                  {"prefix": "192.0.2.0/24", "maxLength": 24, "asn": 64496, "ta": "lacnic", "expires": 1700086400},
+                  {"prefix": "198.51.100.0/24", "maxLength": 24, "asn": "AS64497"}
+                ]}"#,
+            RpkiFileFormat::VrpsJson,
+        );
+        let roa = &trie.lookup_roas_by_asn(64496)[0];
+        assert_eq!(roa.rir, Some(Rir::LACNIC));
+        assert_eq!(
+            roa.not_after,
+            DateTime::from_timestamp(1700086400, 0).map(|dt| dt.naive_utc())
+        );
+        assert_eq!(trie.lookup_roas_by_asn(64497).len(), 1);
+    }
+
+    #[test]
+    fn test_csv_formats() {
+        let routinator = "ASN,IP Prefix,Max Length,Trust Anchor\nAS64496,192.0.2.0/24,24,afrinic\n";
+        let fort = "ASN,Prefix,Max prefix length\nAS64496,192.0.2.0/24,24\n";
+        let plain = "# exported VRPs\n64496,192.0.2.0/24,24\n64497,2001:db8::/32,\n";
+
+        let trie = load(routinator, RpkiFileFormat::RoutinatorCsv);
+        assert_eq!(trie.lookup_roas_by_asn(64496)[0].rir, Some(Rir::AFRINIC));
+        let trie = load(fort, RpkiFileFormat::FortCsv);
+        assert_eq!(trie.lookup_roas_by_asn(64496).len(), 1);
+        let trie = load(plain, RpkiFileFormat::VrpCsv);
+        assert_eq!(trie.lookup_roas_by_asn(64497)[0].max_length, 32);
+
+        let err = RpkiTrie::from_reader(
+            "64496,192.0.2.0/24,24\n64496,not-a-prefix,24\n".as_bytes(),
+            RpkiFileFormat::VrpCsv,
+            None,
+        );
+        assert!(err.is_err());
+        assert!(RpkiTrie::from_reader(&b""[..], RpkiFileFormat::RpkiViewsArchive, None).is_err());
+    }
+
+    #[test]
+    fn test_invalid_vrps() {
+        let csv =
+            |input: &str| RpkiTrie::from_reader(input.as_bytes(), RpkiFileFormat::VrpCsv, None);
+        assert!(csv("64496,192.0.2.0/24,23\n").is_err());
+        assert!(csv("64496,192.0.2.0/24,33\n").is_err());
+        assert!(csv("64496,2001:db8::/32,129\n").is_err());
+        assert!(csv("64496,2001:db8::/32,128\n").is_ok());
+
+        let json = |roa: &str| {
+            RpkiTrie::from_reader(
+                format!(r#"{{"roas": [{}]}}"#, roa).as_bytes(),
+                RpkiFileFormat::RoutinatorJson,
+                None,
+            )
+        };
+        assert!(json(r#"{"asn": 64496, "prefix": "192.0.2.0/24", "maxLength": 22}"#).is_err());
+        assert!(json(r#"{"asn": 64496, "prefix": "192.0.2.0/24", "maxLength": 33}"#).is_err());
+        assert!(json(r#"{"asn": 64496, "prefix": "not-a-prefix", "maxLength": 24}"#).is_err());
+        assert!(json(r#"{"asn": 64496, "prefix": "192.0.2.0/24"}"#).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_router_keys() {
+        let json = |ski: &str, pubkey: &str| {
+            RpkiTrie::from_reader(
+                format!(
+                    r#"{{"routerKeys": [{{"asn": 64496, "SKI": "{}", "routerPublicKey": "{}"}}]}}"#,
+                    ski, pubkey
+                )
+                .as_bytes(),
+                RpkiFileFormat::RoutinatorJson,
+                None,
+            )
+        };
+        let ski = "ab".repeat(20);
+        assert!(json(&ski, "MFkwEw").is_ok());
+        assert!(json(&ski, "not base64!").is_err());
+        assert!(json(&"ab".repeat(19), "MFkwEw").is_err());
+        assert!(json(&"zz".repeat(20), "MFkwEw").is_err());
+    }
+
+    #[test]
+    fn test_duplicate_aspas() {
+        let trie = load(
+            r#"{"aspas": [
+                {"customer": "AS64496", "providers": ["AS64510", "AS64512"]},
+                {"customer": "AS64497", "providers": ["AS64510"]},
+                {"customer": "AS64496", "providers": ["AS64511", "AS64512"]}
+            ]}"#,
+            RpkiFileFormat::RoutinatorJson,
+        );
+        assert_eq!(trie.aspas.len(), 2);
+        assert_eq!(
+            trie.lookup_aspa(64496).unwrap().providers,
+            vec![64510, 64511, 64512]
+        );
+    }
+}
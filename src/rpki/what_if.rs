@@ -0,0 +1,331 @@
+//! What-if simulation of ROA and ASPA changes.
+//!
+//! [`RpkiTrie::what_if`] returns an [`RpkiWhatIf`] view that borrows the
+//! trie and records hypothetical ROA and ASPA additions and removals on top
+//! of it, without cloning the trie. The view validates routes like the trie
+//! would after the changes, and [`RpkiWhatIf::impact`] lists the routes of a
+//! batch whose route origin validation or ASPA verification state changes.
+
+use std::collections::{HashMap, HashSet};
+
+use ipnet::IpNet;
+use ipnet_trie::IpnetTrie;
+
+use super::aspa::{hop_check, verify_path};
+use super::policy::validate_origin;
+use super::{
+    Aspa, AspaDirection, AspaHopCheck, AspaVerification, Roa, RpkiRouteStatus, RpkiTimelineRoute,
+    RpkiTrie, RpkiValidation, RpkiValidationPolicy,
+};
+
+/// A route whose state differs between the trie and a what-if view.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RpkiRouteChange {
+    /// Position of the route in the query
+    pub index: usize,
+    /// The route
+    pub route: RpkiTimelineRoute,
+    /// Status with the trie as it is
+    pub before: RpkiRouteStatus,
+    /// Status with the hypothetical changes
+    pub after: RpkiRouteStatus,
+}
+
+/// Hypothetical ROA and ASPA changes on top of an [`RpkiTrie`], see [`RpkiTrie::what_if`].
+#[derive(Clone)]
+pub struct RpkiWhatIf<'a> {
+    base: &'a RpkiTrie,
+    added_roas: IpnetTrie<Vec<Roa>>,
+    /// (prefix, asn, max_length) of ROAs of `base` that are removed
+    removed_roas: HashSet<(IpNet, u32, u8)>,
+    /// Replaced ASPAs per customer ASN; `None` means removed
+    aspas: HashMap<u32, Option<Aspa>>,
+}
+
+impl RpkiTrie {
+    /// Start a what-if view with no changes.
+    pub fn what_if(&self) -> RpkiWhatIf<'_> {
+        RpkiWhatIf {
+            base: self,
+            added_roas: IpnetTrie::new(),
+            removed_roas: HashSet::new(),
+            aspas: HashMap::new(),
+        }
+    }
+}
+
+impl<'a> RpkiWhatIf<'a> {
+    /// The trie the changes apply to.
+    pub fn base(&self) -> &'a RpkiTrie {
+        self.base
+    }
+
+    fn base_has_roa(&self, prefix: &IpNet, asn: u32, max_length: u8) -> bool {
+        self.base.trie.exact_match(*prefix).is_some_and(|roas| {
+            roas.iter()
+                .any(|roa| roa.asn == asn && roa.max_length == max_length)
+        })
+    }
+
+    /// Add a ROA. Returns false if the view already has a ROA with the same
+    /// prefix, ASN and max-length.
+    pub fn insert_roa(&mut self, roa: Roa) -> bool {
+        let key = (roa.prefix, roa.asn, roa.max_length);
+        if self.base_has_roa(&roa.prefix, roa.asn, roa.max_length) {
+            return self.removed_roas.remove(&key);
+        }
+        match self.added_roas.exact_match_mut(roa.prefix) {
+            Some(roas) if roas.iter().any(|r| r.asn == key.1 && r.max_length == key.2) => false,
+            Some(roas) => {
+                roas.push(roa);
+                true
+            }
+            None => {
+                self.added_roas.insert(roa.prefix, vec![roa]);
+                true
+            }
+        }
+    }
+
+    /// Remove a ROA identified by (prefix, asn, max_length). Returns true if it was present.
+    pub fn remove_roa(&mut self, prefix: &IpNet, asn: u32, max_length: u8) -> bool {
+        if let Some(roas) = self.added_roas.exact_match_mut(*prefix) {
+            let before = roas.len();
+            roas.retain(|roa| !(roa.asn == asn && roa.max_length == max_length));
+            if roas.len() != before {
+                if roas.is_empty() {
+                    self.added_roas.remove(*prefix);
+                }
+                return true;
+            }
+        }
+        self.base_has_roa(prefix, asn, max_length)
+            && self.removed_roas.insert((*prefix, asn, max_length))
+    }
+
+    /// Add or replace the ASPA of a customer ASN.
+    pub fn insert_aspa(&mut self, aspa: Aspa) {
+        self.aspas.insert(aspa.customer_asn, Some(aspa));
+    }
+
+    /// Remove the ASPA of a customer ASN. Returns true if it was present.
+    pub fn remove_aspa(&mut self, customer_asn: u32) -> bool {
+        let present = self.lookup_aspa(customer_asn).is_some();
+        self.aspas.insert(customer_asn, None);
+        present
+    }
+
+    /// Whether no change has been recorded.
+    pub fn is_unchanged(&self) -> bool {
+        self.added_roas.iter().next().is_none()
+            && self.removed_roas.is_empty()
+            && self.aspas.is_empty()
+    }
+
+    /// ROAs of the view whose prefix covers `prefix`.
+    fn covering_roas<'b>(&'b self, prefix: &'b IpNet) -> impl Iterator<Item = &'b Roa> {
+        let base = self
+            .base
+            .trie
+            .matches(prefix)
+            .into_iter()
+            .filter(move |(p, _)| p.contains(prefix))
+            .flat_map(|(_, roas)| roas)
+            .filter(|roa| {
+                !self
+                    .removed_roas
+                    .contains(&(roa.prefix, roa.asn, roa.max_length))
+            });
+        let added = self
+            .added_roas
+            .matches(prefix)
+            .into_iter()
+            .filter(move |(p, _)| p.contains(prefix))
+            .flat_map(|(_, roas)| roas);
+        base.chain(added)
+    }
+
+    /// Validate a prefix with an ASN as [`RpkiTrie::validate`] would after the changes.
+    pub fn validate(&self, prefix: &IpNet, asn: u32) -> RpkiValidation {
+        let policy = RpkiValidationPolicy::default();
+        validate_origin(self.covering_roas(prefix), prefix, asn, &policy, |_| {}).0
+    }
+
+    /// The ASPA of a customer ASN after the changes.
+    pub fn lookup_aspa(&self, customer_asn: u32) -> Option<&Aspa> {
+        match self.aspas.get(&customer_asn) {
+            Some(aspa) => aspa.as_ref(),
+            None => self.base.lookup_aspa(customer_asn),
+        }
+    }
+
+    /// Check whether `provider` is an authorized provider of `customer` after the changes.
+    pub fn aspa_hop_check(&self, customer: u32, provider: u32) -> AspaHopCheck {
+        hop_check(self.lookup_aspa(customer), provider)
+    }
+
+    /// Verify an AS_PATH as [`RpkiTrie::verify_aspa_path`] would after the changes.
+    pub fn verify_aspa_path(&self, as_path: &[u32], direction: AspaDirection) -> AspaVerification {
+        verify_path(as_path, direction, |customer, provider| {
+            self.aspa_hop_check(customer, provider)
+        })
+    }
+
+    fn route_status(&self, route: &RpkiTimelineRoute) -> RpkiRouteStatus {
+        RpkiRouteStatus {
+            rov: self.validate(&route.prefix, route.origin),
+            aspa: route
+                .as_path
+                .as_ref()
+                .map(|(path, direction)| self.verify_aspa_path(path, *direction).state),
+        }
+    }
+
+    /// The routes whose status changes with the recorded changes, in query order.
+    pub fn impact(&self, routes: &[RpkiTimelineRoute]) -> Vec<RpkiRouteChange> {
+        routes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, route)| {
+                let before = self.base.route_status(route);
+                let after = self.route_status(route);
+                (before != after).then(|| RpkiRouteChange {
+                    index,
+                    route: route.clone(),
+                    before,
+                    after,
+                })
+            })
+            .collect()
+    }
+
+    /// A copy of the trie with the changes applied.
+    pub fn to_trie(&self) -> RpkiTrie {
+        let mut trie = self.base.clone();
+        for (prefix, asn, max_length) in &self.removed_roas {
+            trie.remove_roa(prefix, *asn, *max_length);
+        }
+        for (_, roas) in self.added_roas.iter() {
+            trie.insert_roas(roas.clone());
+        }
+        for (customer_asn, aspa) in &self.aspas {
+            match aspa {
+                Some(aspa) => {
+                    trie.insert_aspa(aspa.clone());
+                }
+                None => {
+                    trie.remove_aspa(*customer_asn);
+                }
+            }
+        }
+        trie
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::{AspaValidation, roa};
+
+    fn route(prefix: &str, origin: u32) -> RpkiTimelineRoute {
+        RpkiTimelineRoute::new(prefix.parse().unwrap(), origin)
+    }
+
+    #[test]
+    fn test_what_if_roas() {
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_roas(vec![
+            roa("192.0.2.0/24", 64496, 24),
+            roa("198.51.100.0/24", 64497, 24),
+        ]);
+        let routes = vec![
+            route("192.0.2.0/24", 64496),
+            route("203.0.113.0/24", 64498),
+            route("203.0.113.0/25", 64498),
+            route("198.51.100.0/24", 64497),
+            route("198.51.100.0/24", 64499),
+        ];
+
+        let mut what_if = trie.what_if();
+        assert!(what_if.is_unchanged());
+        assert!(what_if.impact(&routes).is_empty());
+
+        // publishing a ROA for the /24 makes the /25 more-specific invalid
+        assert!(what_if.insert_roa(roa("203.0.113.0/24", 64498, 24)));
+        assert!(!what_if.insert_roa(roa("203.0.113.0/24", 64498, 24)));
+        assert!(!what_if.insert_roa(roa("192.0.2.0/24", 64496, 24)));
+        // moving 198.51.100.0/24 to another origin
+        assert!(what_if.remove_roa(&"198.51.100.0/24".parse().unwrap(), 64497, 24));
+        assert!(what_if.insert_roa(roa("198.51.100.0/24", 64499, 24)));
+
+        let changes = what_if.impact(&routes);
+        let states: Vec<(usize, RpkiValidation, RpkiValidation)> = changes
+            .iter()
+            .map(|c| (c.index, c.before.rov.clone(), c.after.rov.clone()))
+            .collect();
+        assert_eq!(
+            states,
+            vec![
+                (1, RpkiValidation::Unknown, RpkiValidation::Valid),
+                (2, RpkiValidation::Unknown, RpkiValidation::Invalid),
+                (3, RpkiValidation::Valid, RpkiValidation::Invalid),
+                (4, RpkiValidation::Invalid, RpkiValidation::Valid),
+            ]
+        );
+        // the trie itself is untouched
+        assert_eq!(
+            trie.validate(&"198.51.100.0/24".parse().unwrap(), 64497),
+            RpkiValidation::Valid
+        );
+
+        let applied = what_if.to_trie();
+        for change in &changes {
+            assert_eq!(
+                applied.validate(&change.route.prefix, change.route.origin),
+                change.after.rov
+            );
+        }
+
+        // undoing the changes restores the original states
+        assert!(what_if.remove_roa(&"203.0.113.0/24".parse().unwrap(), 64498, 24));
+        assert!(what_if.remove_roa(&"198.51.100.0/24".parse().unwrap(), 64499, 24));
+        assert!(what_if.insert_roa(roa("198.51.100.0/24", 64497, 24)));
+        assert!(what_if.impact(&routes).is_empty());
+    }
+
+    #[test]
+    fn test_what_if_aspas() {
+        let mut trie = RpkiTrie::new(None);
+        trie.insert_aspa(Aspa {
+            customer_asn: 64496,
+            providers: vec![64510],
+            expires: None,
+        });
+        let routes = vec![
+            route("192.0.2.0/24", 64496).with_as_path(vec![64511, 64496], AspaDirection::Upstream),
+            route("192.0.2.0/24", 64496).with_as_path(vec![64510, 64496], AspaDirection::Upstream),
+        ];
+
+        let mut what_if = trie.what_if();
+        what_if.insert_aspa(Aspa {
+            customer_asn: 64496,
+            providers: vec![64510, 64511],
+            expires: None,
+        });
+        let changes = what_if.impact(&routes);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].before.aspa, Some(AspaValidation::Invalid));
+        assert_eq!(changes[0].after.aspa, Some(AspaValidation::Valid));
+
+        assert!(what_if.remove_aspa(64496));
+        assert!(!what_if.remove_aspa(64496));
+        let changes = what_if.impact(&routes);
+        assert_eq!(changes.len(), 2);
+        assert!(
+            changes
+                .iter()
+                .all(|c| c.after.aspa == Some(AspaValidation::Unknown))
+        );
+        assert!(what_if.to_trie().lookup_aspa(64496).is_none());
+    }
+}
@@ -0,0 +1,228 @@
+//! Writers that serialize an [`RpkiTrie`] back to validator output formats.
+//!
+//! [`RpkiTrie::write_rpki_client_json`] produces rpki-client compatible JSON
+//! that all loaders of this crate read back, and [`RpkiTrie::write_vrp_csv`]
+//! a VRP CSV in the column order of Routinator's `csv` output, extended with
+//! the ROA validity period. Entries are sorted so that the same data always
+//! produces the same file.
+
+use std::io::Write;
+
+use chrono::{NaiveDateTime, SecondsFormat, Utc};
+use serde::Serialize;
+
+use super::rpki_client::{RpkiClientAspaEntry, RpkiClientBgpsecKeyEntry, RpkiClientRoaEntry};
+use super::{Aspa, Rir, Roa, RouterKey, RpkiTrie};
+use crate::Result;
+
+#[derive(Serialize)]
+struct RpkiClientJsonOutput {
+    metadata: RpkiClientJsonMetadata,
+    roas: Vec<RpkiClientRoaEntry>,
+    aspas: Vec<RpkiClientAspaEntry>,
+    bgpsec_keys: Vec<RpkiClientBgpsecKeyEntry>,
+}
+
+#[derive(Serialize)]
+struct RpkiClientJsonMetadata {
+    buildmachine: String,
+    generated: i64,
+    #[serde(rename = "generatedTime")]
+    generated_time: String,
+    vrps: usize,
+    vaps: usize,
+    bgpsec_pubkeys: usize,
+}
+
+/// The rpki-client trust anchor name of an RIR.
+fn ta_name(rir: Option<Rir>) -> &'static str {
+    match rir {
+        Some(Rir::AFRINIC) => "afrinic",
+        Some(Rir::APNIC) => "apnic",
+        Some(Rir::ARIN) => "arin",
+        Some(Rir::LACNIC) => "lacnic",
+        Some(Rir::RIPENCC) => "ripe",
+        None => "",
+    }
+}
+
+fn format_time(time: NaiveDateTime) -> String {
+    time.and_utc().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+impl RpkiTrie {
+    /// All ROAs sorted by prefix, origin ASN and max-length.
+    pub(crate) fn sorted_roas(&self) -> Vec<&Roa> {
+        let mut roas: Vec<&Roa> = self.trie.iter().flat_map(|(_, roas)| roas).collect();
+        roas.sort_by_key(|roa| (roa.prefix, roa.asn, roa.max_length));
+        roas
+    }
+
+    /// All ASPAs sorted by customer ASN.
+    pub(crate) fn sorted_aspas(&self) -> Vec<&Aspa> {
+        let mut aspas: Vec<&Aspa> = self.aspas.iter().collect();
+        aspas.sort_by_key(|aspa| aspa.customer_asn);
+        aspas
+    }
+
+    /// Write the ROAs, ASPAs and router keys as rpki-client JSON.
+    ///
+    /// Missing expiry times are written as `0`, which the loaders of this
+    /// crate read back as "no expiry".
+    pub fn write_rpki_client_json<W: Write>(&self, writer: W) -> Result<()> {
+        let now = Utc::now();
+        let roas: Vec<RpkiClientRoaEntry> = self
+            .sorted_roas()
+            .into_iter()
+            .map(|roa| RpkiClientRoaEntry {
+                prefix: roa.prefix.to_string(),
+                max_length: roa.max_length,
+                asn: roa.asn,
+                ta: ta_name(roa.rir).to_string(),
+                expires: roa
+                    .not_after
+                    .map(|t| t.and_utc().timestamp().max(0) as u64)
+                    .unwrap_or(0),
+            })
+            .collect();
+        let aspas: Vec<RpkiClientAspaEntry> = self
+            .sorted_aspas()
+            .into_iter()
+            .map(|aspa| RpkiClientAspaEntry {
+                customer_asid: aspa.customer_asn,
+                expires: aspa.expires.map(|t| t.and_utc().timestamp()).unwrap_or(0),
+                providers: aspa.providers.clone(),
+            })
+            .collect();
+        let mut keys: Vec<&RouterKey> = self.router_keys.iter().collect();
+        keys.sort_by(|a, b| (a.asn, &a.ski).cmp(&(b.asn, &b.ski)));
+        let bgpsec_keys: Vec<RpkiClientBgpsecKeyEntry> = keys
+            .into_iter()
+            .map(|key| RpkiClientBgpsecKeyEntry {
+                asn: key.asn,
+                ski: key.ski.clone(),
+                pubkey: key.pubkey.clone(),
+                ta: key.ta.clone().unwrap_or_default(),
+                expires: key.expires.map(|t| t.and_utc().timestamp()).unwrap_or(0),
+            })
+            .collect();
+
+        let output = RpkiClientJsonOutput {
+            metadata: RpkiClientJsonMetadata {
+                buildmachine: format!("bgpkit-commons {}", env!("CARGO_PKG_VERSION")),
+                generated: now.timestamp(),
+                generated_time: now.to_rfc3339_opts(SecondsFormat::Secs, true),
+                vrps: roas.len(),
+                vaps: aspas.len(),
+                bgpsec_pubkeys: bgpsec_keys.len(),
+            },
+            roas,
+            aspas,
+            bgpsec_keys,
+        };
+        serde_json::to_writer(writer, &output)?;
+        Ok(())
+    }
+
+    /// Write the ROAs as a VRP CSV.
+    ///
+    /// Columns: `ASN,IP Prefix,Max Length,Trust Anchor,Not Before,Not After`,
+    /// with times in RFC 3339 and empty fields for unknown values. The file
+    /// loads back with [`RpkiFileFormat::VrpCsv`](super::RpkiFileFormat::VrpCsv).
+    pub fn write_vrp_csv<W: Write>(&self, mut writer: W) -> Result<()> {
+        writeln!(
+            writer,
+            "ASN,IP Prefix,Max Length,Trust Anchor,Not Before,Not After"
+        )?;
+        for roa in self.sorted_roas() {
+            writeln!(
+                writer,
+                "AS{},{},{},{},{},{}",
+                roa.asn,
+                roa.prefix,
+                roa.max_length,
+                ta_name(roa.rir),
+                roa.not_before.map(format_time).unwrap_or_default(),
+                roa.not_after.map(format_time).unwrap_or_default(),
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpki::RpkiFileFormat;
+    use chrono::DateTime;
+
+    fn sample_trie() -> RpkiTrie {
+        let mut trie = RpkiTrie::new(None);
+        let expiry = DateTime::from_timestamp(1_900_000_000, 0).map(|dt| dt.naive_utc());
+        trie.insert_roas(vec![
+            Roa {
+                prefix: "198.51.100.0/24".parse().unwrap(),
+                asn: 64497,
+                max_length: 24,
+                rir: None,
+                not_before: None,
+                not_after: None,
+            },
+            Roa {
+                prefix: "192.0.2.0/24".parse().unwrap(),
+                asn: 64496,
+                max_length: 25,
+                rir: Some(Rir::RIPENCC),
+                not_before: DateTime::from_timestamp(1_700_000_000, 0).map(|dt| dt.naive_utc()),
+                not_after: expiry,
+            },
+        ]);
+        trie.insert_aspa(Aspa {
+            customer_asn: 64496,
+            providers: vec![64510, 64511],
+            expires: expiry,
+        });
+        trie.insert_router_key(RouterKey::from_bytes(64496, &[0xab; 20], &[0x30, 0x59]));
+        trie
+    }
+
+    #[test]
+    fn test_rpki_client_json_roundtrip() {
+        let trie = sample_trie();
+        let mut buf = Vec::new();
+        trie.write_rpki_client_json(&mut buf).unwrap();
+
+        let loaded =
+            RpkiTrie::from_reader(buf.as_slice(), RpkiFileFormat::RpkiClientJson, None).unwrap();
+        assert!(trie.diff(&loaded).is_empty());
+        assert_eq!(loaded.lookup_roas_by_asn(64496)[0].rir, Some(Rir::RIPENCC));
+        assert_eq!(loaded.lookup_roas_by_asn(64497)[0].not_after, None);
+        assert_eq!(loaded.router_keys, trie.router_keys);
+    }
+
+    #[test]
+    fn test_vrp_csv_roundtrip() {
+        let trie = sample_trie();
+        let mut buf = Vec::new();
+        trie.write_vrp_csv(&mut buf).unwrap();
+        let csv = String::from_utf8(buf).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("ASN,IP Prefix,Max Length,Trust Anchor,Not Before,Not After")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("AS64496,192.0.2.0/24,25,ripe,2023-11-14T22:13:20Z,2030-03-17T17:46:40Z")
+        );
+        assert_eq!(lines.next(), Some("AS64497,198.51.100.0/24,24,,,"));
+
+        let loaded = RpkiTrie::from_reader(csv.as_bytes(), RpkiFileFormat::VrpCsv, None).unwrap();
+        let original = &trie.lookup_roas_by_asn(64496)[0];
+        let roa = &loaded.lookup_roas_by_asn(64496)[0];
+        assert_eq!(roa.rir, original.rir);
+        assert_eq!(roa.not_before, original.not_before);
+        assert_eq!(roa.not_after, original.not_after);
+    }
+}